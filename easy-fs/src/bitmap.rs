@@ -0,0 +1,127 @@
+//! 记录 inode 或数据块是否被占用的位图
+
+use crate::layout::*;
+use crate::{BlockDevice, Result};
+
+/// 每个位图块能记录的个数
+const BITS_PER_BLOCK: usize = BLOCK_SIZE * 8;
+
+/// 位于连续若干块中的位图
+#[derive(Clone, Copy, Debug)]
+pub struct Bitmap {
+    /// 起始块号
+    start: usize,
+    /// 占用的块数
+    blocks: usize,
+    /// 实际可以分配的个数，可能小于位图能记录的个数
+    count: usize,
+}
+
+impl Bitmap {
+    pub fn new(start: usize, blocks: usize, count: usize) -> Self {
+        Self {
+            start,
+            blocks,
+            count,
+        }
+    }
+
+    /// 分配一个空闲位，返回其序号，没有空闲时返回 `None`
+    pub fn alloc(&self, device: &dyn BlockDevice) -> Result<Option<usize>> {
+        let mut block: Block = [0; BLOCK_SIZE];
+        for i in 0..self.blocks {
+            device.read_block(self.start + i, &mut block)?;
+            let found = block
+                .iter()
+                .enumerate()
+                .find(|(_, byte)| **byte != 0xff)
+                .map(|(index, &byte)| index * 8 + (!byte).trailing_zeros() as usize);
+            if let Some(bit) = found {
+                let index = i * BITS_PER_BLOCK + bit;
+                if index >= self.count {
+                    return Ok(None);
+                }
+                block[bit / 8] |= 1 << (bit % 8);
+                device.write_block(self.start + i, &block)?;
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 释放序号为 `index` 的位
+    pub fn dealloc(&self, device: &dyn BlockDevice, index: usize) -> Result<()> {
+        assert!(index < self.count, "bitmap index out of range");
+        let mut block: Block = [0; BLOCK_SIZE];
+        let block_id = self.start + index / BITS_PER_BLOCK;
+        let bit = index % BITS_PER_BLOCK;
+        device.read_block(block_id, &mut block)?;
+        assert!(
+            block[bit / 8] & (1 << (bit % 8)) != 0,
+            "double free in bitmap"
+        );
+        block[bit / 8] &= !(1 << (bit % 8));
+        device.write_block(block_id, &block)
+    }
+
+    /// 已经占用的个数
+    pub fn used(&self, device: &dyn BlockDevice) -> Result<usize> {
+        let mut block: Block = [0; BLOCK_SIZE];
+        let mut used = 0;
+        for i in 0..self.blocks {
+            device.read_block(self.start + i, &mut block)?;
+            used += block
+                .iter()
+                .map(|byte| byte.count_ones() as usize)
+                .sum::<usize>();
+        }
+        Ok(used)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ram_disk::RamDisk;
+
+    #[test]
+    fn alloc_in_order_until_full() {
+        let disk = RamDisk::new(4);
+        let bitmap = Bitmap::new(1, 2, BITS_PER_BLOCK + 3);
+        for index in 0..BITS_PER_BLOCK + 3 {
+            assert_eq!(bitmap.alloc(&disk).unwrap(), Some(index));
+        }
+        // 不能超过实际的个数，即使位图块中还有空位
+        assert_eq!(bitmap.alloc(&disk).unwrap(), None);
+        assert_eq!(bitmap.used(&disk).unwrap(), BITS_PER_BLOCK + 3);
+        // 位图之外的块不受影响
+        let mut block: Block = [0; BLOCK_SIZE];
+        disk.read_block(0, &mut block).unwrap();
+        assert!(block.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn dealloc_reuses_lowest() {
+        let disk = RamDisk::new(2);
+        let bitmap = Bitmap::new(1, 1, 100);
+        for _ in 0..20 {
+            bitmap.alloc(&disk).unwrap();
+        }
+        bitmap.dealloc(&disk, 13).unwrap();
+        bitmap.dealloc(&disk, 7).unwrap();
+        assert_eq!(bitmap.used(&disk).unwrap(), 18);
+        assert_eq!(bitmap.alloc(&disk).unwrap(), Some(7));
+        assert_eq!(bitmap.alloc(&disk).unwrap(), Some(13));
+        assert_eq!(bitmap.alloc(&disk).unwrap(), Some(20));
+    }
+
+    #[test]
+    #[should_panic(expected = "double free")]
+    fn double_free_panics() {
+        let disk = RamDisk::new(2);
+        let bitmap = Bitmap::new(1, 1, 100);
+        bitmap.alloc(&disk).unwrap();
+        bitmap.dealloc(&disk, 0).unwrap();
+        bitmap.dealloc(&disk, 0).unwrap();
+    }
+}
@@ -0,0 +1,588 @@
+//! 文件系统的实现 [`EasyFileSystem`]
+
+use crate::bitmap::Bitmap;
+use crate::layout::*;
+use crate::{BlockDevice, Error, Result};
+use alloc::{string::String, sync::Arc};
+use core::cmp::min;
+use core::mem::size_of;
+
+/// 根目录的 inode 编号
+const ROOT_INODE: u32 = 0;
+
+/// 文件的信息
+#[derive(Clone, Copy, Debug)]
+pub struct InodeStat {
+    pub kind: InodeKind,
+    pub size: usize,
+    pub nlinks: usize,
+    /// 占用的数据块个数，不包括索引块
+    pub blocks: usize,
+}
+
+/// 一个打开的文件系统
+///
+/// inode 用编号表示，所有操作直接读写块设备，不做缓存
+pub struct EasyFileSystem {
+    device: Arc<dyn BlockDevice>,
+    inode_bitmap: Bitmap,
+    data_bitmap: Bitmap,
+    /// inode 区域的起始块号
+    inode_area_start: usize,
+    /// 数据块区域的起始块号
+    data_area_start: usize,
+    super_block: SuperBlock,
+}
+
+impl EasyFileSystem {
+    /// 在块设备上建立一个空的文件系统，只包含根目录
+    ///
+    /// `total_blocks` 为块设备的总块数，`inode_bitmap_blocks` 决定最多的 inode 个数
+    pub fn format(
+        device: Arc<dyn BlockDevice>,
+        total_blocks: usize,
+        inode_bitmap_blocks: usize,
+    ) -> Result<Self> {
+        let inode_count = inode_bitmap_blocks * BLOCK_SIZE * 8;
+        let inode_area_blocks = inode_count / INODES_PER_BLOCK;
+        let used = 1 + inode_bitmap_blocks + inode_area_blocks;
+        if total_blocks <= used + 1 || total_blocks > u32::MAX as usize {
+            return Err(Error::InvalidImage);
+        }
+        // 每个位图块记录 4096 个数据块，按照 4097 块一组划分剩余的空间
+        let remaining = total_blocks - used;
+        let data_bitmap_blocks = (remaining + BLOCK_SIZE * 8) / (BLOCK_SIZE * 8 + 1);
+        let data_area_blocks = remaining - data_bitmap_blocks;
+        let super_block = SuperBlock {
+            magic: MAGIC,
+            total_blocks: total_blocks as u32,
+            inode_bitmap_blocks: inode_bitmap_blocks as u32,
+            inode_area_blocks: inode_area_blocks as u32,
+            data_bitmap_blocks: data_bitmap_blocks as u32,
+            data_area_blocks: data_area_blocks as u32,
+        };
+
+        // 清空超级块之外的元数据，数据块在分配时清空
+        let zero: Block = [0; BLOCK_SIZE];
+        for block_id in 1..used + data_bitmap_blocks {
+            device.write_block(block_id, &zero)?;
+        }
+        let mut block: Block = [0; BLOCK_SIZE];
+        write_struct(&mut block, 0, &super_block);
+        device.write_block(0, &block)?;
+
+        let fs = Self::from_super_block(device, super_block);
+        let root = fs.alloc_inode(InodeKind::Directory)?;
+        assert_eq!(root, ROOT_INODE);
+        Ok(fs)
+    }
+
+    /// 打开块设备上已有的文件系统
+    pub fn open(device: Arc<dyn BlockDevice>) -> Result<Self> {
+        let mut block: Block = [0; BLOCK_SIZE];
+        device.read_block(0, &mut block)?;
+        let super_block: SuperBlock = read_struct(&block, 0);
+        if !super_block.is_valid() {
+            return Err(Error::InvalidImage);
+        }
+        Ok(Self::from_super_block(device, super_block))
+    }
+
+    fn from_super_block(device: Arc<dyn BlockDevice>, super_block: SuperBlock) -> Self {
+        let inode_bitmap_blocks = super_block.inode_bitmap_blocks as usize;
+        let inode_area_blocks = super_block.inode_area_blocks as usize;
+        let data_bitmap_blocks = super_block.data_bitmap_blocks as usize;
+        let data_area_blocks = super_block.data_area_blocks as usize;
+        let inode_area_start = 1 + inode_bitmap_blocks;
+        let data_bitmap_start = inode_area_start + inode_area_blocks;
+        Self {
+            device,
+            inode_bitmap: Bitmap::new(1, inode_bitmap_blocks, inode_area_blocks * INODES_PER_BLOCK),
+            data_bitmap: Bitmap::new(data_bitmap_start, data_bitmap_blocks, data_area_blocks),
+            inode_area_start,
+            data_area_start: data_bitmap_start + data_bitmap_blocks,
+            super_block,
+        }
+    }
+
+    /// 根目录的 inode 编号
+    pub fn root_inode(&self) -> u32 {
+        ROOT_INODE
+    }
+
+    /// 总块数
+    pub fn total_blocks(&self) -> usize {
+        self.super_block.total_blocks as usize
+    }
+
+    /// 数据块的（总数，空闲数）
+    pub fn data_blocks(&self) -> Result<(usize, usize)> {
+        let total = self.super_block.data_area_blocks as usize;
+        Ok((total, total - self.data_bitmap.used(&*self.device)?))
+    }
+
+    /// 文件的信息
+    pub fn stat(&self, inode: u32) -> Result<InodeStat> {
+        let disk_inode = self.read_inode(inode)?;
+        Ok(InodeStat {
+            kind: disk_inode.kind(),
+            size: disk_inode.size as usize,
+            nlinks: disk_inode.nlinks as usize,
+            blocks: disk_inode.data_blocks(),
+        })
+    }
+
+    /// 从 `offset` 开始读取文件，返回读取的字节数
+    pub fn read_at(&self, inode: u32, offset: usize, buffer: &mut [u8]) -> Result<usize> {
+        let disk_inode = self.read_inode(inode)?;
+        self.read_data(&disk_inode, offset, buffer)
+    }
+
+    /// 从 `offset` 开始写入文件，必要时扩展文件，返回写入的字节数
+    pub fn write_at(&self, inode: u32, offset: usize, buffer: &[u8]) -> Result<usize> {
+        let mut disk_inode = self.read_inode(inode)?;
+        let end = offset + buffer.len();
+        if end > disk_inode.size as usize {
+            // 空间不足时也要写回 inode，记录已经分配的数据块
+            let result = self.resize_inode(&mut disk_inode, end);
+            self.write_inode(inode, &disk_inode)?;
+            result?;
+        }
+        self.write_data(&disk_inode, offset, buffer)
+    }
+
+    /// 将文件大小改为 `size`，扩展的部分为 0
+    pub fn resize(&self, inode: u32, size: usize) -> Result<()> {
+        let mut disk_inode = self.read_inode(inode)?;
+        let result = self.resize_inode(&mut disk_inode, size);
+        self.write_inode(inode, &disk_inode)?;
+        result
+    }
+
+    /// 在目录中查找名为 `name` 的项，返回其 inode 编号
+    pub fn lookup(&self, directory: u32, name: &str) -> Result<u32> {
+        let disk_inode = self.read_directory(directory)?;
+        self.find_entry(&disk_inode, name)?
+            .map(|(_, entry)| entry.inode)
+            .ok_or(Error::NotFound)
+    }
+
+    /// 在目录中创建名为 `name` 的文件或目录，返回其 inode 编号
+    pub fn create(&self, directory: u32, name: &str, kind: InodeKind) -> Result<u32> {
+        if name.is_empty() || name.len() > NAME_LENGTH_LIMIT || name.contains('/') {
+            return Err(Error::InvalidName);
+        }
+        let disk_inode = self.read_directory(directory)?;
+        if self.find_entry(&disk_inode, name)?.is_some() {
+            return Err(Error::Exists);
+        }
+        let inode = self.alloc_inode(kind)?;
+        let entry = DirEntry::new(name, inode);
+        if let Err(error) = self.write_at(directory, disk_inode.size as usize, entry.as_bytes()) {
+            self.free_inode(inode)?;
+            return Err(error);
+        }
+        Ok(inode)
+    }
+
+    /// 从目录中删除名为 `name` 的项并释放其 inode，目录必须为空
+    pub fn unlink(&self, directory: u32, name: &str) -> Result<()> {
+        let mut disk_inode = self.read_directory(directory)?;
+        let (index, entry) = self.find_entry(&disk_inode, name)?.ok_or(Error::NotFound)?;
+        let target = self.read_inode(entry.inode)?;
+        if target.kind() == InodeKind::Directory && target.size != 0 {
+            return Err(Error::NotEmpty);
+        }
+        self.free_inode(entry.inode)?;
+        // 用最后一项填补被删除的项
+        let count = disk_inode.size as usize / DIRENT_SIZE;
+        if index != count - 1 {
+            let last = self.read_entry(&disk_inode, count - 1)?;
+            self.write_data(&disk_inode, index * DIRENT_SIZE, last.as_bytes())?;
+        }
+        self.resize_inode(&mut disk_inode, (count - 1) * DIRENT_SIZE)?;
+        self.write_inode(directory, &disk_inode)
+    }
+
+    /// 目录中的第 `index` 项，返回名称和 inode 编号
+    pub fn readdir(&self, directory: u32, index: usize) -> Result<Option<(String, u32)>> {
+        let disk_inode = self.read_directory(directory)?;
+        if index >= disk_inode.size as usize / DIRENT_SIZE {
+            return Ok(None);
+        }
+        let entry = self.read_entry(&disk_inode, index)?;
+        Ok(Some((String::from(entry.name()), entry.inode)))
+    }
+
+    /// 读取一个块
+    fn read_block(&self, block_id: usize) -> Result<Block> {
+        let mut block: Block = [0; BLOCK_SIZE];
+        self.device.read_block(block_id, &mut block)?;
+        Ok(block)
+    }
+
+    /// inode 所在的块号和块内偏移
+    fn inode_position(&self, inode: u32) -> (usize, usize) {
+        let inode = inode as usize;
+        (
+            self.inode_area_start + inode / INODES_PER_BLOCK,
+            (inode % INODES_PER_BLOCK) * INODE_SIZE,
+        )
+    }
+
+    fn read_inode(&self, inode: u32) -> Result<DiskInode> {
+        let (block_id, offset) = self.inode_position(inode);
+        Ok(read_struct(&self.read_block(block_id)?, offset))
+    }
+
+    fn write_inode(&self, inode: u32, disk_inode: &DiskInode) -> Result<()> {
+        let (block_id, offset) = self.inode_position(inode);
+        let mut block = self.read_block(block_id)?;
+        write_struct(&mut block, offset, disk_inode);
+        self.device.write_block(block_id, &block)
+    }
+
+    /// 读取 inode 并确认是目录
+    fn read_directory(&self, inode: u32) -> Result<DiskInode> {
+        let disk_inode = self.read_inode(inode)?;
+        if disk_inode.kind() != InodeKind::Directory {
+            return Err(Error::NotDir);
+        }
+        Ok(disk_inode)
+    }
+
+    fn alloc_inode(&self, kind: InodeKind) -> Result<u32> {
+        let inode = self
+            .inode_bitmap
+            .alloc(&*self.device)?
+            .ok_or(Error::NoSpace)? as u32;
+        self.write_inode(inode, &DiskInode::new(kind))?;
+        Ok(inode)
+    }
+
+    /// 释放 inode 及其所有数据块
+    fn free_inode(&self, inode: u32) -> Result<()> {
+        let mut disk_inode = self.read_inode(inode)?;
+        self.resize_inode(&mut disk_inode, 0)?;
+        self.inode_bitmap.dealloc(&*self.device, inode as usize)
+    }
+
+    /// 分配一个清零的数据块，返回块号
+    fn alloc_block(&self) -> Result<u32> {
+        let index = self
+            .data_bitmap
+            .alloc(&*self.device)?
+            .ok_or(Error::NoSpace)?;
+        let block_id = self.data_area_start + index;
+        self.device.write_block(block_id, &[0; BLOCK_SIZE])?;
+        Ok(block_id as u32)
+    }
+
+    fn free_block(&self, block_id: u32) -> Result<()> {
+        self.data_bitmap
+            .dealloc(&*self.device, block_id as usize - self.data_area_start)
+    }
+
+    /// 读取索引块中的第 `index` 项
+    fn read_index(&self, block_id: u32, index: usize) -> Result<u32> {
+        Ok(read_struct(&self.read_block(block_id as usize)?, index * 4))
+    }
+
+    /// 修改索引块中的第 `index` 项
+    fn write_index(&self, block_id: u32, index: usize, value: u32) -> Result<()> {
+        let mut block = self.read_block(block_id as usize)?;
+        write_struct(&mut block, index * 4, &value);
+        self.device.write_block(block_id as usize, &block)
+    }
+
+    /// 文件中第 `index` 个数据块的块号
+    fn block_id(&self, disk_inode: &DiskInode, index: usize) -> Result<u32> {
+        if index < DIRECT_COUNT {
+            return Ok(disk_inode.direct[index]);
+        }
+        let index = index - DIRECT_COUNT;
+        if index < INDIRECT_COUNT {
+            return self.read_index(disk_inode.indirect1, index);
+        }
+        let index = index - INDIRECT_COUNT;
+        let table = self.read_index(disk_inode.indirect2, index / INDIRECT_COUNT)?;
+        self.read_index(table, index % INDIRECT_COUNT)
+    }
+
+    /// 依次分配 `blocks.len()` 个数据块，失败时释放已经分配的部分
+    fn alloc_blocks(&self, blocks: &mut [u32]) -> Result<()> {
+        for i in 0..blocks.len() {
+            match self.alloc_block() {
+                Ok(block_id) => blocks[i] = block_id,
+                Err(error) => {
+                    for &block_id in &blocks[..i] {
+                        self.free_block(block_id)?;
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 为文件中第 `index` 个数据块分配块，必要时分配索引块
+    ///
+    /// 数据块和索引块一次分配完毕，空间不足时不会占用任何块
+    fn append_block(&self, disk_inode: &mut DiskInode, index: usize) -> Result<()> {
+        // 依次为数据块、一级或二级间接索引块、二级索引中的一张索引表
+        let mut blocks = [0u32; 3];
+        let count = if index < DIRECT_COUNT {
+            1
+        } else if index < DIRECT_COUNT + INDIRECT_COUNT {
+            1 + (index == DIRECT_COUNT) as usize
+        } else {
+            let index = index - DIRECT_COUNT - INDIRECT_COUNT;
+            1 + (index == 0) as usize + (index % INDIRECT_COUNT == 0) as usize
+        };
+        self.alloc_blocks(&mut blocks[..count])?;
+        let mut blocks = blocks[..count].iter().copied();
+        let block_id = blocks.next().unwrap();
+        if index < DIRECT_COUNT {
+            disk_inode.direct[index] = block_id;
+            return Ok(());
+        }
+        let index = index - DIRECT_COUNT;
+        if index < INDIRECT_COUNT {
+            if index == 0 {
+                disk_inode.indirect1 = blocks.next().unwrap();
+            }
+            return self.write_index(disk_inode.indirect1, index, block_id);
+        }
+        let index = index - INDIRECT_COUNT;
+        if index == 0 {
+            disk_inode.indirect2 = blocks.next().unwrap();
+        }
+        if index % INDIRECT_COUNT == 0 {
+            let table = blocks.next().unwrap();
+            self.write_index(disk_inode.indirect2, index / INDIRECT_COUNT, table)?;
+        }
+        let table = self.read_index(disk_inode.indirect2, index / INDIRECT_COUNT)?;
+        self.write_index(table, index % INDIRECT_COUNT, block_id)
+    }
+
+    /// 释放文件中第 `index` 个数据块，它必须是最后一个，不再需要的索引块一并释放
+    fn remove_block(&self, disk_inode: &mut DiskInode, index: usize) -> Result<()> {
+        self.free_block(self.block_id(disk_inode, index)?)?;
+        if index < DIRECT_COUNT {
+            disk_inode.direct[index] = 0;
+            return Ok(());
+        }
+        let index = index - DIRECT_COUNT;
+        if index < INDIRECT_COUNT {
+            if index == 0 {
+                self.free_block(disk_inode.indirect1)?;
+                disk_inode.indirect1 = 0;
+            }
+            return Ok(());
+        }
+        let index = index - INDIRECT_COUNT;
+        if index % INDIRECT_COUNT == 0 {
+            self.free_block(self.read_index(disk_inode.indirect2, index / INDIRECT_COUNT)?)?;
+        }
+        if index == 0 {
+            self.free_block(disk_inode.indirect2)?;
+            disk_inode.indirect2 = 0;
+        }
+        Ok(())
+    }
+
+    /// 将 inode 的大小改为 `size`，分配或释放数据块，由调用者写回 inode
+    fn resize_inode(&self, disk_inode: &mut DiskInode, size: usize) -> Result<()> {
+        let old_blocks = disk_inode.data_blocks();
+        let new_blocks = DiskInode::blocks_for(size);
+        if new_blocks > MAX_FILE_BLOCKS || size > u32::MAX as usize {
+            return Err(Error::FileTooLarge);
+        }
+        if size < disk_inode.size as usize && size % BLOCK_SIZE != 0 {
+            // 缩小后最后一块中超出的部分需要清零，以免再次扩展时读到旧的数据
+            let block_id = self.block_id(disk_inode, new_blocks - 1)? as usize;
+            let mut block = self.read_block(block_id)?;
+            for byte in block[size % BLOCK_SIZE..].iter_mut() {
+                *byte = 0;
+            }
+            self.device.write_block(block_id, &block)?;
+        }
+        for index in old_blocks..new_blocks {
+            if let Err(error) = self.append_block(disk_inode, index) {
+                // 空间不足时保留已经分配的部分
+                disk_inode.size = min(size, index * BLOCK_SIZE) as u32;
+                return Err(error);
+            }
+        }
+        for index in (new_blocks..old_blocks).rev() {
+            self.remove_block(disk_inode, index)?;
+        }
+        disk_inode.size = size as u32;
+        Ok(())
+    }
+
+    /// 读取文件的数据，不超过文件末尾
+    fn read_data(&self, disk_inode: &DiskInode, offset: usize, buffer: &mut [u8]) -> Result<usize> {
+        let end = min(offset + buffer.len(), disk_inode.size as usize);
+        let mut position = offset;
+        while position < end {
+            let block_offset = position % BLOCK_SIZE;
+            let len = min(BLOCK_SIZE - block_offset, end - position);
+            let block_id = self.block_id(disk_inode, position / BLOCK_SIZE)?;
+            let block = self.read_block(block_id as usize)?;
+            buffer[position - offset..position - offset + len]
+                .copy_from_slice(&block[block_offset..block_offset + len]);
+            position += len;
+        }
+        Ok(end.saturating_sub(offset))
+    }
+
+    /// 写入文件的数据，调用者需要保证文件足够大
+    fn write_data(&self, disk_inode: &DiskInode, offset: usize, buffer: &[u8]) -> Result<usize> {
+        let end = offset + buffer.len();
+        let mut position = offset;
+        while position < end {
+            let block_offset = position % BLOCK_SIZE;
+            let len = min(BLOCK_SIZE - block_offset, end - position);
+            let block_id = self.block_id(disk_inode, position / BLOCK_SIZE)? as usize;
+            // 整块写入时不需要先读出
+            let mut block = if len == BLOCK_SIZE {
+                [0; BLOCK_SIZE]
+            } else {
+                self.read_block(block_id)?
+            };
+            block[block_offset..block_offset + len]
+                .copy_from_slice(&buffer[position - offset..position - offset + len]);
+            self.device.write_block(block_id, &block)?;
+            position += len;
+        }
+        Ok(buffer.len())
+    }
+
+    /// 目录中的第 `index` 项
+    fn read_entry(&self, disk_inode: &DiskInode, index: usize) -> Result<DirEntry> {
+        let mut bytes = [0u8; DIRENT_SIZE];
+        self.read_data(disk_inode, index * DIRENT_SIZE, &mut bytes)?;
+        Ok(unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const DirEntry) })
+    }
+
+    /// 在目录中查找名为 `name` 的项，返回序号和目录项
+    fn find_entry(&self, disk_inode: &DiskInode, name: &str) -> Result<Option<(usize, DirEntry)>> {
+        let count = disk_inode.size as usize / DIRENT_SIZE;
+        for index in 0..count {
+            let entry = self.read_entry(disk_inode, index)?;
+            if entry.name() == name {
+                return Ok(Some((index, entry)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// 磁盘上的结构大小必须与布局一致
+const _: [(); INODE_SIZE] = [(); size_of::<DiskInode>()];
+const _: [(); DIRENT_SIZE] = [(); size_of::<DirEntry>()];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ram_disk::RamDisk;
+    use std::vec;
+    use std::vec::Vec;
+
+    /// 在 `blocks` 块的内存盘上建立文件系统，inode 相关的元数据占用前 1026 块
+    fn format(blocks: usize) -> EasyFileSystem {
+        EasyFileSystem::format(Arc::new(RamDisk::new(blocks)), blocks, 1).unwrap()
+    }
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn create_lookup_unlink() {
+        let device = Arc::new(RamDisk::new(2048));
+        let fs = EasyFileSystem::format(device.clone(), 2048, 1).unwrap();
+        let root = fs.root_inode();
+        let dir = fs.create(root, "dir", InodeKind::Directory).unwrap();
+        assert_eq!(fs.create(root, "dir", InodeKind::File), Err(Error::Exists));
+        assert_eq!(fs.create(root, "a/b", InodeKind::File), Err(Error::InvalidName));
+        let file = fs.create(dir, "file", InodeKind::File).unwrap();
+        assert_eq!(fs.write_at(file, 0, b"hello").unwrap(), 5);
+
+        // 重新打开后内容不变
+        let fs = EasyFileSystem::open(device).unwrap();
+        assert_eq!(fs.lookup(root, "dir"), Ok(dir));
+        assert_eq!(fs.readdir(dir, 0).unwrap(), Some((String::from("file"), file)));
+        assert_eq!(fs.readdir(dir, 1).unwrap(), None);
+        let mut buffer = [0u8; 8];
+        assert_eq!(fs.read_at(file, 0, &mut buffer).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"hello");
+        assert_eq!(fs.lookup(file, "x"), Err(Error::NotDir));
+
+        assert_eq!(fs.unlink(root, "dir"), Err(Error::NotEmpty));
+        fs.unlink(dir, "file").unwrap();
+        fs.unlink(root, "dir").unwrap();
+        assert_eq!(fs.lookup(root, "dir"), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn double_indirect() {
+        let fs = format(2048);
+        let file = fs.create(fs.root_inode(), "file", InodeKind::File).unwrap();
+        let (_, free) = fs.data_blocks().unwrap();
+        // 二级间接索引中用到两张索引表
+        let blocks = DIRECT_COUNT + INDIRECT_COUNT + INDIRECT_COUNT + 10;
+        let data = pattern(blocks * BLOCK_SIZE - 100);
+        assert_eq!(fs.write_at(file, 0, &data).unwrap(), data.len());
+        let stat = fs.stat(file).unwrap();
+        assert_eq!((stat.size, stat.blocks), (data.len(), blocks));
+        // 数据块之外还有一个一级、一个二级间接索引块和两张索引表
+        assert_eq!(fs.data_blocks().unwrap().1, free - blocks - 4);
+        let mut buffer = vec![0u8; data.len()];
+        assert_eq!(fs.read_at(file, 0, &mut buffer).unwrap(), data.len());
+        assert!(buffer == data);
+
+        // 缩小到直接索引范围内，再扩展时超出的部分为 0
+        fs.resize(file, 1000).unwrap();
+        assert_eq!(fs.data_blocks().unwrap().1, free - 2);
+        fs.resize(file, 2000).unwrap();
+        let mut buffer = vec![0xffu8; 2000];
+        fs.read_at(file, 0, &mut buffer).unwrap();
+        assert!(buffer[..1000] == data[..1000]);
+        assert!(buffer[1000..].iter().all(|&byte| byte == 0));
+
+        fs.resize(file, 0).unwrap();
+        assert_eq!(fs.data_blocks().unwrap().1, free);
+    }
+
+    #[test]
+    fn fill_until_no_space() {
+        let fs = format(1400);
+        let root = fs.root_inode();
+        let (_, free) = fs.data_blocks().unwrap();
+        let file = fs.create(root, "file", InodeKind::File).unwrap();
+        let chunk = pattern(BLOCK_SIZE * 7);
+        let mut offset = 0;
+        let error = loop {
+            match fs.write_at(file, offset, &chunk) {
+                Ok(len) => offset += len,
+                Err(error) => break error,
+            }
+        };
+        assert_eq!(error, Error::NoSpace);
+        assert_eq!(fs.data_blocks().unwrap().1, 0);
+        // 已经分配的数据块都记录在文件中
+        let stat = fs.stat(file).unwrap();
+        assert!(stat.size >= offset && stat.blocks > DIRECT_COUNT + INDIRECT_COUNT);
+        // 目录块中还有空位，可以创建文件，但不能写入
+        let other = fs.create(root, "other", InodeKind::File).unwrap();
+        assert_eq!(fs.write_at(other, 0, b"x"), Err(Error::NoSpace));
+        assert_eq!(fs.stat(other).unwrap().size, 0);
+
+        // 删除后所有数据块都被释放
+        fs.unlink(root, "file").unwrap();
+        fs.unlink(root, "other").unwrap();
+        assert_eq!(fs.data_blocks().unwrap().1, free);
+    }
+}
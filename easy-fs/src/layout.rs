@@ -0,0 +1,207 @@
+//! 磁盘上的数据结构
+
+/// 块大小
+pub const BLOCK_SIZE: usize = 512;
+
+/// 超级块中的魔数
+pub const MAGIC: u32 = 0x3b80_0001;
+
+/// 每个 inode 的大小
+pub const INODE_SIZE: usize = 128;
+
+/// 每块中的 inode 个数
+pub const INODES_PER_BLOCK: usize = BLOCK_SIZE / INODE_SIZE;
+
+/// 直接索引的个数
+pub const DIRECT_COUNT: usize = 27;
+
+/// 每个索引块中的索引个数
+pub const INDIRECT_COUNT: usize = BLOCK_SIZE / 4;
+
+/// 单个文件最多的数据块个数
+pub const MAX_FILE_BLOCKS: usize = DIRECT_COUNT + INDIRECT_COUNT + INDIRECT_COUNT * INDIRECT_COUNT;
+
+/// 文件名的最大长度
+pub const NAME_LENGTH_LIMIT: usize = 27;
+
+/// 每个目录项的大小
+pub const DIRENT_SIZE: usize = 32;
+
+/// 一个块的数据
+pub type Block = [u8; BLOCK_SIZE];
+
+/// 超级块，位于第 0 块，记录各区域的大小
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SuperBlock {
+    pub magic: u32,
+    pub total_blocks: u32,
+    pub inode_bitmap_blocks: u32,
+    pub inode_area_blocks: u32,
+    pub data_bitmap_blocks: u32,
+    pub data_area_blocks: u32,
+}
+
+impl SuperBlock {
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+            && 1 + self.inode_bitmap_blocks as u64
+                + self.inode_area_blocks as u64
+                + self.data_bitmap_blocks as u64
+                + self.data_area_blocks as u64
+                <= self.total_blocks as u64
+    }
+}
+
+/// inode 的类型
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InodeKind {
+    File,
+    Directory,
+}
+
+/// 磁盘上 `kind` 字段的取值
+pub const KIND_FILE: u32 = 1;
+pub const KIND_DIRECTORY: u32 = 2;
+
+/// 磁盘上的 inode
+///
+/// 数据块编号为 0 表示尚未分配（第 0 块是超级块，不会作为数据块）
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct DiskInode {
+    pub size: u32,
+    pub kind: u32,
+    pub nlinks: u32,
+    pub direct: [u32; DIRECT_COUNT],
+    pub indirect1: u32,
+    pub indirect2: u32,
+}
+
+impl DiskInode {
+    pub fn new(kind: InodeKind) -> Self {
+        Self {
+            size: 0,
+            kind: match kind {
+                InodeKind::File => KIND_FILE,
+                InodeKind::Directory => KIND_DIRECTORY,
+            },
+            nlinks: 1,
+            direct: [0; DIRECT_COUNT],
+            indirect1: 0,
+            indirect2: 0,
+        }
+    }
+
+    pub fn kind(&self) -> InodeKind {
+        if self.kind == KIND_DIRECTORY {
+            InodeKind::Directory
+        } else {
+            InodeKind::File
+        }
+    }
+
+    /// 存放 `size` 字节需要的数据块个数
+    pub fn blocks_for(size: usize) -> usize {
+        (size + BLOCK_SIZE - 1) / BLOCK_SIZE
+    }
+
+    /// 目前的数据块个数
+    pub fn data_blocks(&self) -> usize {
+        Self::blocks_for(self.size as usize)
+    }
+}
+
+/// 目录项，名称以 '\0' 结尾
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DirEntry {
+    pub name: [u8; NAME_LENGTH_LIMIT + 1],
+    pub inode: u32,
+}
+
+impl DirEntry {
+    pub fn new(name: &str, inode: u32) -> Self {
+        let mut bytes = [0u8; NAME_LENGTH_LIMIT + 1];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Self { name: bytes, inode }
+    }
+
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self as *const Self as *const u8, DIRENT_SIZE) }
+    }
+}
+
+/// 从块中 `offset` 处读出一个结构
+pub fn read_struct<T: Copy>(block: &Block, offset: usize) -> T {
+    assert!(offset + core::mem::size_of::<T>() <= BLOCK_SIZE);
+    unsafe { core::ptr::read_unaligned(block.as_ptr().add(offset) as *const T) }
+}
+
+/// 将一个结构写入块中 `offset` 处
+pub fn write_struct<T: Copy>(block: &mut Block, offset: usize, value: &T) {
+    assert!(offset + core::mem::size_of::<T>() <= BLOCK_SIZE);
+    unsafe { core::ptr::write_unaligned(block.as_mut_ptr().add(offset) as *mut T, *value) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_for_rounds_up() {
+        assert_eq!(DiskInode::blocks_for(0), 0);
+        assert_eq!(DiskInode::blocks_for(1), 1);
+        assert_eq!(DiskInode::blocks_for(BLOCK_SIZE), 1);
+        assert_eq!(DiskInode::blocks_for(BLOCK_SIZE + 1), 2);
+    }
+
+    #[test]
+    fn dir_entry_name() {
+        assert_eq!(DirEntry::new("initproc", 3).name(), "initproc");
+        let name = "a".repeat(NAME_LENGTH_LIMIT);
+        let entry = DirEntry::new(&name, 3);
+        assert_eq!((entry.name(), entry.inode), (name.as_str(), 3));
+        assert_eq!(entry.as_bytes().len(), DIRENT_SIZE);
+    }
+
+    #[test]
+    fn struct_round_trip() {
+        let mut block: Block = [0; BLOCK_SIZE];
+        let mut inode = DiskInode::new(InodeKind::Directory);
+        inode.size = 1234;
+        inode.indirect2 = 42;
+        write_struct(&mut block, INODE_SIZE, &inode);
+        let read: DiskInode = read_struct(&block, INODE_SIZE);
+        assert_eq!(read.kind(), InodeKind::Directory);
+        assert_eq!((read.size, read.nlinks, read.indirect2), (1234, 1, 42));
+        assert!(block[..INODE_SIZE].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn super_block_validity() {
+        let mut super_block = SuperBlock {
+            magic: MAGIC,
+            total_blocks: 100,
+            inode_bitmap_blocks: 1,
+            inode_area_blocks: 10,
+            data_bitmap_blocks: 1,
+            data_area_blocks: 87,
+        };
+        assert!(super_block.is_valid());
+        super_block.data_area_blocks = 88;
+        assert!(!super_block.is_valid());
+        super_block.data_area_blocks = 87;
+        super_block.magic = 0;
+        assert!(!super_block.is_valid());
+    }
+}
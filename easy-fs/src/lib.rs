@@ -0,0 +1,62 @@
+//! 一个简单的文件系统（SFS）
+//!
+//! 磁盘按 512 字节的块划分，依次为：
+//! - 超级块 [`SuperBlock`]
+//! - inode 位图和 inode 区域，每个 [`DiskInode`] 128 字节
+//! - 数据块位图和数据块区域
+//!
+//! 文件通过直接、一级间接和二级间接索引找到数据块；目录的内容是若干 [`DirEntry`]。
+//! `.` 和 `..` 不存放在磁盘上，由使用者按路径处理
+//!
+//! 本身不使用 `std`，也不加锁，内核和主机上的打包工具 `mkfs` 都直接使用这里的实现。
+//! 并发访问时由使用者为 [`EasyFileSystem`] 加锁
+
+#![no_std]
+// 内核使用的 nightly 版本中还没有 `div_ceil` 和 `is_multiple_of`
+#![allow(unknown_lints, clippy::manual_div_ceil, clippy::manual_is_multiple_of)]
+
+extern crate alloc;
+#[cfg(test)]
+extern crate std;
+
+mod bitmap;
+mod efs;
+mod layout;
+#[cfg(test)]
+mod ram_disk;
+
+pub use efs::{EasyFileSystem, InodeStat};
+pub use layout::{InodeKind, BLOCK_SIZE, NAME_LENGTH_LIMIT};
+
+/// 文件系统操作中的错误
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// 块设备读写失败
+    Io,
+    /// 不是合法的文件系统镜像
+    InvalidImage,
+    /// 没有空闲的 inode 或数据块
+    NoSpace,
+    /// 超过单个文件的最大长度
+    FileTooLarge,
+    /// 目录中没有这一项
+    NotFound,
+    /// 目录中已经存在这一项
+    Exists,
+    /// 不是目录
+    NotDir,
+    /// 目录不为空
+    NotEmpty,
+    /// 名称为空、过长或包含 `/`
+    InvalidName,
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// 存放文件系统的块设备，块大小为 [`BLOCK_SIZE`]
+pub trait BlockDevice: Send + Sync {
+    /// 读取第 `block_id` 块
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> Result<()>;
+    /// 写入第 `block_id` 块
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> Result<()>;
+}
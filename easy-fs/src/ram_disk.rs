@@ -0,0 +1,30 @@
+//! 测试使用的内存块设备
+
+use crate::{BlockDevice, Error, Result, BLOCK_SIZE};
+use std::sync::Mutex;
+use std::vec;
+use std::vec::Vec;
+
+/// 全部数据放在内存中的块设备
+pub struct RamDisk(Mutex<Vec<[u8; BLOCK_SIZE]>>);
+
+impl RamDisk {
+    /// 全部清零的 `blocks` 块
+    pub fn new(blocks: usize) -> Self {
+        Self(Mutex::new(vec![[0; BLOCK_SIZE]; blocks]))
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> Result<()> {
+        let blocks = self.0.lock().unwrap();
+        buffer.copy_from_slice(blocks.get(block_id).ok_or(Error::Io)?);
+        Ok(())
+    }
+
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> Result<()> {
+        let mut blocks = self.0.lock().unwrap();
+        blocks.get_mut(block_id).ok_or(Error::Io)?.copy_from_slice(buffer);
+        Ok(())
+    }
+}
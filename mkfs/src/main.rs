@@ -0,0 +1,128 @@
+//! 在主机上生成 SFS 磁盘镜像
+//!
+//! 用法：`mkfs -s <源文件目录> -t <程序目录> -o <镜像> [-n <大小，单位 MiB>]`
+//!
+//! 对源文件目录中的每个 `*.rs`，将程序目录中编译出的同名 ELF 文件放入根目录，
+//! 与 `os/build.rs` 选择嵌入程序的方式相同
+
+use easy_fs::{BlockDevice, EasyFileSystem, Error, InodeKind, BLOCK_SIZE};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+
+/// 默认的镜像大小（MiB）
+const DEFAULT_SIZE: usize = 16;
+
+/// 作为块设备的镜像文件
+struct ImageFile(Mutex<File>);
+
+impl BlockDevice for ImageFile {
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> easy_fs::Result<()> {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SIZE) as u64))
+            .and_then(|_| file.read_exact(&mut buffer[..BLOCK_SIZE]))
+            .map_err(|_| Error::Io)
+    }
+
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> easy_fs::Result<()> {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SIZE) as u64))
+            .and_then(|_| file.write_all(&buffer[..BLOCK_SIZE]))
+            .map_err(|_| Error::Io)
+    }
+}
+
+/// 命令行参数
+struct Options {
+    source: PathBuf,
+    target: PathBuf,
+    output: PathBuf,
+    size: usize,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: mkfs -s <source dir> -t <binary dir> -o <image> [-n <size in MiB>]");
+    exit(1)
+}
+
+fn parse_options() -> Options {
+    let mut source = None;
+    let mut target = None;
+    let mut output = None;
+    let mut size = DEFAULT_SIZE;
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match flag.as_str() {
+            "-s" => source = Some(PathBuf::from(value)),
+            "-t" => target = Some(PathBuf::from(value)),
+            "-o" => output = Some(PathBuf::from(value)),
+            "-n" => size = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    match (source, target, output) {
+        (Some(source), Some(target), Some(output)) => Options {
+            source,
+            target,
+            output,
+            size,
+        },
+        _ => usage(),
+    }
+}
+
+/// 源文件目录中所有程序的名称
+fn program_names(source: &PathBuf) -> std::io::Result<Vec<String>> {
+    let mut names: Vec<String> = fs::read_dir(source)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            Some(name.strip_suffix(".rs")?.to_string())
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+fn main() {
+    let options = parse_options();
+    let total_blocks = options.size * 1024 * 1024 / BLOCK_SIZE;
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&options.output)
+        .expect("failed to create image");
+    file.set_len((total_blocks * BLOCK_SIZE) as u64)
+        .expect("failed to resize image");
+    let device = Arc::new(ImageFile(Mutex::new(file)));
+    let fs = EasyFileSystem::format(device, total_blocks, 1).expect("failed to format image");
+    let root = fs.root_inode();
+
+    let names = program_names(&options.source).expect("failed to read source directory");
+    for name in names.iter() {
+        let path = options.target.join(name);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(_) => {
+                eprintln!("mkfs: {} is not built, skipped", path.display());
+                continue;
+            }
+        };
+        let inode = fs
+            .create(root, name, InodeKind::File)
+            .unwrap_or_else(|error| panic!("failed to create {}: {:?}", name, error));
+        fs.write_at(inode, 0, &data)
+            .unwrap_or_else(|error| panic!("failed to write {}: {:?}", name, error));
+        println!("mkfs: {} ({} bytes)", name, data.len());
+    }
+
+    let (total, free) = fs.data_blocks().expect("failed to read bitmap");
+    println!("mkfs: {} data blocks, {} free", total, free);
+}
@@ -0,0 +1,111 @@
+//! 将 user 中编译出的用户程序和 initramfs 归档嵌入内核镜像
+//!
+//! 生成 `link_app.S`，由 `process::loader` 通过 `global_asm!` 引入。
+//! 只会嵌入已经编译出的程序，因此单独编译内核时也不会出错。
+//!
+//! 生成 `initramfs.S`，由 `fs::initramfs` 引入。环境变量 `INITRAMFS` 给出 cpio 归档的路径
+//! （相对路径以 os 目录为起点），没有指定时归档为空
+
+use std::env;
+use std::fs::{read_dir, File};
+use std::io::{Result, Write};
+use std::path::Path;
+
+/// 用户程序源文件所在目录
+static SOURCE_PATH: &str = "../user/src/bin";
+/// 用户程序编译结果所在目录
+static TARGET_PATH: &str = "../user/target/riscv64imac-unknown-none-elf/release";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SOURCE_PATH);
+    println!("cargo:rerun-if-changed={}", TARGET_PATH);
+    insert_app_data().unwrap();
+    insert_initramfs().unwrap();
+}
+
+fn insert_initramfs() -> Result<()> {
+    println!("cargo:rerun-if-env-changed=INITRAMFS");
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let mut f = File::create(Path::new(&env::var("OUT_DIR").unwrap()).join("initramfs.S"))?;
+    writeln!(
+        f,
+        r#"
+    .section .data
+    .global _initramfs_start
+    .global _initramfs_end
+    .align 3
+_initramfs_start:"#
+    )?;
+    if let Some(archive) = env::var("INITRAMFS").ok().filter(|path| !path.is_empty()) {
+        let path = Path::new(&manifest_dir).join(archive);
+        println!("cargo:rerun-if-changed={}", path.display());
+        writeln!(f, "    .incbin \"{}\"", path.display())?;
+    }
+    writeln!(f, "_initramfs_end:")?;
+    Ok(())
+}
+
+fn insert_app_data() -> Result<()> {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let target_dir = Path::new(&manifest_dir).join(TARGET_PATH);
+
+    // 找到所有已经编译出的用户程序
+    let mut apps: Vec<String> = match read_dir(Path::new(&manifest_dir).join(SOURCE_PATH)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                Some(name.strip_suffix(".rs")?.to_string())
+            })
+            .filter(|app| target_dir.join(app).exists())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    apps.sort();
+
+    let mut f = File::create(Path::new(&env::var("OUT_DIR").unwrap()).join("link_app.S"))?;
+    // 程序个数，以及每个程序的起止地址
+    writeln!(
+        f,
+        r#"
+    .section .data
+    .align 3
+    .global _app_count
+_app_count:
+    .quad {}"#,
+        apps.len()
+    )?;
+    for i in 0..apps.len() {
+        writeln!(f, "    .quad app_{}_start", i)?;
+        writeln!(f, "    .quad app_{}_end", i)?;
+    }
+    // 程序名称，依次以 '\0' 结尾
+    writeln!(
+        f,
+        r#"
+    .global _app_names
+_app_names:"#
+    )?;
+    for app in apps.iter() {
+        writeln!(f, "    .string \"{}\"", app)?;
+    }
+    // 程序的 ELF 数据
+    for (i, app) in apps.iter().enumerate() {
+        let path = target_dir.join(app);
+        println!("cargo:rerun-if-changed={}", path.display());
+        writeln!(
+            f,
+            r#"
+    .section .data
+    .global app_{0}_start
+    .global app_{0}_end
+    .align 3
+app_{0}_start:
+    .incbin "{1}"
+app_{0}_end:"#,
+            i,
+            path.display()
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+#!/bin/sh
+# cargo run / cargo test 使用的 runner：写入符号表，将内核 ELF 转换为二进制文件并在 QEMU 中运行
+#
+# QEMU 的退出码即为内核通过 SiFive test 设备给出的退出码，测试超时（默认 300 秒）同样视为失败
+#
+# 环境变量 DISK 指定的磁盘镜像会作为 virtio-blk 设备，默认为 target/disk.img，不存在时不使用。
+# FAT_DISK 指定的镜像（例如 FAT32 镜像）作为另一个 virtio-blk 设备。
+# INITRD 指定的 cpio 归档由 QEMU 加载，此时通过 -kernel 加载内核，设备树中才会给出 initrd 的位置
+#
+# 用法：qemu-runner.sh <内核 ELF 文件>
+
+set -e
+
+KERNEL=$1
+BIN=$KERNEL.bin
+OBJCOPY=${OBJCOPY:-rust-objcopy}
+
+DISK=${DISK:-target/disk.img}
+
+# 与 make kernel 相同，写入符号表后 panic 时的栈回溯才能显示函数名
+sh "$(dirname "$0")/symbols.sh" "$KERNEL"
+$OBJCOPY --binary-architecture=riscv64 "$KERNEL" --strip-all -O binary "$BIN"
+
+if [ -f "$DISK" ]; then
+    set -- -drive file="$DISK",if=none,format=raw,id=disk0 -device virtio-blk-device,drive=disk0
+else
+    set --
+fi
+if [ -n "$FAT_DISK" ] && [ -f "$FAT_DISK" ]; then
+    set -- "$@" -drive file="$FAT_DISK",if=none,format=raw,id=disk1 -device virtio-blk-device,drive=disk1
+fi
+
+if [ -n "$INITRD" ] && [ -f "$INITRD" ]; then
+    set -- -kernel "$BIN" -initrd "$INITRD" "$@"
+else
+    set -- -device loader,file="$BIN",addr=0x80200000 "$@"
+fi
+
+exec timeout "${QEMU_TIMEOUT:-300}" qemu-system-riscv64 \
+    -machine virt \
+    -nographic \
+    -smp "${SMP:-4}" \
+    -bios default \
+    "$@"
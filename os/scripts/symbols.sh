@@ -0,0 +1,34 @@
+#!/bin/sh
+# 生成内核的符号表，并写入预留的 .symbol_table 段
+#
+# 符号表为文本格式，每行是按地址排序的「十六进制地址 函数名」，剩余空间以 0 填充。
+# 段的大小在编译时已经确定，因此写入符号表不会改变任何地址
+#
+# 用法：symbols.sh <内核 ELF 文件>
+
+set -e
+
+KERNEL=$1
+SYMBOLS=$KERNEL.symbols
+NM=${NM:-rust-nm}
+OBJDUMP=${OBJDUMP:-rust-objdump}
+OBJCOPY=${OBJCOPY:-rust-objcopy}
+
+# 预留的段大小
+SIZE=$(printf "%d" 0x$($OBJDUMP -h "$KERNEL" | awk '$2 == ".symbol_table" { print $3 }'))
+
+# 只保留代码段中的符号，并去掉 Rust 符号末尾的哈希
+$NM --defined-only -n -C "$KERNEL" | awk '
+    $2 ~ /^[tT]$/ {
+        name = $3
+        for (i = 4; i <= NF; i++) name = name " " $i
+        sub(/::h[0-9a-f]+$/, "", name)
+        print $1, name
+    }' > "$SYMBOLS"
+
+if [ "$(wc -c < "$SYMBOLS")" -ge "$SIZE" ]; then
+    echo "symbol table is larger than the reserved $SIZE bytes" >&2
+    exit 1
+fi
+truncate -s "$SIZE" "$SYMBOLS"
+$OBJCOPY --update-section .symbol_table="$SYMBOLS" "$KERNEL"
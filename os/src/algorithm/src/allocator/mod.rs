@@ -9,8 +9,12 @@ pub trait Allocator {
     fn new(capacity: usize) -> Self;
     /// 分配一个元素, 无法分配则返回 ‘None’
     fn alloc(&mut self) -> Option<usize>;
+    /// 分配连续的 `count` 个元素，返回第一个，无法分配则返回 `None`
+    fn alloc_contiguous(&mut self, count: usize) -> Option<usize>;
     /// 回收一个元素
     fn dealloc(&mut self, index: usize);
+    /// 将 `[start, end)` 中尚未分配的元素标记为已分配，之后可以逐个回收
+    fn reserve(&mut self, start: usize, end: usize);
 }
 
 pub trait VectorAllocator {
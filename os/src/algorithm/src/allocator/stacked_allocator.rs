@@ -29,7 +29,36 @@ impl Allocator for StackedAllocator {
         }
     }
 
+    fn alloc_contiguous(&mut self, count: usize) -> Option<usize> {
+        // 回收的元素各自成为一个区间，因此只能从足够长的区间中分配
+        let position = self
+            .list
+            .iter()
+            .rposition(|&(start, end)| end - start >= count)?;
+        let (start, end) = self.list[position];
+        if end - start > count {
+            self.list[position] = (start + count, end);
+        } else {
+            self.list.remove(position);
+        }
+        Some(start)
+    }
+
     fn dealloc(&mut self, index: usize) {
         self.list.push((index, index + 1))
     }
+
+    fn reserve(&mut self, start: usize, end: usize) {
+        // 每个可用区间去掉与 [start, end) 重叠的部分，最多分为两段
+        let mut list = Vec::with_capacity(self.list.len() + 1);
+        for &(left, right) in self.list.iter() {
+            if left < start.min(right) {
+                list.push((left, start.min(right)));
+            }
+            if end.max(left) < right {
+                list.push((end.max(left), right));
+            }
+        }
+        self.list = list;
+    }
 }
\ No newline at end of file
@@ -2,4 +2,6 @@
 
 extern crate alloc;
 mod allocator;
-pub use allocator::*;
\ No newline at end of file
+mod scheduler;
+pub use allocator::*;
+pub use scheduler::*;
\ No newline at end of file
@@ -0,0 +1,39 @@
+//! 先入先出队列的调度器 [`FifoScheduler`]
+
+use super::Scheduler;
+use alloc::collections::VecDeque;
+
+/// 采用 FIFO 算法的线程调度器
+pub struct FifoScheduler<ThreadType: Clone + Eq> {
+    pool: VecDeque<ThreadType>,
+}
+
+/// `Default` 创建一个空的调度器
+impl<ThreadType: Clone + Eq> Default for FifoScheduler<ThreadType> {
+    fn default() -> Self {
+        Self {
+            pool: VecDeque::new(),
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for FifoScheduler<ThreadType> {
+    fn add_thread(&mut self, thread: ThreadType) {
+        // 加入链表尾部
+        self.pool.push_back(thread);
+    }
+
+    fn get_next(&mut self) -> Option<ThreadType> {
+        // 从头部取出
+        self.pool.pop_front()
+    }
+
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        // 移除相应的线程并且确认恰移除一个线程
+        let mut removed = self.pool.iter().enumerate().filter(|(_, t)| *t == thread);
+        assert!(removed.next().is_some() && removed.next().is_none());
+        self.pool.retain(|t| t != thread);
+    }
+
+    fn set_priority(&mut self, _thread: ThreadType, _priority: usize) {}
+}
@@ -0,0 +1,116 @@
+//! 基于帧指针的栈回溯
+//!
+//! 内核以 `-C force-frame-pointers=yes` 编译，每个函数的栈帧中：
+//! - `fp - 8` 处保存返回地址
+//! - `fp - 16` 处保存调用者的帧指针
+//!
+//! 沿着帧指针逐层向上，直到离开当前所在的栈（启动栈或线程的内核栈）。
+//! 返回地址通过编译后写入的符号表换算为 `函数名+偏移`，见 `scripts/symbols.sh`
+
+use crate::process::try_current_thread;
+use core::mem::size_of;
+
+/// 预留给符号表的空间
+const SYMBOL_TABLE_SIZE: usize = 0x4_0000;
+
+/// 最多回溯的层数
+const MAX_DEPTH: usize = 64;
+
+/// 预留的符号表空间，编译后被替换为真正的符号表
+#[used]
+#[link_section = ".symbol_table"]
+static SYMBOL_TABLE: [u8; SYMBOL_TABLE_SIZE] = [0; SYMBOL_TABLE_SIZE];
+
+extern "C" {
+    /// 由 `linker.ld` 指定的符号表起始位置
+    fn symbol_table_start();
+    /// 由 `linker.ld` 指定的符号表结束位置
+    fn symbol_table_end();
+    /// 启动栈
+    fn boot_stack();
+    fn boot_stack_top();
+}
+
+/// 查找地址所在的函数，返回函数名和偏移
+///
+/// 符号表中每行为「十六进制地址 函数名」，按地址排序，以 0 结尾
+pub fn lookup(address: usize) -> Option<(&'static str, usize)> {
+    // 通过链接脚本中的符号访问，避免编译器认为符号表总是全 0
+    let table = unsafe {
+        core::slice::from_raw_parts(
+            symbol_table_start as usize as *const u8,
+            symbol_table_end as usize - symbol_table_start as usize,
+        )
+    };
+    let end = table.iter().position(|&byte| byte == 0).unwrap_or(table.len());
+    let text = core::str::from_utf8(&table[..end]).ok()?;
+
+    let mut found = None;
+    for line in text.lines() {
+        let mut parts = line.splitn(2, ' ');
+        let start = match usize::from_str_radix(parts.next()?, 16) {
+            Ok(start) => start,
+            Err(_) => continue,
+        };
+        if start > address {
+            break;
+        }
+        found = Some((parts.next().unwrap_or("?"), address - start));
+    }
+    found
+}
+
+/// 帧指针 `fp` 所在栈的范围
+fn stack_range(fp: usize) -> Option<(usize, usize)> {
+    let boot = (boot_stack as usize, boot_stack_top as usize);
+    if fp > boot.0 && fp <= boot.1 {
+        return Some(boot);
+    }
+    let thread = try_current_thread()?;
+    let stack = (thread.kernel_stack.bottom(), thread.kernel_stack.top());
+    if fp > stack.0 && fp <= stack.1 {
+        Some(stack)
+    } else {
+        None
+    }
+}
+
+/// 打印当前的调用栈
+pub fn backtrace() {
+    let mut fp: usize;
+    unsafe { llvm_asm!("mv $0, s0" : "=r"(fp) ::: "volatile") };
+    println!("backtrace:");
+    let (bottom, top) = match stack_range(fp) {
+        Some(range) => range,
+        None => {
+            println!("  frame pointer 0x{:x} is not on a known stack", fp);
+            return;
+        }
+    };
+    for depth in 0..MAX_DEPTH {
+        if fp < bottom + 2 * size_of::<usize>() || fp > top || fp % size_of::<usize>() != 0 {
+            break;
+        }
+        let return_address = unsafe { *((fp - size_of::<usize>()) as *const usize) };
+        let previous_fp = unsafe { *((fp - 2 * size_of::<usize>()) as *const usize) };
+        if return_address == 0 {
+            break;
+        }
+        // 返回地址指向调用指令的下一条，减去 1 以落在调用指令所在的函数内
+        match lookup(return_address - 1) {
+            Some((name, offset)) => println!(
+                "  #{:<2} 0x{:016x} {}+0x{:x}",
+                depth,
+                return_address,
+                name,
+                offset + 1
+            ),
+            None => println!("  #{:<2} 0x{:016x}", depth, return_address),
+        }
+        // 调用者的栈帧总是在更高的地址
+        if previous_fp <= fp {
+            break;
+        }
+        fp = previous_fp;
+    }
+}
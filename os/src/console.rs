@@ -11,26 +11,90 @@
 //! ['write_str']: core::fmt::Write::write_str
 //! ['write_fmt']: core::fmt::Write::write_fmt
 
+use crate::drivers::uart;
 use crate::sbi::*;
+use crate::sync::Lock;
 use core::fmt::{self, Write, Arguments};
 
-/// 一个 [Zero-size Type], 实现 ['']
-struct Stdout;
+/// 控制台锁，持有期间关闭中断，保证一次输出不会与其他输出交错
+static CONSOLE_LOCK: Lock<()> = Lock::new(());
+
+/// 合并输出的缓冲区大小
+const LINE_BUFFER_SIZE: usize = 256;
+
+/// 输出一个字节
+///
+/// 串口驱动初始化之后直接写串口，在此之前通过 SBI 输出
+fn putchar(byte: u8) {
+    if uart::is_ready() {
+        uart::putchar(byte);
+    } else {
+        console_putchar(byte as usize);
+    }
+}
+
+/// 输出一段字节，调用者需要持有 [`CONSOLE_LOCK`]
+fn write_raw(bytes: &[u8]) {
+    for byte in bytes.iter() {
+        putchar(*byte);
+    }
+}
+
+/// 将格式化的输出合并到缓冲区，遇到换行或缓冲区满时再一并输出
+struct Stdout {
+    buffer: [u8; LINE_BUFFER_SIZE],
+    len: usize,
+}
+
+impl Stdout {
+    fn new() -> Self {
+        Self {
+            buffer: [0; LINE_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        write_raw(&self.buffer[..self.len]);
+        self.len = 0;
+    }
+}
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        let mut buffer = [0u8; 4];
-        for c in s.chars() {
-            for code_point in c.encode_utf8(&mut buffer).as_bytes().iter() {
-                console_putchar(*code_point as usize);
+        for &byte in s.as_bytes() {
+            if self.len == LINE_BUFFER_SIZE {
+                self.flush();
+            }
+            self.buffer[self.len] = byte;
+            self.len += 1;
+            if byte == b'\n' {
+                self.flush();
             }
         }
         Ok(())
     }
 }
 
+/// 格式化输出，整个输出期间持有控制台锁
 pub fn print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    let _guard = CONSOLE_LOCK.lock();
+    let mut stdout = Stdout::new();
+    stdout.write_fmt(args).unwrap();
+    stdout.flush();
+}
+
+/// 直接输出字节，用于用户程序的输出
+pub fn write_bytes(bytes: &[u8]) {
+    let _guard = CONSOLE_LOCK.lock();
+    write_raw(bytes);
+}
+
+/// 强制释放控制台锁，用于 panic 时输出
+///
+/// 持有锁的一方可能就是 panic 的线程自身，或者永远不会再释放锁
+pub unsafe fn force_unlock() {
+    CONSOLE_LOCK.force_unlock();
 }
 
 #[macro_export]
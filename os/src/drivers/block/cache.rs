@@ -0,0 +1,170 @@
+//! 块缓存
+//!
+//! [`BlockCache`] 包装一个块设备，在内存中保存最近使用的若干块：
+//! - 读取时命中则直接复制，否则从设备读入；缓存满时替换最久没有使用的块
+//! - 写入只修改缓存并标记为脏，替换、[`sync`](BlockCache::sync) 或定期写回时才写入设备
+//!
+//! 读写设备时会睡眠，因此使用睡眠锁 [`Mutex`]
+
+use super::{BlockDevice, BlockResult, BLOCK_SIZE};
+use crate::memory::stats::{CacheCounters, CacheStats};
+use crate::sync::Mutex;
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc};
+
+/// 缓存中的一块
+struct Entry {
+    data: Box<[u8; BLOCK_SIZE]>,
+    /// 是否被修改过而尚未写回
+    dirty: bool,
+    /// 最近一次使用的时间戳，越小越久
+    last_used: u64,
+}
+
+/// 需要加锁访问的部分
+struct Inner {
+    /// 按照块号索引的缓存
+    entries: BTreeMap<usize, Entry>,
+    /// 单调递增的时间戳
+    clock: u64,
+}
+
+/// 带有 LRU 缓存的块设备
+pub struct BlockCache {
+    device: Arc<dyn BlockDevice>,
+    /// 最多缓存的块数
+    capacity: usize,
+    inner: Mutex<Inner>,
+    counters: CacheCounters,
+}
+
+impl BlockCache {
+    /// 为 `device` 创建最多缓存 `capacity` 块的缓存
+    pub fn new(device: Arc<dyn BlockDevice>, capacity: usize) -> Self {
+        assert!(capacity > 0);
+        BlockCache {
+            device,
+            capacity,
+            inner: Mutex::new(Inner {
+                entries: BTreeMap::new(),
+                clock: 0,
+            }),
+            counters: CacheCounters::new(),
+        }
+    }
+
+    /// 取得第 `block_id` 块的缓存，不存在时先腾出空间再插入
+    ///
+    /// `load` 为真时未命中需要从设备读入；整块覆盖时则不需要
+    fn entry<'a>(
+        &self,
+        inner: &'a mut Inner,
+        block_id: usize,
+        load: bool,
+    ) -> BlockResult<&'a mut Entry> {
+        inner.clock += 1;
+        let clock = inner.clock;
+        if inner.entries.contains_key(&block_id) {
+            self.counters.hit();
+        } else {
+            self.counters.miss();
+            if inner.entries.len() >= self.capacity {
+                self.evict(inner)?;
+            }
+            let mut data = Box::new([0u8; BLOCK_SIZE]);
+            if load {
+                self.device.read_block(block_id, &mut data[..])?;
+            }
+            inner.entries.insert(
+                block_id,
+                Entry {
+                    data,
+                    dirty: false,
+                    last_used: clock,
+                },
+            );
+        }
+        let entry = inner.entries.get_mut(&block_id).unwrap();
+        entry.last_used = clock;
+        Ok(entry)
+    }
+
+    /// 替换最久没有使用的块，必要时先写回
+    fn evict(&self, inner: &mut Inner) -> BlockResult {
+        let victim = inner
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(&block_id, _)| block_id);
+        if let Some(block_id) = victim {
+            let entry = &inner.entries[&block_id];
+            if entry.dirty {
+                self.device.write_block(block_id, &entry.data[..])?;
+            }
+            inner.entries.remove(&block_id);
+        }
+        Ok(())
+    }
+
+    /// 将所有脏块写回设备
+    pub fn sync(&self) -> BlockResult {
+        let mut inner = self.inner.lock();
+        for (&block_id, entry) in inner.entries.iter_mut().filter(|(_, entry)| entry.dirty) {
+            self.device.write_block(block_id, &entry.data[..])?;
+            entry.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// 被缓存的设备，通过它读写会绕过缓存
+    pub fn device(&self) -> &Arc<dyn BlockDevice> {
+        &self.device
+    }
+
+    /// 统计信息
+    pub fn stats(&self, name: &'static str) -> CacheStats {
+        CacheStats {
+            name,
+            cached: self.inner.lock().entries.len(),
+            capacity: self.capacity,
+            hits: self.counters.hits(),
+            misses: self.counters.misses(),
+        }
+    }
+}
+
+impl BlockDevice for BlockCache {
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> BlockResult {
+        assert_eq!(buffer.len(), BLOCK_SIZE);
+        if block_id >= self.device.num_blocks() {
+            return Err("block index out of range");
+        }
+        let mut inner = self.inner.lock();
+        let entry = self.entry(&mut inner, block_id, true)?;
+        buffer.copy_from_slice(&entry.data[..]);
+        Ok(())
+    }
+
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> BlockResult {
+        assert_eq!(buffer.len(), BLOCK_SIZE);
+        if block_id >= self.device.num_blocks() {
+            return Err("block index out of range");
+        }
+        let mut inner = self.inner.lock();
+        let entry = self.entry(&mut inner, block_id, false)?;
+        entry.data.copy_from_slice(buffer);
+        entry.dirty = true;
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> usize {
+        self.device.num_blocks()
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        if let Err(message) = self.sync() {
+            warn!("failed to write back block cache: {}", message);
+        }
+    }
+}
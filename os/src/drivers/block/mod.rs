@@ -0,0 +1,101 @@
+//! 块设备
+//!
+//! 所有块设备实现 [`BlockDevice`]，初始化时通过 [`register`] 登记，
+//! 之后由文件系统通过 [`get`] 按照登记的顺序取得。
+//! 登记的设备都包装了一层 [`BlockCache`]，脏块由内核线程定期写回
+
+mod cache;
+mod virtio_blk;
+
+pub use cache::BlockCache;
+pub use virtio_blk::VirtIOBlock;
+
+use crate::interrupt::timer;
+use crate::memory::stats::{self, CacheStats};
+use crate::process::{add_thread, Thread};
+use crate::sync::Lock;
+use alloc::{sync::Arc, vec::Vec};
+use core::time::Duration;
+
+/// 每个设备缓存的块数（256K）
+const CACHE_CAPACITY: usize = 512;
+
+/// 定期写回脏块的间隔
+const WRITEBACK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 统计信息中各个设备的名称
+const NAMES: &[&str] = &["block0", "block1", "block2", "block3", "block4", "block5", "block6", "block7"];
+
+/// 块的大小
+pub const BLOCK_SIZE: usize = 512;
+
+/// 块设备操作的结果，出错时给出原因
+pub type BlockResult<T = ()> = Result<T, &'static str>;
+
+/// 以块为单位读写的设备
+pub trait BlockDevice: Send + Sync {
+    /// 读取第 `block_id` 块，`buffer` 的长度必须为 [`BLOCK_SIZE`]
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> BlockResult;
+
+    /// 写入第 `block_id` 块，`buffer` 的长度必须为 [`BLOCK_SIZE`]
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> BlockResult;
+
+    /// 块的数量
+    fn num_blocks(&self) -> usize;
+}
+
+/// 所有登记的块设备
+static DEVICES: Lock<Vec<Arc<BlockCache>>> = Lock::new(Vec::new());
+
+/// 登记统计信息，并启动定期写回的内核线程
+///
+/// 需要在所有驱动初始化之后调用，内核线程所在的地址空间会映射驱动登记的 MMIO 区域
+pub fn init() {
+    stats::register_cache(cache_stats);
+    add_thread(Thread::new_kernel(writeback as usize, None));
+}
+
+/// 登记一个块设备，返回其编号
+pub fn register(device: Arc<dyn BlockDevice>) -> usize {
+    let mut devices = DEVICES.lock();
+    devices.push(Arc::new(BlockCache::new(device, CACHE_CAPACITY)));
+    devices.len() - 1
+}
+
+/// 编号为 `index` 的块设备
+pub fn get(index: usize) -> Option<Arc<BlockCache>> {
+    DEVICES.lock().get(index).cloned()
+}
+
+/// 将所有设备的脏块写回
+pub fn sync_all() {
+    let devices = DEVICES.lock().clone();
+    for device in devices.iter() {
+        if let Err(message) = device.sync() {
+            warn!("failed to write back block cache: {}", message);
+        }
+    }
+}
+
+/// 定期写回的内核线程
+fn writeback() {
+    loop {
+        timer::sleep(WRITEBACK_INTERVAL);
+        sync_all();
+    }
+}
+
+/// 所有设备的缓存的统计信息
+fn cache_stats() -> Vec<CacheStats> {
+    let devices = DEVICES.lock().clone();
+    devices
+        .iter()
+        .enumerate()
+        .map(|(index, device)| device.stats(NAMES.get(index).copied().unwrap_or("block")))
+        .collect()
+}
+
+/// 块设备的数量
+pub fn count() -> usize {
+    DEVICES.lock().len()
+}
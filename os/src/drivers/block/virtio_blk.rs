@@ -0,0 +1,161 @@
+//! virtio-blk 块设备驱动
+//!
+//! 每个请求由三个缓冲区组成：请求头（设备读）、数据、状态（设备写）。
+//! 三者都放在为请求分配的一个物理页中，请求发出后线程睡眠，由设备中断唤醒。
+//! 规范见 virtio 1.1 的 5.2 节
+
+use super::{BlockDevice, BlockResult, BLOCK_SIZE};
+use crate::drivers::virtio::{MmioTransport, VirtQueue};
+use crate::memory::frame;
+use crate::sync::{Condvar, Lock};
+use alloc::{vec, vec::Vec};
+
+/// 请求类型：读
+const REQUEST_IN: u32 = 0;
+/// 请求类型：写
+const REQUEST_OUT: u32 = 1;
+
+/// 状态：成功
+const STATUS_OK: u8 = 0;
+/// 设备尚未写入状态时的值
+const STATUS_PENDING: u8 = 0xff;
+
+/// 请求页中各部分的偏移
+const HEADER_OFFSET: usize = 0;
+const HEADER_SIZE: usize = 16;
+const STATUS_OFFSET: usize = 16;
+const DATA_OFFSET: usize = 512;
+
+/// 队列长度
+const QUEUE_SIZE: u16 = 16;
+
+/// 请求头
+#[repr(C)]
+struct RequestHeader {
+    request_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+/// 需要加锁访问的部分
+struct Inner {
+    queue: VirtQueue,
+    /// 以链头为下标，请求是否已经完成
+    completed: Vec<bool>,
+}
+
+/// virtio-blk 设备
+pub struct VirtIOBlock {
+    transport: MmioTransport,
+    inner: Lock<Inner>,
+    /// 有请求完成时通知
+    condvar: Condvar,
+    /// 容量，以 512 字节的扇区为单位
+    capacity: usize,
+}
+
+impl VirtIOBlock {
+    /// 初始化设备
+    pub fn new(transport: MmioTransport) -> BlockResult<Self> {
+        transport.begin_init()?;
+        let size = QUEUE_SIZE.min(transport.max_queue_size(0) as u16);
+        if size < 3 {
+            return Err("request queue is too small");
+        }
+        let queue = VirtQueue::new(size)?;
+        transport.setup_queue(0, &queue);
+        transport.finish_init();
+        // 配置空间的第一项为 u64 的容量
+        let capacity =
+            transport.config_u32(0) as usize | (transport.config_u32(4) as usize) << 32;
+        Ok(VirtIOBlock {
+            transport,
+            inner: Lock::new(Inner {
+                queue,
+                completed: vec![false; size as usize],
+            }),
+            condvar: Condvar::new(),
+            capacity,
+        })
+    }
+
+    /// 处理设备中断，标记所有完成的请求并唤醒等待的线程
+    pub fn handle_irq(&self) {
+        self.transport.ack_interrupt();
+        let mut inner = self.inner.lock();
+        while let Some((head, _)) = inner.queue.pop_used() {
+            inner.completed[head as usize] = true;
+        }
+        drop(inner);
+        self.condvar.notify_all();
+    }
+
+    /// 发出一个请求并等待完成，`data` 在请求页中，由 `prepare` 和 `finish` 读写
+    fn request(
+        &self,
+        request_type: u32,
+        block_id: usize,
+        prepare: impl FnOnce(&mut [u8]),
+        finish: impl FnOnce(&[u8]),
+    ) -> BlockResult {
+        if block_id >= self.capacity {
+            return Err("block index out of range");
+        }
+        let mut page = frame::alloc()?;
+        let header = RequestHeader {
+            request_type,
+            reserved: 0,
+            sector: block_id as u64,
+        };
+        unsafe { (page.as_mut_ptr().add(HEADER_OFFSET) as *mut RequestHeader).write(header) };
+        page[STATUS_OFFSET] = STATUS_PENDING;
+        prepare(&mut page[DATA_OFFSET..DATA_OFFSET + BLOCK_SIZE]);
+
+        let address = page.address();
+        let buffers = [
+            (address + HEADER_OFFSET, HEADER_SIZE, false),
+            (address + DATA_OFFSET, BLOCK_SIZE, request_type == REQUEST_IN),
+            (address + STATUS_OFFSET, 1, true),
+        ];
+        let mut inner = self.inner.lock();
+        // 队列满时等待其他请求完成
+        let head = loop {
+            if let Some(head) = inner.queue.add(&buffers) {
+                break head;
+            }
+            inner = self.condvar.wait_lock(inner);
+        };
+        inner.completed[head as usize] = false;
+        self.transport.notify(0);
+        while !inner.completed[head as usize] {
+            inner = self.condvar.wait_lock(inner);
+        }
+        // 请求完成之后才回收描述符，链头在此之前不会被其他请求使用
+        inner.queue.recycle(head);
+        drop(inner);
+        // 可能有请求在等待空闲的描述符
+        self.condvar.notify_all();
+
+        if page[STATUS_OFFSET] != STATUS_OK {
+            return Err("virtio-blk request failed");
+        }
+        finish(&page[DATA_OFFSET..DATA_OFFSET + BLOCK_SIZE]);
+        Ok(())
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> BlockResult {
+        assert_eq!(buffer.len(), BLOCK_SIZE);
+        self.request(REQUEST_IN, block_id, |_| {}, |data| buffer.copy_from_slice(data))
+    }
+
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> BlockResult {
+        assert_eq!(buffer.len(), BLOCK_SIZE);
+        self.request(REQUEST_OUT, block_id, |data| data.copy_from_slice(buffer), |_| {})
+    }
+
+    fn num_blocks(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,394 @@
+//! 扁平设备树（FDT，即 DTB 文件）的解析
+//!
+//! 直接在 DTB 所在的内存上查询，不分配内存。格式见 Devicetree Specification 第 5 章：
+//! - 文件头记录结构块和字符串块的位置
+//! - 结构块由 `FDT_BEGIN_NODE`、`FDT_PROP`、`FDT_END_NODE` 等 token 组成，每个 token 4 字节对齐
+//! - 所有整数均为大端序
+
+use core::convert::TryInto;
+
+/// 文件头中的魔数
+const MAGIC: u32 = 0xd00d_feed;
+/// 文件头的大小
+const HEADER_SIZE: usize = 40;
+/// 支持的最低版本
+const MIN_VERSION: u32 = 17;
+
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+
+/// 父节点没有给出 `#address-cells` 和 `#size-cells` 时的默认值
+const DEFAULT_ADDRESS_CELLS: usize = 2;
+const DEFAULT_SIZE_CELLS: usize = 1;
+
+/// 读取大端序的 u32
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// 读取以 0 结尾的字符串
+fn read_str(data: &'static [u8], offset: usize) -> Option<&'static str> {
+    let rest = data.get(offset..)?;
+    let len = rest.iter().position(|&byte| byte == 0)?;
+    core::str::from_utf8(&rest[..len]).ok()
+}
+
+/// 向上对齐到 4 字节
+fn align(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// 一棵设备树
+#[derive(Clone, Copy)]
+pub struct Fdt {
+    /// 整个 DTB
+    data: &'static [u8],
+    /// 结构块
+    structure: &'static [u8],
+    /// 字符串块
+    strings: &'static [u8],
+}
+
+impl Fdt {
+    /// 解析位于 `address`（虚拟地址）的 DTB
+    ///
+    /// # Safety
+    ///
+    /// `address` 处必须是可以读取的内存，并且在内核运行期间不会被修改
+    pub unsafe fn from_address(address: usize) -> Result<Self, &'static str> {
+        let header = core::slice::from_raw_parts(address as *const u8, HEADER_SIZE);
+        if read_u32(header, 0) != Some(MAGIC) {
+            return Err("bad magic");
+        }
+        let field = |offset| read_u32(header, offset).unwrap() as usize;
+        if (field(20) as u32) < MIN_VERSION {
+            return Err("unsupported version");
+        }
+        let data = core::slice::from_raw_parts(address as *const u8, field(4));
+        let block = |offset: usize, size: usize| {
+            data.get(offset..offset.checked_add(size)?)
+        };
+        Ok(Fdt {
+            data,
+            structure: block(field(8), field(36)).ok_or("structure block out of range")?,
+            strings: block(field(12), field(32)).ok_or("strings block out of range")?,
+        })
+    }
+
+    /// DTB 的起始地址
+    pub fn address(&self) -> usize {
+        self.data.as_ptr() as usize
+    }
+
+    /// DTB 的大小
+    pub fn total_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// 根节点
+    pub fn root(&self) -> Option<Node> {
+        let (token, offset) = self.next_token(0)?;
+        if token != FDT_BEGIN_NODE {
+            return None;
+        }
+        self.node_at(offset, DEFAULT_ADDRESS_CELLS, DEFAULT_SIZE_CELLS)
+    }
+
+    /// 按照路径查找节点，例如 `/chosen`、`/cpus`
+    ///
+    /// 路径中的每一段可以省略 `@` 之后的单元地址
+    pub fn find_node(&self, path: &str) -> Option<Node> {
+        let mut node = self.root()?;
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            node = node.children().find(|child| {
+                child.name() == component || child.base_name() == component
+            })?;
+        }
+        Some(node)
+    }
+
+    /// 找到第一个与 `compatible` 中任意一项兼容的节点
+    pub fn find_compatible(&self, compatible: &[&str]) -> Option<Node> {
+        let mut found = None;
+        self.walk(&mut |node| {
+            if found.is_none() && compatible.iter().any(|&c| node.is_compatible(c)) {
+                found = Some(*node);
+            }
+        });
+        found
+    }
+
+    /// 按照深度优先的顺序访问所有节点
+    pub fn walk(&self, f: &mut dyn FnMut(&Node)) {
+        if let Some(root) = self.root() {
+            root.walk(f);
+        }
+    }
+
+    /// 从 `offset` 开始跳过 `FDT_NOP`，返回下一个 token 和它的位置
+    fn next_token(&self, mut offset: usize) -> Option<(u32, usize)> {
+        loop {
+            let token = read_u32(self.structure, offset)?;
+            if token != FDT_NOP {
+                return Some((token, offset));
+            }
+            offset += 4;
+        }
+    }
+
+    /// 解析位于 `offset` 的 `FDT_BEGIN_NODE`
+    fn node_at(&self, offset: usize, address_cells: usize, size_cells: usize) -> Option<Node> {
+        let name = read_str(self.structure, offset + 4)?;
+        Some(Node {
+            fdt: *self,
+            name,
+            body: align(offset + 4 + name.len() + 1),
+            parent_address_cells: address_cells,
+            parent_size_cells: size_cells,
+        })
+    }
+
+    /// 解析位于 `offset` 的 `FDT_PROP`，返回属性和其后的位置
+    fn property_at(&self, offset: usize) -> Option<(Property, usize)> {
+        let len = read_u32(self.structure, offset + 4)? as usize;
+        let name_offset = read_u32(self.structure, offset + 8)? as usize;
+        let value = self.structure.get(offset + 12..offset + 12 + len)?;
+        let property = Property {
+            name: read_str(self.strings, name_offset)?,
+            value,
+        };
+        Some((property, align(offset + 12 + len)))
+    }
+
+    /// 跳过从 `offset` 开始的属性和子节点，返回所在节点的 `FDT_END_NODE` 的位置
+    fn end_of_node(&self, mut offset: usize) -> Option<usize> {
+        let mut depth = 0;
+        loop {
+            let (token, at) = self.next_token(offset)?;
+            match token {
+                FDT_PROP => offset = self.property_at(at)?.1,
+                FDT_BEGIN_NODE => {
+                    depth += 1;
+                    let name = read_str(self.structure, at + 4)?;
+                    offset = align(at + 4 + name.len() + 1);
+                }
+                FDT_END_NODE if depth == 0 => return Some(at),
+                FDT_END_NODE => {
+                    depth -= 1;
+                    offset = at + 4;
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// 设备树中的一个节点
+#[derive(Clone, Copy)]
+pub struct Node {
+    fdt: Fdt,
+    /// 节点名，例如 `uart@10000000`
+    name: &'static str,
+    /// 节点名之后，第一个属性或子节点的位置
+    body: usize,
+    /// 父节点的 `#address-cells`，用于解析 `reg`
+    parent_address_cells: usize,
+    /// 父节点的 `#size-cells`，用于解析 `reg`
+    parent_size_cells: usize,
+}
+
+impl Node {
+    /// 完整的节点名
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// 去掉 `@` 及单元地址后的节点名
+    pub fn base_name(&self) -> &'static str {
+        self.name.split('@').next().unwrap()
+    }
+
+    /// 所有属性
+    pub fn properties(&self) -> Properties {
+        Properties {
+            fdt: self.fdt,
+            offset: self.body,
+        }
+    }
+
+    /// 名为 `name` 的属性
+    pub fn property(&self, name: &str) -> Option<Property> {
+        self.properties().find(|property| property.name == name)
+    }
+
+    /// 所有子节点
+    pub fn children(&self) -> Children {
+        // 子节点位于所有属性之后
+        let mut properties = self.properties();
+        while properties.next().is_some() {}
+        Children {
+            fdt: self.fdt,
+            offset: properties.offset,
+            address_cells: self.address_cells(),
+            size_cells: self.size_cells(),
+        }
+    }
+
+    /// 按照深度优先的顺序访问自身和所有子孙节点
+    pub fn walk(&self, f: &mut dyn FnMut(&Node)) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+
+    /// `compatible` 属性中的所有字符串
+    pub fn compatible(&self) -> impl Iterator<Item = &'static str> {
+        self.property("compatible")
+            .into_iter()
+            .flat_map(|property| property.strings())
+    }
+
+    /// 是否与 `compatible` 兼容
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        self.compatible().any(|c| c == compatible)
+    }
+
+    /// 设备是否启用，没有 `status` 属性时视为启用
+    pub fn is_enabled(&self) -> bool {
+        match self.property("status").and_then(|property| property.as_str()) {
+            Some(status) => status == "okay" || status == "ok",
+            None => true,
+        }
+    }
+
+    /// 子节点 `reg` 中地址所占的 cell 数
+    pub fn address_cells(&self) -> usize {
+        self.property("#address-cells")
+            .and_then(|property| property.as_u32())
+            .map_or(DEFAULT_ADDRESS_CELLS, |cells| cells as usize)
+    }
+
+    /// 子节点 `reg` 中长度所占的 cell 数
+    pub fn size_cells(&self) -> usize {
+        self.property("#size-cells")
+            .and_then(|property| property.as_u32())
+            .map_or(DEFAULT_SIZE_CELLS, |cells| cells as usize)
+    }
+
+    /// `reg` 属性中的所有（地址，长度）
+    pub fn reg(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (address_cells, size_cells) = (self.parent_address_cells, self.parent_size_cells);
+        let value = self.property("reg").map_or(&[][..], |property| property.value);
+        let entry_size = (address_cells + size_cells) * 4;
+        value
+            .chunks_exact(entry_size.max(4))
+            .map(move |entry| {
+                let (address, size) = entry.split_at(address_cells * 4);
+                (read_cells(address), read_cells(size))
+            })
+    }
+
+    /// `reg` 中的第一项
+    pub fn first_reg(&self) -> Option<(usize, usize)> {
+        self.reg().next()
+    }
+
+    /// `interrupts` 属性中的第一个中断号
+    pub fn interrupt(&self) -> Option<usize> {
+        self.property("interrupts")
+            .and_then(|property| property.as_u32())
+            .map(|irq| irq as usize)
+    }
+}
+
+/// 将若干个大端序的 cell 合并为一个数
+fn read_cells(cells: &[u8]) -> usize {
+    cells
+        .chunks_exact(4)
+        .fold(0, |value, cell| (value << 32) | read_u32(cell, 0).unwrap() as usize)
+}
+
+/// 节点的属性
+#[derive(Clone, Copy, Debug)]
+pub struct Property {
+    /// 属性名
+    pub name: &'static str,
+    /// 属性的原始值
+    pub value: &'static [u8],
+}
+
+impl Property {
+    /// 作为一个 u32 读取
+    pub fn as_u32(&self) -> Option<u32> {
+        read_u32(self.value, 0)
+    }
+
+    /// 作为一个 u32 或 u64 读取，取决于属性的长度
+    pub fn as_usize(&self) -> Option<usize> {
+        match self.value.len() {
+            4 | 8 => Some(read_cells(self.value)),
+            _ => None,
+        }
+    }
+
+    /// 作为一个字符串读取
+    pub fn as_str(&self) -> Option<&'static str> {
+        let value = self.value.split(|&byte| byte == 0).next()?;
+        core::str::from_utf8(value).ok()
+    }
+
+    /// 作为字符串列表读取，例如 `compatible`
+    pub fn strings(&self) -> impl Iterator<Item = &'static str> {
+        self.value
+            .split(|&byte| byte == 0)
+            .filter(|value| !value.is_empty())
+            .filter_map(|value| core::str::from_utf8(value).ok())
+    }
+}
+
+/// 节点属性的迭代器
+pub struct Properties {
+    fdt: Fdt,
+    offset: usize,
+}
+
+impl Iterator for Properties {
+    type Item = Property;
+
+    fn next(&mut self) -> Option<Property> {
+        let (token, at) = self.fdt.next_token(self.offset)?;
+        if token != FDT_PROP {
+            return None;
+        }
+        let (property, next) = self.fdt.property_at(at)?;
+        self.offset = next;
+        Some(property)
+    }
+}
+
+/// 子节点的迭代器
+pub struct Children {
+    fdt: Fdt,
+    offset: usize,
+    /// 本节点的 `#address-cells` 和 `#size-cells`
+    address_cells: usize,
+    size_cells: usize,
+}
+
+impl Iterator for Children {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Node> {
+        let (token, at) = self.fdt.next_token(self.offset)?;
+        if token != FDT_BEGIN_NODE {
+            return None;
+        }
+        let node = self.fdt.node_at(at, self.address_cells, self.size_cells)?;
+        self.offset = self.fdt.end_of_node(node.body)? + 4;
+        Some(node)
+    }
+}
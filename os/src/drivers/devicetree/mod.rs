@@ -0,0 +1,165 @@
+//! 设备树
+//!
+//! OpenSBI 启动内核时在 `a1` 中传入设备树（DTB）的物理地址。
+//! - [`init`] 在驱动初始化之前读取内存范围、时基频率和启动参数，并保留 initrd 所在的内存
+//! - [`probe`] 按照 `compatible` 为设备树中的设备找到 [`Driver`] 并初始化
+//!
+//! 增加新的设备只需要在驱动表中登记其 `compatible` 和 `probe` 函数
+
+mod fdt;
+
+pub use fdt::{Fdt, Node, Property};
+
+use crate::interrupt::timer;
+use crate::logger;
+use crate::memory::address::{PhysicalAddress, VirtualAddress};
+use crate::memory::config::{memory_end, set_memory_end, MEMORY_START_ADDRESS};
+use crate::memory::frame;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 设备树的虚拟地址，为 0 表示没有设备树
+static DEVICE_TREE: AtomicUsize = AtomicUsize::new(0);
+
+/// 一个设备驱动
+pub struct Driver {
+    /// 驱动名，用于输出日志
+    pub name: &'static str,
+    /// 驱动支持的设备，匹配节点 `compatible` 中的任意一项
+    pub compatible: &'static [&'static str],
+    /// 初始化一个匹配的设备
+    pub probe: fn(&Node) -> Result<(), &'static str>,
+}
+
+/// 解析设备树，并根据其内容设置内存范围、时基频率和日志过滤规则，保留 initrd 所在的内存
+///
+/// 需要在第一次分配物理页之前调用
+pub fn init(device_tree: PhysicalAddress) {
+    if device_tree.0 == 0 {
+        warn!("no device tree is passed, using default configuration");
+        return;
+    }
+    let fdt = match unsafe { Fdt::from_address(VirtualAddress::from(device_tree).0) } {
+        Ok(fdt) => fdt,
+        Err(message) => {
+            warn!("invalid device tree at {}: {}", device_tree, message);
+            return;
+        }
+    };
+    DEVICE_TREE.store(fdt.address(), Ordering::Relaxed);
+
+    // 可用内存到设备树之前为止，避免设备树所在的物理页被分配出去
+    if let Some(end) = memory_region_end(&fdt) {
+        let end = if device_tree > MEMORY_START_ADDRESS {
+            end.min(device_tree.0)
+        } else {
+            end
+        };
+        set_memory_end(PhysicalAddress(end));
+    }
+    if let Some(frequency) = fdt
+        .find_node("/cpus")
+        .and_then(|cpus| cpus.property("timebase-frequency"))
+        .and_then(|property| property.as_u32())
+    {
+        timer::set_timebase_frequency(frequency as usize);
+    }
+    // initrd 在文件系统初始化时解包，此前其所在的物理页不能被分配出去
+    if let Some((start, end)) = initrd() {
+        frame::reserve(start, end);
+        info!("initrd at [{}, {})", start, end);
+    }
+    // 启动参数中的 `log=<规则>` 覆盖编译时指定的日志过滤规则，
+    // `dmesg_on_panic` 使 panic 时输出日志缓冲区
+    if let Some(bootargs) = bootargs() {
+        for argument in bootargs.split_whitespace() {
+            if argument.starts_with("log=") {
+                logger::set_filter(&argument[4..]);
+            } else if argument == "dmesg_on_panic" {
+                logger::dmesg::set_dump_on_panic(true);
+            }
+        }
+    }
+    info!(
+        "device tree at {} ({} bytes), memory end {}, timebase {} Hz",
+        device_tree,
+        fdt.total_size(),
+        memory_end(),
+        timer::timebase_frequency(),
+    );
+}
+
+/// 内核所在的内存区域的结束地址
+fn memory_region_end(fdt: &Fdt) -> Option<usize> {
+    let mut end = None;
+    fdt.walk(&mut |node| {
+        let is_memory = node
+            .property("device_type")
+            .and_then(|property| property.as_str())
+            == Some("memory");
+        if is_memory {
+            for (start, len) in node.reg() {
+                if start <= MEMORY_START_ADDRESS.0 && MEMORY_START_ADDRESS.0 < start + len {
+                    end = Some(start + len);
+                }
+            }
+        }
+    });
+    end
+}
+
+/// 设备树，启动时没有传入有效的设备树则为 `None`
+pub fn tree() -> Option<Fdt> {
+    match DEVICE_TREE.load(Ordering::Relaxed) {
+        0 => None,
+        address => unsafe { Fdt::from_address(address).ok() },
+    }
+}
+
+/// `/chosen` 中的启动参数
+pub fn bootargs() -> Option<&'static str> {
+    tree()?
+        .find_node("/chosen")?
+        .property("bootargs")?
+        .as_str()
+        .filter(|bootargs| !bootargs.is_empty())
+}
+
+/// `/chosen` 中给出的 initrd 的物理地址范围，例如 QEMU 的 `-initrd` 参数
+///
+/// 只接受位于可用内存之中的 initrd，其内容通过内核的线性映射访问
+pub fn initrd() -> Option<(PhysicalAddress, PhysicalAddress)> {
+    let chosen = tree()?.find_node("/chosen")?;
+    let start = chosen.property("linux,initrd-start")?.as_usize()?;
+    let end = chosen.property("linux,initrd-end")?.as_usize()?;
+    if MEMORY_START_ADDRESS.0 <= start && start < end && end <= memory_end().0 {
+        Some((PhysicalAddress(start), PhysicalAddress(end)))
+    } else {
+        None
+    }
+}
+
+/// 按照 `drivers` 的顺序，为每个驱动初始化设备树中所有匹配且启用的设备
+///
+/// 没有设备树时返回 `false`
+pub fn probe(drivers: &[Driver]) -> bool {
+    let fdt = match tree() {
+        Some(fdt) => fdt,
+        None => return false,
+    };
+    for driver in drivers {
+        fdt.walk(&mut |node| {
+            let matched = driver
+                .compatible
+                .iter()
+                .any(|&compatible| node.is_compatible(compatible));
+            if !matched || !node.is_enabled() {
+                return;
+            }
+            match (driver.probe)(node) {
+                Ok(()) => debug!("{}: probed {}", driver.name, node.name()),
+                Err(message) => warn!("{}: failed to probe {}: {}", driver.name, node.name(), message),
+            }
+        });
+    }
+    true
+}
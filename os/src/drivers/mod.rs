@@ -0,0 +1,74 @@
+//! 设备驱动
+//!
+//! - [`devicetree`]：解析设备树，按照 `compatible` 为设备找到驱动
+//! - [`block`]：块设备，目前有 virtio-blk
+//! - [`virtio`]：virtio-mmio 传输层，按照设备类型交给对应的驱动
+//! - [`plic`]：平台级中断控制器，将外部中断分发给各个驱动
+//! - [`uart`]：NS16550A 串口，用于控制台输入输出
+//! - [`sifive_test`]：QEMU 的 SiFive test 设备，用于结束模拟并给出退出码
+
+// 驱动提供的接口不一定都会被使用
+#![allow(dead_code)]
+
+pub mod block;
+pub mod devicetree;
+pub mod plic;
+pub mod sifive_test;
+pub mod uart;
+pub mod virtio;
+
+use crate::memory::address::PhysicalAddress;
+use devicetree::Driver;
+
+/// 所有驱动，按照顺序初始化。中断控制器需要在使用中断的设备之前
+const DRIVERS: &[Driver] = &[
+    Driver {
+        name: "plic",
+        compatible: &["riscv,plic0", "sifive,plic-1.0.0"],
+        probe: plic::probe,
+    },
+    Driver {
+        name: "sifive_test",
+        compatible: &["sifive,test0", "sifive,test1"],
+        probe: sifive_test::probe,
+    },
+    Driver {
+        name: "uart",
+        compatible: &["ns16550a"],
+        probe: uart::probe,
+    },
+    Driver {
+        name: "virtio",
+        compatible: &["virtio,mmio"],
+        probe: virtio::probe,
+    },
+];
+
+/// 没有设备树时，QEMU virt 平台 SiFive test 设备的默认地址
+const DEFAULT_SIFIVE_TEST_BASE: PhysicalAddress = PhysicalAddress(0x10_0000);
+/// 没有设备树时，QEMU virt 平台 PLIC 的默认地址
+const DEFAULT_PLIC_BASE: PhysicalAddress = PhysicalAddress(0x0c00_0000);
+/// 没有设备树时，QEMU virt 平台串口的默认地址
+const DEFAULT_UART_BASE: PhysicalAddress = PhysicalAddress(0x1000_0000);
+/// 没有设备树时，QEMU virt 平台串口的中断源编号
+const DEFAULT_UART_IRQ: usize = 10;
+
+/// 初始化设备驱动
+///
+/// 有设备树时按照设备树初始化，否则使用 QEMU virt 平台的默认配置
+pub fn init() {
+    // 设备树中没有 SiFive test 设备时不再使用默认地址
+    sifive_test::set_present(false);
+    if !devicetree::probe(DRIVERS) {
+        sifive_test::init(DEFAULT_SIFIVE_TEST_BASE);
+        plic::init(DEFAULT_PLIC_BASE);
+        uart::init(DEFAULT_UART_BASE, DEFAULT_UART_IRQ);
+    }
+    block::init();
+    info!("mod drivers initialized");
+}
+
+/// 在其他 hart 上初始化设备驱动需要的 hart 局部状态
+pub fn init_hart() {
+    plic::init_hart();
+}
@@ -0,0 +1,161 @@
+//! 平台级中断控制器（PLIC）
+//!
+//! PLIC 将外部设备的中断源（irq）路由至各个 hart 的 context。
+//! 每个 context 有独立的使能位和优先级阈值，优先级高于阈值的中断会触发对应的外部中断。
+//! 处理时先 claim 得到中断源编号，交给 [`register_irq`] 登记的处理函数，再 complete
+//!
+//! 寄存器布局见 <https://github.com/riscv/riscv-plic-spec>
+
+use super::devicetree::Node;
+use crate::hart::{hart_id, MAX_HARTS};
+use crate::memory::address::{PhysicalAddress, VirtualAddress};
+use crate::memory::mmio;
+use crate::sync::Lock;
+use alloc::{collections::BTreeMap, sync::Arc};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use riscv::register::sie;
+
+/// 中断源的优先级寄存器，每个中断源 4 字节
+const PRIORITY_OFFSET: usize = 0x0;
+/// 每个 context 的使能位，每个 context 0x80 字节
+const ENABLE_OFFSET: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+/// 每个 context 的阈值和 claim / complete 寄存器，每个 context 0x1000 字节
+const CONTEXT_OFFSET: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+
+/// 支持的中断源数量，0 号中断源不存在
+pub const MAX_IRQ: usize = 1024;
+
+/// 中断处理函数
+pub type IrqHandler = Arc<dyn Fn() + Send + Sync>;
+
+/// PLIC 寄存器的虚拟地址
+static BASE: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// 各个中断源的处理函数
+    static ref HANDLERS: Lock<BTreeMap<usize, IrqHandler>> = Lock::new(BTreeMap::new());
+}
+
+/// 当前 hart 的 S 态 context 编号
+///
+/// QEMU virt 平台上每个 hart 依次有 M 态和 S 态两个 context
+fn current_context() -> usize {
+    context_of(hart_id())
+}
+
+/// hart 的 S 态 context 编号
+fn context_of(hart: usize) -> usize {
+    hart * 2 + 1
+}
+
+/// 获取偏移处的寄存器
+fn register(offset: usize) -> &'static mut u32 {
+    let base = BASE.load(Ordering::Relaxed);
+    assert_ne!(base, 0, "PLIC is not initialized");
+    VirtualAddress(base + offset).deref()
+}
+
+/// 读写 MMIO 寄存器需要使用 volatile
+fn read(offset: usize) -> u32 {
+    unsafe { core::ptr::read_volatile(register(offset)) }
+}
+
+fn write(offset: usize, value: u32) {
+    unsafe { core::ptr::write_volatile(register(offset), value) }
+}
+
+/// 初始化 PLIC，并允许当前 hart 接收外部中断
+pub fn init(base: PhysicalAddress) {
+    // 优先级、使能寄存器，以及所有 hart 的 S 态 context
+    mmio::add_region(base, CONTEXT_OFFSET);
+    mmio::add_region(
+        base + CONTEXT_OFFSET,
+        (context_of(MAX_HARTS - 1) + 1) * CONTEXT_STRIDE,
+    );
+    BASE.store(VirtualAddress::from(base).0, Ordering::Relaxed);
+    init_hart();
+}
+
+/// 按照设备树节点初始化
+pub fn probe(node: &Node) -> Result<(), &'static str> {
+    let (base, _) = node.first_reg().ok_or("no reg property")?;
+    init(PhysicalAddress(base));
+    Ok(())
+}
+
+/// 当前 hart 接收所有优先级不为 0 的中断，并开启 `sie.SEIE`
+pub fn init_hart() {
+    set_threshold(current_context(), 0);
+    unsafe { sie::set_sext() };
+}
+
+/// 设置中断源的优先级，0 表示不会触发中断
+pub fn set_priority(irq: usize, priority: u32) {
+    write(PRIORITY_OFFSET + irq * 4, priority);
+}
+
+/// 设置 context 的优先级阈值，只有优先级高于阈值的中断会被接收
+pub fn set_threshold(context: usize, threshold: u32) {
+    write(CONTEXT_OFFSET + context * CONTEXT_STRIDE, threshold);
+}
+
+/// 在 context 上使能或禁用一个中断源
+pub fn set_enable(context: usize, irq: usize, enable: bool) {
+    let offset = ENABLE_OFFSET + context * ENABLE_STRIDE + irq / 32 * 4;
+    let bit = 1 << (irq % 32);
+    let value = read(offset);
+    write(offset, if enable { value | bit } else { value & !bit });
+}
+
+/// 在 hart 上使能或禁用一个中断源
+pub fn set_hart_enable(hart: usize, irq: usize, enable: bool) {
+    set_enable(context_of(hart), irq, enable);
+}
+
+/// 获取当前 hart 上等待处理的最高优先级中断源，没有时返回 `None`
+pub fn claim() -> Option<usize> {
+    match read(CONTEXT_OFFSET + current_context() * CONTEXT_STRIDE + 4) {
+        0 => None,
+        irq => Some(irq as usize),
+    }
+}
+
+/// 通知 PLIC 中断源已经处理完毕
+pub fn complete(irq: usize) {
+    write(
+        CONTEXT_OFFSET + current_context() * CONTEXT_STRIDE + 4,
+        irq as u32,
+    );
+}
+
+/// 登记中断源的处理函数，设置其优先级为 1 并在当前 hart 上使能
+///
+/// 处理函数在中断处理中执行，不能睡眠
+pub fn register_irq(irq: usize, handler: impl Fn() + Send + Sync + 'static) {
+    assert!(irq > 0 && irq < MAX_IRQ, "invalid irq {}", irq);
+    HANDLERS.lock().insert(irq, Arc::new(handler));
+    set_priority(irq, 1);
+    set_enable(current_context(), irq, true);
+}
+
+/// 移除中断源的处理函数，并在当前 hart 上禁用
+pub fn unregister_irq(irq: usize) {
+    set_enable(current_context(), irq, false);
+    HANDLERS.lock().remove(&irq);
+}
+
+/// 处理外部中断，依次处理所有等待中的中断源
+pub fn handle_external() {
+    while let Some(irq) = claim() {
+        // 执行处理函数时不持有锁
+        let handler = HANDLERS.lock().get(&irq).cloned();
+        match handler {
+            Some(handler) => handler(),
+            None => warn!("unhandled external interrupt {}", irq),
+        }
+        complete(irq);
+    }
+}
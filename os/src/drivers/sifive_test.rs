@@ -0,0 +1,64 @@
+//! SiFive test 设备，QEMU virt 平台上用于结束模拟
+//!
+//! 写入 `0x5555` 使 QEMU 以 0 退出；写入 `(code << 16) | 0x3333` 使 QEMU 以 `code` 退出；
+//! 写入 `0x7777` 则重启
+
+use super::devicetree::Node;
+use crate::memory::address::{PhysicalAddress, VirtualAddress};
+use crate::memory::mmio;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// 设备的物理地址，默认为 QEMU virt 平台的布局，使得驱动初始化之前也可以退出
+static BASE: AtomicUsize = AtomicUsize::new(0x10_0000);
+
+/// 设备是否存在
+static PRESENT: AtomicBool = AtomicBool::new(true);
+
+const FINISHER_FAIL: u32 = 0x3333;
+const FINISHER_PASS: u32 = 0x5555;
+const FINISHER_RESET: u32 = 0x7777;
+
+/// 使用位于 `base` 的设备
+pub fn init(base: PhysicalAddress) {
+    mmio::add_region(base, 0x1000);
+    BASE.store(base.0, Ordering::Relaxed);
+    set_present(true);
+}
+
+/// 按照设备树节点初始化
+pub fn probe(node: &Node) -> Result<(), &'static str> {
+    let (base, _) = node.first_reg().ok_or("no reg property")?;
+    init(PhysicalAddress(base));
+    Ok(())
+}
+
+/// 设备是否存在
+pub fn is_present() -> bool {
+    PRESENT.load(Ordering::Relaxed)
+}
+
+/// 记录设备是否存在
+pub fn set_present(present: bool) {
+    PRESENT.store(present, Ordering::Relaxed);
+}
+
+fn write(value: u32) -> ! {
+    unsafe { core::ptr::write_volatile(VirtualAddress::from(PhysicalAddress(BASE.load(Ordering::Relaxed))).deref::<u32>(), value) };
+    // 写入之后 QEMU 就会退出
+    loop {}
+}
+
+/// 使 QEMU 以 0 退出
+pub fn exit_success() -> ! {
+    write(FINISHER_PASS)
+}
+
+/// 使 QEMU 以 `code` 退出，`code` 不能为 0
+pub fn exit_failure(code: u16) -> ! {
+    write(((code as u32) << 16) | FINISHER_FAIL)
+}
+
+/// 重启
+pub fn reset() -> ! {
+    write(FINISHER_RESET)
+}
@@ -0,0 +1,189 @@
+//! NS16550A 串口驱动
+//!
+//! 输出时直接写寄存器；输入通过接收中断放入缓冲区，读取时缓冲区为空则睡眠等待。
+//! 初始化之前（或者没有串口时）控制台仍然通过 SBI 输入输出
+//!
+//! 寄存器说明见 <http://caro.su/msx/ocm_de1/16550.pdf>
+
+use super::devicetree::Node;
+use crate::memory::address::{PhysicalAddress, VirtualAddress};
+use crate::memory::mmio;
+use crate::sync::{Condvar, Lock};
+use alloc::string::String;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+
+/// 接收缓冲寄存器（读）/ 发送保持寄存器（写）
+const RBR_THR: usize = 0;
+/// 中断使能寄存器
+const IER: usize = 1;
+/// FIFO 控制寄存器（写）
+const FCR: usize = 2;
+/// 线路控制寄存器
+const LCR: usize = 3;
+/// Modem 控制寄存器
+const MCR: usize = 4;
+/// 线路状态寄存器
+const LSR: usize = 5;
+
+/// `IER`：接收到数据时产生中断
+const IER_RECEIVE: u8 = 1 << 0;
+/// `FCR`：启用并清空 FIFO
+const FCR_ENABLE_CLEAR: u8 = 0b111;
+/// `LCR`：8 位数据，1 位停止位，无校验
+const LCR_8N1: u8 = 0b11;
+/// `MCR`：DTR、RTS 和 OUT2，OUT2 用于将中断送出芯片
+const MCR_DTR_RTS_OUT2: u8 = 0b1011;
+/// `LSR`：有数据可读
+const LSR_DATA_READY: u8 = 1 << 0;
+/// `LSR`：发送保持寄存器为空
+const LSR_THR_EMPTY: u8 = 1 << 5;
+
+/// 输入缓冲区的大小，缓冲区满时丢弃新的输入
+const BUFFER_SIZE: usize = 256;
+
+/// 串口寄存器的虚拟地址，为 0 表示尚未初始化
+static BASE: AtomicUsize = AtomicUsize::new(0);
+
+/// 环形输入缓冲区
+struct RingBuffer {
+    data: [u8; BUFFER_SIZE],
+    /// 下一个读取的位置
+    head: usize,
+    /// 已有的字节数
+    len: usize,
+}
+
+impl RingBuffer {
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == BUFFER_SIZE {
+            return false;
+        }
+        self.data[(self.head + self.len) % BUFFER_SIZE] = byte;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.data[self.head];
+        self.head = (self.head + 1) % BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+lazy_static! {
+    /// 由接收中断填充的输入缓冲区
+    static ref INPUT: Lock<RingBuffer> = Lock::new(RingBuffer {
+        data: [0; BUFFER_SIZE],
+        head: 0,
+        len: 0,
+    });
+    /// 等待输入的线程
+    static ref INPUT_CONDVAR: Condvar = Condvar::new();
+}
+
+fn read(offset: usize) -> u8 {
+    let address = VirtualAddress(BASE.load(Ordering::Relaxed) + offset);
+    unsafe { core::ptr::read_volatile(address.deref::<u8>()) }
+}
+
+fn write(offset: usize, value: u8) {
+    let address = VirtualAddress(BASE.load(Ordering::Relaxed) + offset);
+    unsafe { core::ptr::write_volatile(address.deref::<u8>(), value) }
+}
+
+/// 初始化串口，并通过 PLIC 接收 `irq` 号中断
+pub fn init(base: PhysicalAddress, irq: usize) {
+    mmio::add_region(base, 0x1000);
+    BASE.store(VirtualAddress::from(base).0, Ordering::Relaxed);
+    write(IER, 0);
+    write(LCR, LCR_8N1);
+    write(FCR, FCR_ENABLE_CLEAR);
+    write(MCR, MCR_DTR_RTS_OUT2);
+    write(IER, IER_RECEIVE);
+    super::plic::register_irq(irq, handle_irq);
+}
+
+/// 按照设备树节点初始化
+pub fn probe(node: &Node) -> Result<(), &'static str> {
+    if is_ready() {
+        return Err("only one UART is supported");
+    }
+    let (base, _) = node.first_reg().ok_or("no reg property")?;
+    let irq = node.interrupt().ok_or("no interrupts property")?;
+    init(PhysicalAddress(base), irq);
+    Ok(())
+}
+
+/// 串口是否已经初始化
+pub fn is_ready() -> bool {
+    BASE.load(Ordering::Relaxed) != 0
+}
+
+/// 输出一个字节，等待发送保持寄存器为空
+pub fn putchar(byte: u8) {
+    while read(LSR) & LSR_THR_EMPTY == 0 {}
+    write(RBR_THR, byte);
+}
+
+/// 接收中断，将所有可读的字节放入缓冲区并唤醒等待的线程
+fn handle_irq() {
+    let mut received = false;
+    {
+        let mut input = INPUT.lock();
+        while read(LSR) & LSR_DATA_READY != 0 {
+            received |= input.push(read(RBR_THR));
+        }
+    }
+    if received {
+        INPUT_CONDVAR.notify_all();
+    }
+}
+
+/// 从缓冲区取出一个字节，没有输入时返回 `None`
+pub fn try_getchar() -> Option<u8> {
+    INPUT.lock().pop()
+}
+
+/// 读取一个字节，没有输入时睡眠等待
+///
+/// 只能在线程中调用
+pub fn getchar() -> u8 {
+    let mut input = INPUT.lock();
+    loop {
+        if let Some(byte) = input.pop() {
+            return byte;
+        }
+        input = INPUT_CONDVAR.wait_lock(input);
+    }
+}
+
+/// 读取一行输入并回显，返回不含换行的内容
+///
+/// 只能在线程中调用
+pub fn read_line() -> String {
+    let mut line = String::new();
+    loop {
+        match getchar() {
+            b'\r' | b'\n' => {
+                print!("\n");
+                return line;
+            }
+            // 退格键
+            0x08 | 0x7f => {
+                if line.pop().is_some() {
+                    print!("\x08 \x08");
+                }
+            }
+            byte if byte.is_ascii_graphic() || byte == b' ' => {
+                line.push(byte as char);
+                print!("{}", byte as char);
+            }
+            _ => {}
+        }
+    }
+}
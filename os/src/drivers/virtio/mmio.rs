@@ -0,0 +1,178 @@
+//! virtio-mmio 传输层
+//!
+//! 同时支持旧版（version 1）和新版（version 2）的寄存器布局，QEMU 默认使用旧版。
+//! 寄存器说明见 virtio 规范 1.1 的 4.2.2 和 4.2.4 节
+
+use super::queue::VirtQueue;
+use crate::memory::address::{PhysicalAddress, VirtualAddress};
+use crate::memory::config::PAGE_SIZE;
+
+/// `MagicValue` 应当为 "virt"
+const MAGIC: u32 = 0x7472_6976;
+
+const MAGIC_VALUE: usize = 0x000;
+const VERSION: usize = 0x004;
+const DEVICE_ID: usize = 0x008;
+const DEVICE_FEATURES: usize = 0x010;
+const DEVICE_FEATURES_SEL: usize = 0x014;
+const DRIVER_FEATURES: usize = 0x020;
+const DRIVER_FEATURES_SEL: usize = 0x024;
+/// 旧版：客户机的页大小
+const GUEST_PAGE_SIZE: usize = 0x028;
+const QUEUE_SEL: usize = 0x030;
+const QUEUE_NUM_MAX: usize = 0x034;
+const QUEUE_NUM: usize = 0x038;
+/// 旧版：已用环的对齐
+const QUEUE_ALIGN: usize = 0x03c;
+/// 旧版：队列所在的物理页号
+const QUEUE_PFN: usize = 0x040;
+/// 新版：队列是否可用
+const QUEUE_READY: usize = 0x044;
+const QUEUE_NOTIFY: usize = 0x050;
+const INTERRUPT_STATUS: usize = 0x060;
+const INTERRUPT_ACK: usize = 0x064;
+const STATUS: usize = 0x070;
+/// 新版：描述符表、可用环、已用环的物理地址
+const QUEUE_DESC_LOW: usize = 0x080;
+const QUEUE_DESC_HIGH: usize = 0x084;
+const QUEUE_AVAIL_LOW: usize = 0x090;
+const QUEUE_AVAIL_HIGH: usize = 0x094;
+const QUEUE_USED_LOW: usize = 0x0a0;
+const QUEUE_USED_HIGH: usize = 0x0a4;
+/// 设备相关的配置空间
+const CONFIG: usize = 0x100;
+
+/// 设备状态位
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+const STATUS_FAILED: u32 = 128;
+
+/// 新版设备必须协商的特性 `VIRTIO_F_VERSION_1`，即第 32 位
+const FEATURE_VERSION_1: u32 = 1 << 0;
+
+/// 设备类型
+pub const DEVICE_ID_BLOCK: u32 = 2;
+
+/// 一个 virtio-mmio 设备的寄存器
+pub struct MmioTransport {
+    /// 寄存器的虚拟地址
+    base: usize,
+    /// 寄存器布局的版本
+    version: u32,
+}
+
+impl MmioTransport {
+    /// 检查位于 `base` 的寄存器，没有设备（设备类型为 0）时返回 `None`
+    pub fn new(base: PhysicalAddress) -> Result<Option<Self>, &'static str> {
+        let transport = MmioTransport {
+            base: VirtualAddress::from(base).0,
+            version: 0,
+        };
+        if transport.read(MAGIC_VALUE) != MAGIC {
+            return Err("bad magic value");
+        }
+        let version = transport.read(VERSION);
+        if version != 1 && version != 2 {
+            return Err("unsupported virtio-mmio version");
+        }
+        if transport.read(DEVICE_ID) == 0 {
+            return Ok(None);
+        }
+        Ok(Some(MmioTransport { version, ..transport }))
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        unsafe { core::ptr::read_volatile(VirtualAddress(self.base + offset).deref()) }
+    }
+
+    fn write(&self, offset: usize, value: u32) {
+        unsafe { core::ptr::write_volatile(VirtualAddress(self.base + offset).deref(), value) }
+    }
+
+    /// 设备类型
+    pub fn device_id(&self) -> u32 {
+        self.read(DEVICE_ID)
+    }
+
+    /// 重置设备并协商特性，驱动不使用任何可选的特性
+    ///
+    /// 之后需要通过 [`setup_queue`](Self::setup_queue) 设置队列，再调用 [`finish_init`](Self::finish_init)
+    pub fn begin_init(&self) -> Result<(), &'static str> {
+        self.write(STATUS, 0);
+        self.write(STATUS, STATUS_ACKNOWLEDGE);
+        self.write(STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+        // 低 32 位的特性全部不使用
+        self.write(DRIVER_FEATURES_SEL, 0);
+        self.write(DRIVER_FEATURES, 0);
+        if self.version == 1 {
+            self.write(GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+        } else {
+            self.write(DEVICE_FEATURES_SEL, 1);
+            if self.read(DEVICE_FEATURES) & FEATURE_VERSION_1 == 0 {
+                self.write(STATUS, STATUS_FAILED);
+                return Err("device does not offer VIRTIO_F_VERSION_1");
+            }
+            self.write(DRIVER_FEATURES_SEL, 1);
+            self.write(DRIVER_FEATURES, FEATURE_VERSION_1);
+            let status = STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK;
+            self.write(STATUS, status);
+            if self.read(STATUS) & STATUS_FEATURES_OK == 0 {
+                self.write(STATUS, STATUS_FAILED);
+                return Err("features are not accepted");
+            }
+        }
+        Ok(())
+    }
+
+    /// 设备支持的最大队列长度，为 0 表示队列不存在
+    pub fn max_queue_size(&self, index: u32) -> u32 {
+        self.write(QUEUE_SEL, index);
+        self.read(QUEUE_NUM_MAX)
+    }
+
+    /// 将 `queue` 设置为第 `index` 个队列
+    pub fn setup_queue(&self, index: u32, queue: &VirtQueue) {
+        self.write(QUEUE_SEL, index);
+        self.write(QUEUE_NUM, queue.size() as u32);
+        let (desc, avail, used) = queue.addresses();
+        if self.version == 1 {
+            // 旧版要求三部分连续存放，设备按照 QueueAlign 计算已用环的位置，
+            // 因此必须与队列实际的布局一致
+            self.write(QUEUE_ALIGN, VirtQueue::USED_ALIGN as u32);
+            self.write(QUEUE_PFN, (desc.0 / PAGE_SIZE) as u32);
+        } else {
+            self.write(QUEUE_DESC_LOW, desc.0 as u32);
+            self.write(QUEUE_DESC_HIGH, (desc.0 >> 32) as u32);
+            self.write(QUEUE_AVAIL_LOW, avail.0 as u32);
+            self.write(QUEUE_AVAIL_HIGH, (avail.0 >> 32) as u32);
+            self.write(QUEUE_USED_LOW, used.0 as u32);
+            self.write(QUEUE_USED_HIGH, (used.0 >> 32) as u32);
+            self.write(QUEUE_READY, 1);
+        }
+    }
+
+    /// 完成初始化，设备开始工作
+    pub fn finish_init(&self) {
+        let status = self.read(STATUS);
+        self.write(STATUS, status | STATUS_DRIVER_OK);
+    }
+
+    /// 通知设备第 `index` 个队列中有新的请求
+    pub fn notify(&self, index: u32) {
+        self.write(QUEUE_NOTIFY, index);
+    }
+
+    /// 读取并确认中断，返回中断状态
+    pub fn ack_interrupt(&self) -> u32 {
+        let status = self.read(INTERRUPT_STATUS);
+        self.write(INTERRUPT_ACK, status);
+        status
+    }
+
+    /// 读取配置空间中偏移为 `offset` 的 u32
+    pub fn config_u32(&self, offset: usize) -> u32 {
+        self.read(CONFIG + offset)
+    }
+}
@@ -0,0 +1,45 @@
+//! virtio 设备
+//!
+//! QEMU virt 平台提供若干个 virtio-mmio 插槽，每个插槽在设备树中是一个 `virtio,mmio` 节点，
+//! 没有插入设备的插槽设备类型为 0。[`probe`] 按照设备类型交给对应的驱动
+
+mod mmio;
+mod queue;
+
+pub use mmio::MmioTransport;
+pub use queue::VirtQueue;
+
+use super::block::{self, BlockDevice, VirtIOBlock};
+use super::devicetree::Node;
+use super::plic;
+use crate::memory::address::PhysicalAddress;
+use crate::memory::mmio as mmio_regions;
+use alloc::sync::Arc;
+
+/// 按照设备树节点初始化一个 virtio-mmio 插槽
+pub fn probe(node: &Node) -> Result<(), &'static str> {
+    let (base, len) = node.first_reg().ok_or("no reg property")?;
+    let irq = node.interrupt().ok_or("no interrupts property")?;
+    let base = PhysicalAddress(base);
+    let transport = match MmioTransport::new(base)? {
+        Some(transport) => transport,
+        // 空的插槽
+        None => return Ok(()),
+    };
+    match transport.device_id() {
+        mmio::DEVICE_ID_BLOCK => {
+            let device = Arc::new(VirtIOBlock::new(transport)?);
+            mmio_regions::add_region(base, len);
+            let handler = device.clone();
+            plic::register_irq(irq, move || handler.handle_irq());
+            info!(
+                "virtio-blk at {}: {} blocks, registered as block device {}",
+                base,
+                device.num_blocks(),
+                block::register(device.clone()),
+            );
+        }
+        id => info!("virtio device type {} at {} is not supported", id, base),
+    }
+    Ok(())
+}
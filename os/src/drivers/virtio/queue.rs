@@ -0,0 +1,183 @@
+//! split virtqueue
+//!
+//! 描述符表和可用环放在第一个物理页中，已用环放在紧接着的下一个物理页：
+//! - 驱动将请求的缓冲区串成描述符链，把链头放入可用环，再通知设备
+//! - 设备处理完毕后把链头放入已用环，并产生中断
+//!
+//! 结构说明见 virtio 规范 1.1 的 2.6 节。旧版设备由队列的起始页号和 `QueueAlign` 自行计算
+//! 已用环的位置（规范 1.1 的 2.6.2 节），因此布局必须与之一致
+
+use crate::memory::address::{PhysicalAddress, VirtualAddress};
+use crate::memory::config::PAGE_SIZE;
+use crate::memory::frame::{self, FrameTracker};
+use crate::memory::MemoryResult;
+use alloc::vec::Vec;
+use core::sync::atomic::{fence, Ordering};
+
+/// 描述符：后面还有描述符
+const DESC_F_NEXT: u16 = 1;
+/// 描述符：缓冲区由设备写入
+const DESC_F_WRITE: u16 = 2;
+
+/// 描述符表的一项
+#[repr(C)]
+struct Descriptor {
+    address: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+/// 已用环的一项
+#[repr(C)]
+struct UsedElement {
+    /// 描述符链的链头
+    id: u32,
+    /// 设备写入的字节数
+    len: u32,
+}
+
+/// 一个 split virtqueue
+pub struct VirtQueue {
+    /// 存放描述符表、可用环和已用环的两个连续的物理页
+    frames: Vec<FrameTracker>,
+    /// 队列长度
+    size: u16,
+    /// 空闲描述符链表的头
+    free_head: u16,
+    /// 空闲描述符的数量
+    num_free: u16,
+    /// 下一个放入可用环的位置
+    avail_index: u16,
+    /// 下一个从已用环取出的位置
+    last_used_index: u16,
+}
+
+impl VirtQueue {
+    /// 已用环的对齐，旧版设备通过 `QueueAlign` 得知。按页对齐时已用环总是从第二页开始
+    pub const USED_ALIGN: usize = PAGE_SIZE;
+
+    /// 最大的队列长度，保证描述符表和可用环可以放在一个物理页中
+    pub const MAX_SIZE: u16 = 64;
+
+    /// 创建长度为 `size` 的队列，`size` 必须是 2 的幂
+    pub fn new(size: u16) -> MemoryResult<Self> {
+        assert!(size.is_power_of_two() && size <= Self::MAX_SIZE);
+        let mut frames = frame::alloc_contiguous(2)?;
+        for frame in frames.iter_mut() {
+            frame.fill(0);
+        }
+        let mut queue = VirtQueue {
+            frames,
+            size,
+            free_head: 0,
+            num_free: size,
+            avail_index: 0,
+            last_used_index: 0,
+        };
+        // 所有描述符串成空闲链表
+        for i in 0..size - 1 {
+            queue.descriptor(i).next = i + 1;
+        }
+        Ok(queue)
+    }
+
+    /// 队列长度
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// 空闲描述符的数量
+    pub fn num_free(&self) -> u16 {
+        self.num_free
+    }
+
+    /// 可用环在页内的偏移
+    fn avail_offset(&self) -> usize {
+        16 * self.size as usize
+    }
+
+    /// 已用环相对于队列起始的偏移
+    fn used_offset(&self) -> usize {
+        let avail_end = self.avail_offset() + 6 + 2 * self.size as usize;
+        (avail_end + Self::USED_ALIGN - 1) / Self::USED_ALIGN * Self::USED_ALIGN
+    }
+
+    /// 描述符表、可用环和已用环的物理地址
+    pub fn addresses(&self) -> (PhysicalAddress, PhysicalAddress, PhysicalAddress) {
+        let base = self.frames[0].address();
+        (base, base + self.avail_offset(), base + self.used_offset())
+    }
+
+    /// 相对于队列起始偏移 `offset` 处的 `T`，两个物理页在线性映射中同样连续
+    fn at<T>(&self, offset: usize) -> *mut T {
+        (VirtualAddress::from(self.frames[0].address()).0 + offset) as *mut T
+    }
+
+    fn descriptor(&mut self, index: u16) -> &mut Descriptor {
+        unsafe { &mut *self.at::<Descriptor>(16 * index as usize) }
+    }
+
+    /// 将 `buffers`（物理地址，长度，是否由设备写入）串成描述符链放入可用环，返回链头
+    ///
+    /// 空闲描述符不足时返回 `None`。调用者需要随后通知设备
+    pub fn add(&mut self, buffers: &[(PhysicalAddress, usize, bool)]) -> Option<u16> {
+        if buffers.is_empty() || buffers.len() > self.num_free as usize {
+            return None;
+        }
+        let head = self.free_head;
+        let mut last = head;
+        for (i, &(address, len, device_writable)) in buffers.iter().enumerate() {
+            let index = if i == 0 { head } else { self.descriptor(last).next };
+            let descriptor = self.descriptor(index);
+            descriptor.address = address.0 as u64;
+            descriptor.len = len as u32;
+            descriptor.flags = if device_writable { DESC_F_WRITE } else { 0 };
+            if i + 1 < buffers.len() {
+                descriptor.flags |= DESC_F_NEXT;
+            }
+            last = index;
+        }
+        self.free_head = self.descriptor(last).next;
+        self.num_free -= buffers.len() as u16;
+
+        // 放入可用环，描述符写入完成之后才能更新 idx
+        let slot = self.avail_offset() + 4 + 2 * (self.avail_index % self.size) as usize;
+        unsafe { self.at::<u16>(slot).write_volatile(head) };
+        fence(Ordering::SeqCst);
+        self.avail_index = self.avail_index.wrapping_add(1);
+        unsafe { self.at::<u16>(self.avail_offset() + 2).write_volatile(self.avail_index) };
+        fence(Ordering::SeqCst);
+        Some(head)
+    }
+
+    /// 从已用环中取出一个处理完毕的描述符链，返回链头和设备写入的字节数
+    ///
+    /// 描述符不会被回收，需要再调用 [`recycle`](Self::recycle)
+    pub fn pop_used(&mut self) -> Option<(u16, u32)> {
+        let used_index = unsafe { self.at::<u16>(self.used_offset() + 2).read_volatile() };
+        if used_index == self.last_used_index {
+            return None;
+        }
+        fence(Ordering::SeqCst);
+        let slot = self.used_offset() + 4 + 8 * (self.last_used_index % self.size) as usize;
+        let element = unsafe { self.at::<UsedElement>(slot).read_volatile() };
+        self.last_used_index = self.last_used_index.wrapping_add(1);
+        Some((element.id as u16, element.len))
+    }
+
+    /// 回收以 `head` 为链头的描述符链
+    pub fn recycle(&mut self, head: u16) {
+        let mut index = head;
+        loop {
+            self.num_free += 1;
+            let descriptor = self.descriptor(index);
+            if descriptor.flags & DESC_F_NEXT == 0 {
+                descriptor.next = self.free_head;
+                break;
+            }
+            index = descriptor.next;
+        }
+        self.free_head = head;
+    }
+}
@@ -6,10 +6,30 @@
 # 关于 RISC-V 下的汇编语言，可以参考 https://github.com/riscv/riscv-asm-manual/blob/master/riscv-asm.md
 # %hi 表示取 [12,32) 位，%lo 表示取 [0,12) 位
 
+# 每个 hart 的启动栈大小（64K）
+.set    BOOT_STACK_SIZE, 4096 * 16
+# 最多支持的 hart 数量，需要与 hart::MAX_HARTS 一致
+.set    MAX_HARTS, 8
+
 	.section .text.entry
 	.global _start
-# 目前 _start 的功能: 将预留的栈空间写入 $sp, 然后跳转至 rust_main
+# 启动 hart 的入口，a0 为 hart 编号，a1 为设备树的物理地址
 _start:
+    lui t2, %hi(rust_main)
+    addi t2, t2, %lo(rust_main)
+    j boot
+
+    .global _secondary_start
+# 其他 hart 由启动 hart 通过 SBI HSM 扩展启动，a0 为 hart 编号，a1 为启动时传入的参数
+_secondary_start:
+    lui t2, %hi(rust_main_secondary)
+    addi t2, t2, %lo(rust_main_secondary)
+
+# 启用页表，设置 tp 和栈之后跳转至 t2
+boot:
+    # tp 保存 hart 编号
+    mv tp, a0
+
     # 计算 boot_page_table 的物理页号
     lui t0, %hi(boot_page_table)
     li t1, 0xffffffff00000000
@@ -22,21 +42,23 @@ _start:
     csrw satp, t0
     sfence.vma
 
-    # 加载栈地址
-    lui sp, %hi(boot_stack_top)
-    addi sp, sp, %lo(boot_stack_top)
-    # 跳转至 rust_main
-    lui t0, %hi(rust_main)
-    addi t0, t0, %lo(rust_main)
-    jr t0
+    # 加载栈地址，hart i 使用 boot_stack 中的第 i 段
+    lui sp, %hi(boot_stack)
+    addi sp, sp, %lo(boot_stack)
+    addi t0, tp, 1
+    li t1, BOOT_STACK_SIZE
+    mul t0, t0, t1
+    add sp, sp, t0
+    # 跳转至 rust_main 或 rust_main_secondary
+    jr t2
 
     # 回忆：bss 段是 ELF 文件中只记录长度，而全部初始化为 0 的一段内存空间
     # 这里声明字段 .bss.stack 作为操作系统启动时的栈
     .section .bss.stack
     .global boot_stack
 boot_stack:
-    # 16K 启动栈大小
-    .space 4096 * 16
+    # 每个 hart 64K 启动栈
+    .space BOOT_STACK_SIZE * MAX_HARTS
     .global boot_stack_top
 boot_stack_top:
     # 栈结尾
@@ -49,7 +71,10 @@ boot_page_table:
     .quad 0
     # 第 2 项：0x8000_0000 -> 0x8000_0000，0xcf 表示 VRWXAD 均为 1
     .quad (0x80000 << 10) | 0xcf
-    .zero 507 * 8
+    .zero 505 * 8
+    # 第 508 项：0xffff_ffff_0000_0000 -> 0x0000_0000，用于访问 MMIO，0xc7 表示 VRWAD 均为 1
+    .quad (0x00000 << 10) | 0xc7
+    .quad 0
     # 第 510 项：0xffff_ffff_8000_0000 -> 0x8000_0000，0xcf 表示 VRWXAD 均为 1
     .quad (0x80000 << 10) | 0xcf
-    .quad 0
\ No newline at end of file
+    .quad 0
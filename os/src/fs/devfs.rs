@@ -0,0 +1,258 @@
+//! 设备文件系统 devfs，挂载在 `/dev`
+//!
+//! - `console`：控制台，读写同 [`Stdin`] 和 [`Stdout`]
+//! - `null`：读取时总是到达末尾，写入的数据被丢弃
+//! - `zero`：读取到的总是 0
+//! - `random`：伪随机数，不能用于密码学用途
+//! - `block<编号>`：[`block`] 中登记的块设备，通过块缓存按字节读写
+//!
+//! 目录的内容是固定的，不能创建或删除文件
+
+use super::*;
+use crate::drivers::block::{BlockDevice, BLOCK_SIZE};
+use crate::interrupt::timer;
+use crate::sync::Lock;
+use alloc::string::ToString;
+use alloc::vec;
+
+/// 根目录的 inode 编号
+const ROOT_INODE: usize = 1;
+
+/// 块设备的 inode 编号从这里开始
+const BLOCK_INODE_BASE: usize = 16;
+
+/// 伪随机数发生器的状态，为 0 表示尚未初始化
+static RANDOM_STATE: Lock<u64> = Lock::new(0);
+
+/// devfs 中的设备
+#[derive(Clone)]
+enum Device {
+    Console,
+    Null,
+    Zero,
+    Random,
+    Block(usize, Arc<BlockCache>),
+}
+
+impl Device {
+    /// 设备的文件名和 inode 编号
+    fn name_and_inode(&self) -> (String, usize) {
+        match self {
+            Device::Console => ("console".to_string(), 2),
+            Device::Null => ("null".to_string(), 3),
+            Device::Zero => ("zero".to_string(), 4),
+            Device::Random => ("random".to_string(), 5),
+            Device::Block(index, _) => (format!("block{}", index), BLOCK_INODE_BASE + index),
+        }
+    }
+}
+
+/// 所有设备，块设备在最后
+fn devices() -> Vec<Device> {
+    let mut devices = vec![Device::Console, Device::Null, Device::Zero, Device::Random];
+    devices.extend(
+        (0..block::count()).filter_map(|index| Some(Device::Block(index, block::get(index)?))),
+    );
+    devices
+}
+
+/// devfs 文件系统
+pub struct DevFs {
+    dev: usize,
+}
+
+impl DevFs {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { dev: alloc_dev() })
+    }
+}
+
+impl FileSystem for DevFs {
+    fn name(&self) -> &'static str {
+        "devfs"
+    }
+
+    fn root(&self) -> Arc<dyn INode> {
+        Arc::new(DevRoot { dev: self.dev })
+    }
+}
+
+/// devfs 的根目录
+struct DevRoot {
+    dev: usize,
+}
+
+impl INode for DevRoot {
+    fn metadata(&self) -> Result<Metadata, isize> {
+        Ok(Metadata {
+            dev: self.dev,
+            inode: ROOT_INODE,
+            file_type: FileType::Directory,
+            mode: 0o755,
+            nlinks: 2,
+            size: 0,
+            blocks: 0,
+        })
+    }
+
+    fn read_at(&self, _offset: usize, _buffer: &mut [u8]) -> Result<usize, isize> {
+        Err(EISDIR)
+    }
+
+    fn write_at(&self, _offset: usize, _buffer: &[u8]) -> Result<usize, isize> {
+        Err(EISDIR)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn INode>, isize> {
+        let device = devices()
+            .into_iter()
+            .find(|device| device.name_and_inode().0 == name)
+            .ok_or(ENOENT)?;
+        Ok(Arc::new(DevINode {
+            dev: self.dev,
+            device,
+        }))
+    }
+
+    fn create(&self, _name: &str, _file_type: FileType) -> Result<Arc<dyn INode>, isize> {
+        Err(EACCES)
+    }
+
+    fn unlink(&self, _name: &str) -> Result<(), isize> {
+        Err(EACCES)
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, isize> {
+        Ok(devices().get(index).map(|device| {
+            let (name, inode) = device.name_and_inode();
+            DirEntry {
+                name,
+                inode,
+                file_type: match device {
+                    Device::Block(..) => FileType::BlockDevice,
+                    _ => FileType::CharDevice,
+                },
+            }
+        }))
+    }
+}
+
+/// 一个设备文件
+struct DevINode {
+    dev: usize,
+    device: Device,
+}
+
+impl INode for DevINode {
+    fn metadata(&self) -> Result<Metadata, isize> {
+        let (file_type, mode, size) = match &self.device {
+            Device::Console => (FileType::CharDevice, 0o620, 0),
+            Device::Block(_, disk) => {
+                (FileType::BlockDevice, 0o660, disk.num_blocks() * BLOCK_SIZE)
+            }
+            _ => (FileType::CharDevice, 0o666, 0),
+        };
+        Ok(Metadata {
+            dev: self.dev,
+            inode: self.device.name_and_inode().1,
+            file_type,
+            mode,
+            nlinks: 1,
+            size,
+            blocks: 0,
+        })
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, isize> {
+        match &self.device {
+            Device::Console => Stdin.read(buffer),
+            Device::Null => Ok(0),
+            Device::Zero => {
+                buffer.fill(0);
+                Ok(buffer.len())
+            }
+            Device::Random => {
+                fill_random(buffer);
+                Ok(buffer.len())
+            }
+            Device::Block(_, disk) => read_disk(disk, offset, buffer),
+        }
+    }
+
+    fn write_at(&self, offset: usize, buffer: &[u8]) -> Result<usize, isize> {
+        match &self.device {
+            Device::Console => Stdout.write(buffer),
+            Device::Block(_, disk) => write_disk(disk, offset, buffer),
+            _ => Ok(buffer.len()),
+        }
+    }
+
+    /// 设备的大小不能改变，以 `O_TRUNC` 打开时直接忽略
+    fn resize(&self, _len: usize) -> Result<(), isize> {
+        Ok(())
+    }
+
+    fn sync(&self) -> Result<(), isize> {
+        match &self.device {
+            Device::Block(_, disk) => disk.sync().map_err(|_| EIO),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 用 xorshift64* 生成伪随机数填满 `buffer`，第一次使用时以当前时间为种子
+fn fill_random(buffer: &mut [u8]) {
+    let mut state = RANDOM_STATE.lock();
+    if *state == 0 {
+        *state = timer::now_ns() ^ 0x9e37_79b9_7f4a_7c15;
+    }
+    for chunk in buffer.chunks_mut(8) {
+        *state ^= *state >> 12;
+        *state ^= *state << 25;
+        *state ^= *state >> 27;
+        let value = state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        chunk.copy_from_slice(&value.to_ne_bytes()[..chunk.len()]);
+    }
+}
+
+/// 从块设备的 `offset` 字节处开始读取，到达设备末尾时返回 0
+fn read_disk(disk: &BlockCache, offset: usize, buffer: &mut [u8]) -> Result<usize, isize> {
+    let end = (disk.num_blocks() * BLOCK_SIZE).min(offset.saturating_add(buffer.len()));
+    let mut block = [0u8; BLOCK_SIZE];
+    let mut position = offset;
+    while position < end {
+        disk.read_block(position / BLOCK_SIZE, &mut block)
+            .map_err(|_| EIO)?;
+        let start = position % BLOCK_SIZE;
+        let len = (BLOCK_SIZE - start).min(end - position);
+        buffer[position - offset..position - offset + len]
+            .copy_from_slice(&block[start..start + len]);
+        position += len;
+    }
+    Ok(end.saturating_sub(offset))
+}
+
+/// 从块设备的 `offset` 字节处开始写入，不完整的块先读出再修改，超出设备末尾时返回 `ENOSPC`
+fn write_disk(disk: &BlockCache, offset: usize, buffer: &[u8]) -> Result<usize, isize> {
+    let size = disk.num_blocks() * BLOCK_SIZE;
+    if offset >= size && !buffer.is_empty() {
+        return Err(ENOSPC);
+    }
+    let end = size.min(offset.saturating_add(buffer.len()));
+    let mut block = [0u8; BLOCK_SIZE];
+    let mut position = offset;
+    while position < end {
+        let start = position % BLOCK_SIZE;
+        let len = (BLOCK_SIZE - start).min(end - position);
+        if len < BLOCK_SIZE {
+            disk.read_block(position / BLOCK_SIZE, &mut block)
+                .map_err(|_| EIO)?;
+        }
+        block[start..start + len]
+            .copy_from_slice(&buffer[position - offset..position - offset + len]);
+        disk.write_block(position / BLOCK_SIZE, &block)
+            .map_err(|_| EIO)?;
+        position += len;
+    }
+    Ok(end - offset.min(end))
+}
@@ -0,0 +1,109 @@
+//! 引导扇区中的 BIOS 参数块（BPB）
+
+use crate::drivers::block::BLOCK_SIZE;
+
+/// 读取小端序的 16 位整数
+pub fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// 读取小端序的 32 位整数
+pub fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+/// 写入小端序的 16 位整数
+pub fn write_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+/// 写入小端序的 32 位整数
+pub fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// FAT32 卷的参数，只支持 512 字节的扇区
+#[derive(Clone, Copy, Debug)]
+pub struct BiosParameterBlock {
+    pub sectors_per_cluster: usize,
+    pub reserved_sectors: usize,
+    pub num_fats: usize,
+    pub sectors_per_fat: usize,
+    pub total_sectors: usize,
+    pub root_cluster: u32,
+    /// FSInfo 所在的扇区，为 0 表示没有
+    pub fs_info_sector: usize,
+}
+
+impl BiosParameterBlock {
+    /// 解析引导扇区，不是 FAT32 时返回 `None`
+    ///
+    /// 与 Linux 相同，以 16 位的 FAT 大小为 0 判断是 FAT32，而不是按照簇的数量
+    pub fn parse(sector: &[u8]) -> Option<Self> {
+        if sector[510] != 0x55 || sector[511] != 0xaa {
+            return None;
+        }
+        let bytes_per_sector = read_u16(sector, 11) as usize;
+        let sectors_per_cluster = sector[13] as usize;
+        let root_entries = read_u16(sector, 17);
+        let sectors_per_fat_16 = read_u16(sector, 22);
+        let total_sectors = match read_u16(sector, 19) {
+            0 => read_u32(sector, 32) as usize,
+            sectors => sectors as usize,
+        };
+        let bpb = Self {
+            sectors_per_cluster,
+            reserved_sectors: read_u16(sector, 14) as usize,
+            num_fats: sector[16] as usize,
+            sectors_per_fat: read_u32(sector, 36) as usize,
+            total_sectors,
+            root_cluster: read_u32(sector, 44),
+            fs_info_sector: read_u16(sector, 48) as usize,
+        };
+        let valid = bytes_per_sector == BLOCK_SIZE
+            && sectors_per_cluster.is_power_of_two()
+            && bpb.reserved_sectors != 0
+            && bpb.num_fats != 0
+            && root_entries == 0
+            && sectors_per_fat_16 == 0
+            && bpb.sectors_per_fat != 0
+            && bpb.total_sectors > bpb.first_data_sector()
+            && bpb.cluster_count() != 0
+            && bpb.root_cluster >= 2
+            && (bpb.root_cluster as usize) < bpb.cluster_count() + 2;
+        if valid {
+            Some(bpb)
+        } else {
+            None
+        }
+    }
+
+    /// 第一个 FAT 的起始扇区
+    pub fn fat_start(&self) -> usize {
+        self.reserved_sectors
+    }
+
+    /// 数据区的起始扇区，即 2 号簇所在的扇区
+    pub fn first_data_sector(&self) -> usize {
+        self.reserved_sectors + self.num_fats * self.sectors_per_fat
+    }
+
+    /// 数据区中簇的数量，簇号从 2 开始
+    pub fn cluster_count(&self) -> usize {
+        let data_sectors = self.total_sectors - self.first_data_sector();
+        // FAT 中的表项也限制了簇的数量
+        (data_sectors / self.sectors_per_cluster).min(self.sectors_per_fat * BLOCK_SIZE / 4 - 2)
+    }
+
+    /// 簇的大小，单位为字节
+    pub fn cluster_size(&self) -> usize {
+        self.sectors_per_cluster * BLOCK_SIZE
+    }
+
+    /// 簇的起始扇区
+    pub fn cluster_sector(&self, cluster: u32) -> usize {
+        self.first_data_sector() + (cluster as usize - 2) * self.sectors_per_cluster
+    }
+}
@@ -0,0 +1,498 @@
+//! 目录项，包括长文件名（LFN）
+//!
+//! 每个文件有一个 32 字节的短目录项，记录 8.3 格式的名称、属性、起始簇和大小。
+//! 名称不符合 8.3 格式时，在短目录项之前倒序存放若干个长文件名项，每项 13 个 UTF-16 字符
+
+use super::bpb::*;
+use super::volume::Volume;
+use crate::drivers::block::BLOCK_SIZE;
+use crate::kernel::errno::*;
+use alloc::{string::String, vec::Vec};
+
+/// 目录项的大小
+pub const ENTRY_SIZE: usize = 32;
+
+/// 属性：只读、隐藏、系统、卷标、目录、归档
+pub const ATTR_VOLUME_ID: u8 = 0x08;
+pub const ATTR_DIRECTORY: u8 = 0x10;
+pub const ATTR_ARCHIVE: u8 = 0x20;
+/// 长文件名项的属性
+const ATTR_LONG_NAME: u8 = 0x0f;
+
+/// 名称第一个字节为此值表示已删除
+const DELETED: u8 = 0xe5;
+
+/// 长文件名项中最后一项的标记
+const LAST_LONG_ENTRY: u8 = 0x40;
+
+/// 每个长文件名项中的字符数
+const CHARS_PER_LONG_ENTRY: usize = 13;
+
+/// 长文件名项中各字符的字节偏移
+const LONG_NAME_OFFSETS: [usize; CHARS_PER_LONG_ENTRY] =
+    [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// 长文件名的最大长度（UTF-16 字符）
+const NAME_LENGTH_LIMIT: usize = 255;
+
+/// 短目录项中的小写标记（Windows NT 使用）
+const LOWERCASE_BASE: u8 = 0x08;
+const LOWERCASE_EXTENSION: u8 = 0x10;
+
+/// FAT 中日期的最小值 1980-01-01
+const DEFAULT_DATE: u16 = 0x0021;
+
+/// 一个目录项
+pub type RawEntry = [u8; ENTRY_SIZE];
+
+/// 目录项在磁盘上的位置
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EntryPosition {
+    pub sector: usize,
+    pub offset: usize,
+}
+
+impl EntryPosition {
+    /// 作为 inode 编号，在卷中唯一
+    pub fn id(&self) -> usize {
+        self.sector * BLOCK_SIZE + self.offset
+    }
+}
+
+/// 解析后的目录项
+#[derive(Clone, Debug)]
+pub struct DirItem {
+    pub name: String,
+    /// 8.3 格式的短名称
+    pub short_name: [u8; 11],
+    pub attribute: u8,
+    pub first_cluster: u32,
+    pub size: u32,
+    /// 短目录项的位置
+    pub position: EntryPosition,
+    /// 长文件名项和短目录项的位置，删除时全部标记
+    pub slots: Vec<EntryPosition>,
+}
+
+impl DirItem {
+    pub fn is_dir(&self) -> bool {
+        self.attribute & ATTR_DIRECTORY != 0
+    }
+}
+
+/// 短目录项中的起始簇
+pub fn first_cluster(entry: &RawEntry) -> u32 {
+    (read_u16(entry, 20) as u32) << 16 | read_u16(entry, 26) as u32
+}
+
+/// 修改短目录项中的起始簇和大小
+pub fn set_cluster_and_size(entry: &mut RawEntry, cluster: u32, size: u32) {
+    write_u16(entry, 20, (cluster >> 16) as u16);
+    write_u16(entry, 26, cluster as u16);
+    write_u32(entry, 28, size);
+}
+
+/// 短目录项是否有效（未删除，也不是目录的结尾）
+pub fn is_present(entry: &RawEntry) -> bool {
+    entry[0] != 0 && entry[0] != DELETED
+}
+
+/// 建立一个短目录项
+fn short_entry(short_name: &[u8; 11], attribute: u8, cluster: u32) -> RawEntry {
+    let mut entry = [0u8; ENTRY_SIZE];
+    entry[..11].copy_from_slice(short_name);
+    entry[11] = attribute;
+    // 创建、访问和修改日期
+    write_u16(&mut entry, 16, DEFAULT_DATE);
+    write_u16(&mut entry, 18, DEFAULT_DATE);
+    write_u16(&mut entry, 24, DEFAULT_DATE);
+    set_cluster_and_size(&mut entry, cluster, 0);
+    entry
+}
+
+/// 短名称的校验和，记录在每个长文件名项中
+fn checksum(short_name: &[u8; 11]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, &byte| (sum >> 1 | sum << 7).wrapping_add(byte))
+}
+
+/// 将短名称转换为显示的名称
+fn display_short_name(entry: &RawEntry) -> String {
+    let mut base: Vec<u8> = entry[..8].to_vec();
+    if base[0] == 0x05 {
+        base[0] = DELETED;
+    }
+    let mut extension: Vec<u8> = entry[8..11].to_vec();
+    if entry[12] & LOWERCASE_BASE != 0 {
+        base.make_ascii_lowercase();
+    }
+    if entry[12] & LOWERCASE_EXTENSION != 0 {
+        extension.make_ascii_lowercase();
+    }
+    let trim = |bytes: &[u8]| {
+        let len = bytes
+            .iter()
+            .rposition(|&byte| byte != b' ')
+            .map_or(0, |i| i + 1);
+        String::from_utf8_lossy(&bytes[..len]).into_owned()
+    };
+    let mut name = trim(&base);
+    let extension = trim(&extension);
+    if !extension.is_empty() {
+        name.push('.');
+        name.push_str(&extension);
+    }
+    name
+}
+
+/// 是否可以出现在短名称中
+fn is_short_name_char(byte: u8) -> bool {
+    byte.is_ascii_uppercase() || byte.is_ascii_digit() || b"$%'-_@~`!(){}^#&".contains(&byte)
+}
+
+/// 名称本身符合 8.3 格式（大写）时，返回对应的短名称
+fn exact_short_name(name: &str) -> Option<[u8; 11]> {
+    let bytes = name.as_bytes();
+    let (base, extension) = match bytes.iter().position(|&byte| byte == b'.') {
+        Some(dot) => (&bytes[..dot], &bytes[dot + 1..]),
+        None => (bytes, &[][..]),
+    };
+    let valid = !base.is_empty()
+        && base.len() <= 8
+        && extension.len() <= 3
+        && (bytes.len() == base.len() || !extension.is_empty())
+        && base
+            .iter()
+            .chain(extension.iter())
+            .all(|&byte| is_short_name_char(byte));
+    if !valid {
+        return None;
+    }
+    let mut short_name = [b' '; 11];
+    short_name[..base.len()].copy_from_slice(base);
+    short_name[8..8 + extension.len()].copy_from_slice(extension);
+    Some(short_name)
+}
+
+/// 为长文件名生成不与 `existing` 重复的短名称，形如 `BASIS~1.EXT`
+fn generate_short_name(name: &str, existing: &[[u8; 11]]) -> Result<[u8; 11], isize> {
+    // 不合法的字符替换为 '_'，空格和 '.' 被去掉
+    let convert = |part: &str| -> Vec<u8> {
+        part.bytes()
+            .filter(|&byte| byte != b' ' && byte != b'.')
+            .map(|byte| {
+                let byte = byte.to_ascii_uppercase();
+                if is_short_name_char(byte) {
+                    byte
+                } else {
+                    b'_'
+                }
+            })
+            .collect()
+    };
+    let trimmed = name.trim_start_matches('.');
+    let (base, extension) = match trimmed.rfind('.') {
+        Some(dot) => (convert(&trimmed[..dot]), convert(&trimmed[dot + 1..])),
+        None => (convert(trimmed), Vec::new()),
+    };
+    let mut short_name = [b' '; 11];
+    let extension_len = extension.len().min(3);
+    short_name[8..8 + extension_len].copy_from_slice(&extension[..extension_len]);
+    for number in 1..1_000_000usize {
+        let mut tail = [0u8; 8];
+        let mut tail_len = 0;
+        let mut rest = number;
+        while rest != 0 {
+            tail[7 - tail_len] = b'0' + (rest % 10) as u8;
+            tail_len += 1;
+            rest /= 10;
+        }
+        tail[7 - tail_len] = b'~';
+        tail_len += 1;
+        let base_len = base.len().min(8 - tail_len);
+        for byte in short_name[..8].iter_mut() {
+            *byte = b' ';
+        }
+        short_name[..base_len].copy_from_slice(&base[..base_len]);
+        short_name[base_len..base_len + tail_len].copy_from_slice(&tail[8 - tail_len..]);
+        if !existing.contains(&short_name) {
+            return Ok(short_name);
+        }
+    }
+    Err(EEXIST)
+}
+
+/// 名称是否可以作为文件名
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name.encode_utf16().count() <= NAME_LENGTH_LIMIT
+        && !name
+            .chars()
+            .any(|c| (c as u32) < 0x20 || "/\\:*?\"<>|".contains(c))
+}
+
+/// 建立长文件名项，按照在磁盘上的顺序（最后一段在前）
+fn long_entries(name: &str, short_name: &[u8; 11]) -> Vec<RawEntry> {
+    let mut chars: Vec<u16> = name.encode_utf16().collect();
+    let count = (chars.len() + CHARS_PER_LONG_ENTRY - 1) / CHARS_PER_LONG_ENTRY;
+    // 名称之后以 0 结尾，剩余部分填充 0xffff
+    if chars.len() % CHARS_PER_LONG_ENTRY != 0 {
+        chars.push(0);
+    }
+    chars.resize(count * CHARS_PER_LONG_ENTRY, 0xffff);
+    let checksum = checksum(short_name);
+    (0..count)
+        .rev()
+        .map(|index| {
+            let mut entry = [0u8; ENTRY_SIZE];
+            entry[0] = (index + 1) as u8
+                | if index + 1 == count {
+                    LAST_LONG_ENTRY
+                } else {
+                    0
+                };
+            entry[11] = ATTR_LONG_NAME;
+            entry[13] = checksum;
+            for (i, &offset) in LONG_NAME_OFFSETS.iter().enumerate() {
+                write_u16(&mut entry, offset, chars[index * CHARS_PER_LONG_ENTRY + i]);
+            }
+            entry
+        })
+        .collect()
+}
+
+/// 正在收集的长文件名
+struct LongName {
+    /// 按序号排列的字符，序号从 1 开始
+    chars: Vec<u16>,
+    /// 下一个应当出现的序号
+    expected: u8,
+    checksum: u8,
+    slots: Vec<EntryPosition>,
+}
+
+impl LongName {
+    /// 遇到长文件名项时调用，序号或校验和不连续时丢弃已经收集的部分
+    fn push(state: &mut Option<LongName>, entry: &RawEntry, position: EntryPosition) {
+        let order = entry[0] & !LAST_LONG_ENTRY;
+        if entry[0] & LAST_LONG_ENTRY != 0 {
+            // 最后一段最先出现，开始一个新的长文件名
+            if order == 0 || order > 20 {
+                *state = None;
+                return;
+            }
+            *state = Some(LongName {
+                chars: alloc::vec![0xffff; order as usize * CHARS_PER_LONG_ENTRY],
+                expected: order,
+                checksum: entry[13],
+                slots: Vec::new(),
+            });
+        }
+        let valid = match state {
+            Some(long_name) => long_name.expected == order && long_name.checksum == entry[13],
+            None => false,
+        };
+        if !valid {
+            *state = None;
+            return;
+        }
+        let long_name = state.as_mut().unwrap();
+        let start = (order as usize - 1) * CHARS_PER_LONG_ENTRY;
+        for (i, &offset) in LONG_NAME_OFFSETS.iter().enumerate() {
+            long_name.chars[start + i] = read_u16(entry, offset);
+        }
+        long_name.expected -= 1;
+        long_name.slots.push(position);
+    }
+
+    /// 遇到短目录项时调用，完整且校验和正确时返回长文件名
+    fn finish(self, short_name: &[u8; 11]) -> Option<(String, Vec<EntryPosition>)> {
+        if self.expected != 0 || self.checksum != checksum(short_name) {
+            return None;
+        }
+        let len = self
+            .chars
+            .iter()
+            .position(|&c| c == 0 || c == 0xffff)
+            .unwrap_or(self.chars.len());
+        let name = String::from_utf16(&self.chars[..len]).ok()?;
+        Some((name, self.slots))
+    }
+}
+
+impl Volume {
+    /// 读取目录的所有目录项，直到结尾标记
+    fn slots(&self, directory: u32) -> Result<Vec<(EntryPosition, RawEntry)>, isize> {
+        if !self.is_valid_cluster(directory) {
+            return Err(EIO);
+        }
+        let mut slots = Vec::new();
+        let mut cluster = Some(directory);
+        while let Some(current) = cluster {
+            let first_sector = self.bpb.cluster_sector(current);
+            for sector in first_sector..first_sector + self.bpb.sectors_per_cluster {
+                let data = self.read_sector(sector)?;
+                for offset in (0..BLOCK_SIZE).step_by(ENTRY_SIZE) {
+                    let mut entry = [0u8; ENTRY_SIZE];
+                    entry.copy_from_slice(&data[offset..offset + ENTRY_SIZE]);
+                    if entry[0] == 0 {
+                        return Ok(slots);
+                    }
+                    slots.push((EntryPosition { sector, offset }, entry));
+                }
+            }
+            cluster = self.next_cluster(current)?;
+        }
+        Ok(slots)
+    }
+
+    /// 目录中的所有文件和子目录，不包括 `.`、`..` 和卷标
+    pub fn list(&self, directory: u32) -> Result<Vec<DirItem>, isize> {
+        let mut items = Vec::new();
+        let mut long_name = None;
+        for (position, entry) in self.slots(directory)? {
+            if entry[0] == DELETED {
+                long_name = None;
+                continue;
+            }
+            if entry[11] & 0x3f == ATTR_LONG_NAME {
+                LongName::push(&mut long_name, &entry, position);
+                continue;
+            }
+            let mut short_name = [0u8; 11];
+            short_name.copy_from_slice(&entry[..11]);
+            let long = long_name
+                .take()
+                .and_then(|long_name| long_name.finish(&short_name));
+            if entry[11] & ATTR_VOLUME_ID != 0 || entry[0] == b'.' {
+                continue;
+            }
+            let (name, mut slots) = match long {
+                Some((name, slots)) => (name, slots),
+                None => (display_short_name(&entry), Vec::new()),
+            };
+            slots.push(position);
+            items.push(DirItem {
+                name,
+                short_name,
+                attribute: entry[11],
+                first_cluster: first_cluster(&entry),
+                size: read_u32(&entry, 28),
+                position,
+                slots,
+            });
+        }
+        Ok(items)
+    }
+
+    /// 在目录中查找名称为 `name` 的项，不区分大小写，也可以使用短名称
+    pub fn find(&self, directory: u32, name: &str) -> Result<Option<DirItem>, isize> {
+        let short_name = exact_short_name(&name.to_ascii_uppercase());
+        Ok(self.list(directory)?.into_iter().find(|item| {
+            item.name.eq_ignore_ascii_case(name) || short_name == Some(item.short_name)
+        }))
+    }
+
+    /// 读取一个目录项
+    pub fn read_entry(&self, position: EntryPosition) -> Result<RawEntry, isize> {
+        let data = self.read_sector(position.sector)?;
+        let mut entry = [0u8; ENTRY_SIZE];
+        entry.copy_from_slice(&data[position.offset..position.offset + ENTRY_SIZE]);
+        Ok(entry)
+    }
+
+    /// 写入一个目录项
+    pub fn write_entry(&self, position: EntryPosition, entry: &RawEntry) -> Result<(), isize> {
+        let mut data = self.read_sector(position.sector)?;
+        data[position.offset..position.offset + ENTRY_SIZE].copy_from_slice(entry);
+        self.write_sector(position.sector, &data)
+    }
+
+    /// 在目录中找到 `count` 个连续的空闲目录项，不够时扩展目录
+    fn free_slots(&mut self, directory: u32, count: usize) -> Result<Vec<EntryPosition>, isize> {
+        if !self.is_valid_cluster(directory) {
+            return Err(EIO);
+        }
+        let mut run = Vec::new();
+        let mut cluster = directory;
+        loop {
+            let first_sector = self.bpb.cluster_sector(cluster);
+            for sector in first_sector..first_sector + self.bpb.sectors_per_cluster {
+                let data = self.read_sector(sector)?;
+                for offset in (0..BLOCK_SIZE).step_by(ENTRY_SIZE) {
+                    if data[offset] == 0 || data[offset] == DELETED {
+                        run.push(EntryPosition { sector, offset });
+                        if run.len() == count {
+                            return Ok(run);
+                        }
+                    } else {
+                        run.clear();
+                    }
+                }
+            }
+            cluster = match self.next_cluster(cluster)? {
+                Some(next) => next,
+                // 新的簇已经清零，全部是空闲的目录项
+                None => self.alloc_cluster(Some(cluster))?,
+            };
+        }
+    }
+
+    /// 在目录中添加一项，返回短目录项的位置
+    pub fn add_entry(
+        &mut self,
+        directory: u32,
+        name: &str,
+        attribute: u8,
+        cluster: u32,
+    ) -> Result<EntryPosition, isize> {
+        if !is_valid_name(name) {
+            return Err(EINVAL);
+        }
+        let (short_name, long) = match exact_short_name(name) {
+            Some(short_name) => (short_name, Vec::new()),
+            None => {
+                let existing: Vec<[u8; 11]> = self
+                    .list(directory)?
+                    .iter()
+                    .map(|item| item.short_name)
+                    .collect();
+                let short_name = generate_short_name(name, &existing)?;
+                (short_name, long_entries(name, &short_name))
+            }
+        };
+        let slots = self.free_slots(directory, long.len() + 1)?;
+        for (position, entry) in slots.iter().zip(long.iter()) {
+            self.write_entry(*position, entry)?;
+        }
+        let position = *slots.last().unwrap();
+        self.write_entry(position, &short_entry(&short_name, attribute, cluster))?;
+        Ok(position)
+    }
+
+    /// 删除目录项，不释放其簇链
+    pub fn remove_entry(&mut self, item: &DirItem) -> Result<(), isize> {
+        for position in item.slots.iter() {
+            let mut entry = self.read_entry(*position)?;
+            entry[0] = DELETED;
+            self.write_entry(*position, &entry)?;
+        }
+        Ok(())
+    }
+
+    /// 初始化新目录的第一个簇，写入 `.` 和 `..`
+    ///
+    /// `parent` 为上一级目录的起始簇，根目录记为 0
+    pub fn init_directory(&mut self, cluster: u32, parent: u32) -> Result<(), isize> {
+        let sector = self.bpb.cluster_sector(cluster);
+        let mut data = self.read_sector(sector)?;
+        let dot = short_entry(b".          ", ATTR_DIRECTORY, cluster);
+        let dot_dot = short_entry(b"..         ", ATTR_DIRECTORY, parent);
+        data[..ENTRY_SIZE].copy_from_slice(&dot);
+        data[ENTRY_SIZE..2 * ENTRY_SIZE].copy_from_slice(&dot_dot);
+        self.write_sector(sector, &data)
+    }
+}
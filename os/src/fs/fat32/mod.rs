@@ -0,0 +1,275 @@
+//! 块设备上的 FAT32 文件系统
+//!
+//! 用于和主机交换文件，镜像可以在主机上用 `mkfs.fat -F 32` 建立，用 mtools 读写。
+//! 支持长文件名和簇链的分配，不支持时间戳和权限属性。
+//!
+//! FAT 没有 inode，以短目录项在磁盘上的位置标识一个文件。[`FatINode`] 只记录这个位置，
+//! 起始簇和大小每次从目录项中读取，因此同一个文件的多个 [`FatINode`] 总是一致的
+
+mod bpb;
+mod dir;
+mod volume;
+
+use super::*;
+use crate::drivers::block::BlockCache;
+use crate::sync::Mutex;
+use bpb::read_u32;
+use dir::*;
+use volume::Volume;
+
+/// 根目录的 inode 编号，目录项的位置不会是 1
+const ROOT_INODE: usize = 1;
+
+/// 文件系统和所有 inode 共享的部分
+struct Shared {
+    dev: usize,
+    volume: Mutex<Volume>,
+}
+
+/// FAT32 文件系统
+pub struct Fat32FileSystem {
+    shared: Arc<Shared>,
+}
+
+impl Fat32FileSystem {
+    /// 打开块设备上的 FAT32 卷，需要读取块设备，只能在线程中调用
+    pub fn open(disk: Arc<BlockCache>) -> Result<Arc<Self>, isize> {
+        let volume = Volume::open(disk)?;
+        Ok(Arc::new(Self {
+            shared: Arc::new(Shared {
+                dev: alloc_dev(),
+                volume: Mutex::new(volume),
+            }),
+        }))
+    }
+}
+
+impl FileSystem for Fat32FileSystem {
+    fn name(&self) -> &'static str {
+        "fat32"
+    }
+
+    fn root(&self) -> Arc<dyn INode> {
+        Arc::new(FatINode {
+            shared: self.shared.clone(),
+            position: None,
+        })
+    }
+
+    fn sync(&self) -> Result<(), isize> {
+        self.shared.volume.lock().sync()
+    }
+}
+
+/// FAT32 中的文件或目录
+struct FatINode {
+    shared: Arc<Shared>,
+    /// 短目录项的位置，根目录为 `None`
+    position: Option<EntryPosition>,
+}
+
+/// 从目录项中读出的文件信息
+struct Node {
+    first_cluster: u32,
+    size: usize,
+    is_dir: bool,
+}
+
+impl FatINode {
+    /// 读取文件信息，文件已经被删除时返回 `ENOENT`
+    fn node(&self, volume: &Volume) -> Result<Node, isize> {
+        let position = match self.position {
+            Some(position) => position,
+            None => {
+                return Ok(Node {
+                    first_cluster: volume.bpb.root_cluster,
+                    size: 0,
+                    is_dir: true,
+                })
+            }
+        };
+        let entry = volume.read_entry(position)?;
+        if !is_present(&entry) {
+            return Err(ENOENT);
+        }
+        Ok(Node {
+            first_cluster: first_cluster(&entry),
+            size: read_u32(&entry, 28) as usize,
+            is_dir: entry[11] & ATTR_DIRECTORY != 0,
+        })
+    }
+
+    /// 读取普通文件的信息，是目录时返回 `EISDIR`
+    fn file_node(&self, volume: &Volume) -> Result<Node, isize> {
+        let node = self.node(volume)?;
+        if node.is_dir {
+            return Err(EISDIR);
+        }
+        Ok(node)
+    }
+
+    /// 读取目录的起始簇，不是目录时返回 `ENOTDIR`
+    fn directory(&self, volume: &Volume) -> Result<u32, isize> {
+        let node = self.node(volume)?;
+        if !node.is_dir {
+            return Err(ENOTDIR);
+        }
+        Ok(node.first_cluster)
+    }
+
+    /// 更新目录项中的起始簇和大小
+    fn update(&self, volume: &Volume, first_cluster: u32, size: usize) -> Result<(), isize> {
+        let position = self.position.unwrap();
+        let mut entry = volume.read_entry(position)?;
+        set_cluster_and_size(&mut entry, first_cluster, size as u32);
+        volume.write_entry(position, &entry)
+    }
+
+    /// 将文件扩展到 `size`，新增的部分清零，返回新的起始簇
+    fn grow(&self, volume: &mut Volume, node: &Node, size: usize) -> Result<u32, isize> {
+        if size > u32::MAX as usize {
+            return Err(ENOSPC);
+        }
+        let cluster_size = volume.cluster_size();
+        let clusters = (size + cluster_size - 1) / cluster_size;
+        let first_cluster = volume.extend_chain(node.first_cluster, clusters)?;
+        // 新分配的簇已经清零，原来最后一个簇中文件末尾之后的部分可能有旧数据
+        let allocated = (node.size + cluster_size - 1) / cluster_size * cluster_size;
+        volume.zero_chain(first_cluster, node.size, size.min(allocated) - node.size)?;
+        Ok(first_cluster)
+    }
+
+    fn child(&self, position: EntryPosition) -> Arc<dyn INode> {
+        Arc::new(FatINode {
+            shared: self.shared.clone(),
+            position: Some(position),
+        })
+    }
+}
+
+impl INode for FatINode {
+    fn metadata(&self) -> Result<Metadata, isize> {
+        let volume = self.shared.volume.lock();
+        let node = self.node(&volume)?;
+        let clusters = volume.chain_length(node.first_cluster)?;
+        let (file_type, mode) = if node.is_dir {
+            (FileType::Directory, 0o755)
+        } else {
+            (FileType::Regular, 0o644)
+        };
+        Ok(Metadata {
+            dev: self.shared.dev,
+            inode: self.position.map_or(ROOT_INODE, |position| position.id()),
+            file_type,
+            mode,
+            nlinks: 1,
+            size: node.size,
+            blocks: clusters * volume.bpb.sectors_per_cluster,
+        })
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, isize> {
+        let volume = self.shared.volume.lock();
+        let node = self.file_node(&volume)?;
+        if offset >= node.size {
+            return Ok(0);
+        }
+        let len = buffer.len().min(node.size - offset);
+        volume.read_chain(node.first_cluster, offset, &mut buffer[..len])
+    }
+
+    fn write_at(&self, offset: usize, buffer: &[u8]) -> Result<usize, isize> {
+        let mut volume = self.shared.volume.lock();
+        let node = self.file_node(&volume)?;
+        let end = offset + buffer.len();
+        let first_cluster = if end > node.size {
+            let first_cluster = self.grow(&mut volume, &node, end)?;
+            self.update(&volume, first_cluster, end)?;
+            first_cluster
+        } else {
+            node.first_cluster
+        };
+        volume.write_chain(first_cluster, offset, buffer)
+    }
+
+    fn resize(&self, len: usize) -> Result<(), isize> {
+        let mut volume = self.shared.volume.lock();
+        let node = self.file_node(&volume)?;
+        let first_cluster = if len > node.size {
+            self.grow(&mut volume, &node, len)?
+        } else {
+            let cluster_size = volume.cluster_size();
+            volume.truncate_chain(node.first_cluster, (len + cluster_size - 1) / cluster_size)?
+        };
+        self.update(&volume, first_cluster, len)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn INode>, isize> {
+        let volume = self.shared.volume.lock();
+        let directory = self.directory(&volume)?;
+        let item = volume.find(directory, name)?.ok_or(ENOENT)?;
+        Ok(self.child(item.position))
+    }
+
+    fn create(&self, name: &str, file_type: FileType) -> Result<Arc<dyn INode>, isize> {
+        let mut volume = self.shared.volume.lock();
+        let directory = self.directory(&volume)?;
+        if volume.find(directory, name)?.is_some() {
+            return Err(EEXIST);
+        }
+        let position = match file_type {
+            FileType::Regular => volume.add_entry(directory, name, ATTR_ARCHIVE, 0)?,
+            FileType::Directory => {
+                let cluster = volume.alloc_cluster(None)?;
+                // `..` 指向根目录时记为 0
+                let parent = if self.position.is_none() {
+                    0
+                } else {
+                    directory
+                };
+                let result = volume
+                    .init_directory(cluster, parent)
+                    .and_then(|_| volume.add_entry(directory, name, ATTR_DIRECTORY, cluster));
+                if result.is_err() {
+                    volume.free_chain(cluster)?;
+                }
+                result?
+            }
+            _ => return Err(EINVAL),
+        };
+        Ok(self.child(position))
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), isize> {
+        let mut volume = self.shared.volume.lock();
+        let directory = self.directory(&volume)?;
+        let item = volume.find(directory, name)?.ok_or(ENOENT)?;
+        if item.is_dir() && !volume.list(item.first_cluster)?.is_empty() {
+            return Err(ENOTEMPTY);
+        }
+        volume.remove_entry(&item)?;
+        volume.free_chain(item.first_cluster)
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, isize> {
+        let volume = self.shared.volume.lock();
+        let directory = self.directory(&volume)?;
+        Ok(volume
+            .list(directory)?
+            .into_iter()
+            .nth(index)
+            .map(|item| DirEntry {
+                file_type: if item.is_dir() {
+                    FileType::Directory
+                } else {
+                    FileType::Regular
+                },
+                inode: item.position.id(),
+                name: item.name,
+            }))
+    }
+
+    fn sync(&self) -> Result<(), isize> {
+        self.shared.volume.lock().sync()
+    }
+}
@@ -0,0 +1,340 @@
+//! 按扇区和簇访问 FAT32 卷 [`Volume`]
+//!
+//! 负责 FAT 表的读写、簇链的分配和释放，以及簇链上数据的读写
+
+use super::bpb::*;
+use crate::drivers::block::{BlockCache, BlockDevice, BLOCK_SIZE};
+use crate::kernel::errno::*;
+use alloc::sync::Arc;
+use core::cmp::min;
+
+/// FAT 表项中有效的位
+const FAT_MASK: u32 = 0x0fff_ffff;
+
+/// 不小于此值的表项表示簇链结束
+const END_OF_CHAIN_MIN: u32 = 0x0fff_fff8;
+
+/// 写入表项的簇链结束标记
+const END_OF_CHAIN: u32 = 0x0fff_ffff;
+
+/// FSInfo 扇区中的签名和字段位置
+const FS_INFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+const FS_INFO_STRUCT_SIGNATURE: u32 = 0x6141_7272;
+const FS_INFO_FREE_COUNT: usize = 488;
+const FS_INFO_NEXT_FREE: usize = 492;
+
+/// 一个扇区的数据
+pub type Sector = [u8; BLOCK_SIZE];
+
+/// 一个 FAT32 卷
+pub struct Volume {
+    disk: Arc<BlockCache>,
+    pub bpb: BiosParameterBlock,
+    /// 下一次开始查找空闲簇的位置
+    next_free: u32,
+    /// 空闲簇的数量，未知时为 `None`
+    free_count: Option<u32>,
+}
+
+impl Volume {
+    /// 读取引导扇区和 FSInfo，不是 FAT32 时返回 `EINVAL`
+    pub fn open(disk: Arc<BlockCache>) -> Result<Self, isize> {
+        let mut sector: Sector = [0; BLOCK_SIZE];
+        disk.read_block(0, &mut sector).map_err(|_| EIO)?;
+        let bpb = BiosParameterBlock::parse(&sector).ok_or(EINVAL)?;
+        if bpb.total_sectors > disk.num_blocks() {
+            return Err(EINVAL);
+        }
+        let mut volume = Self {
+            disk,
+            bpb,
+            next_free: 2,
+            free_count: None,
+        };
+        if let Some(sector) = volume.read_fs_info()? {
+            let cluster_count = volume.bpb.cluster_count() as u32;
+            let free_count = read_u32(&sector, FS_INFO_FREE_COUNT);
+            if free_count <= cluster_count {
+                volume.free_count = Some(free_count);
+            }
+            let next_free = read_u32(&sector, FS_INFO_NEXT_FREE);
+            if next_free >= 2 && next_free < cluster_count + 2 {
+                volume.next_free = next_free;
+            }
+        }
+        Ok(volume)
+    }
+
+    /// 读取一个扇区
+    pub fn read_sector(&self, sector: usize) -> Result<Sector, isize> {
+        let mut data: Sector = [0; BLOCK_SIZE];
+        self.disk.read_block(sector, &mut data).map_err(|_| EIO)?;
+        Ok(data)
+    }
+
+    /// 写入一个扇区
+    pub fn write_sector(&self, sector: usize, data: &Sector) -> Result<(), isize> {
+        self.disk.write_block(sector, data).map_err(|_| EIO)
+    }
+
+    /// 签名正确时返回 FSInfo 扇区的内容
+    fn read_fs_info(&self) -> Result<Option<Sector>, isize> {
+        let index = self.bpb.fs_info_sector;
+        if index == 0 || index >= self.bpb.reserved_sectors {
+            return Ok(None);
+        }
+        let sector = self.read_sector(index)?;
+        if read_u32(&sector, 0) == FS_INFO_LEAD_SIGNATURE
+            && read_u32(&sector, 484) == FS_INFO_STRUCT_SIGNATURE
+        {
+            Ok(Some(sector))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// 更新 FSInfo，并将所有修改写回块设备
+    pub fn sync(&self) -> Result<(), isize> {
+        if let Some(mut sector) = self.read_fs_info()? {
+            write_u32(
+                &mut sector,
+                FS_INFO_FREE_COUNT,
+                self.free_count.unwrap_or(u32::MAX),
+            );
+            write_u32(&mut sector, FS_INFO_NEXT_FREE, self.next_free);
+            self.write_sector(self.bpb.fs_info_sector, &sector)?;
+        }
+        self.disk.sync().map_err(|_| EIO)
+    }
+
+    /// 簇的大小，单位为字节
+    pub fn cluster_size(&self) -> usize {
+        self.bpb.cluster_size()
+    }
+
+    /// 是否是数据区中合法的簇号
+    pub fn is_valid_cluster(&self, cluster: u32) -> bool {
+        cluster >= 2 && (cluster as usize) < self.bpb.cluster_count() + 2
+    }
+
+    /// 簇在 FAT 中的（扇区，偏移）
+    fn fat_position(&self, cluster: u32) -> (usize, usize) {
+        let offset = cluster as usize * 4;
+        (
+            self.bpb.fat_start() + offset / BLOCK_SIZE,
+            offset % BLOCK_SIZE,
+        )
+    }
+
+    /// 读取簇在 FAT 中的表项
+    fn fat_entry(&self, cluster: u32) -> Result<u32, isize> {
+        let (sector, offset) = self.fat_position(cluster);
+        Ok(read_u32(&self.read_sector(sector)?, offset) & FAT_MASK)
+    }
+
+    /// 修改簇在所有 FAT 中的表项，保留最高 4 位
+    fn set_fat_entry(&self, cluster: u32, value: u32) -> Result<(), isize> {
+        let (sector, offset) = self.fat_position(cluster);
+        for fat in 0..self.bpb.num_fats {
+            let index = sector + fat * self.bpb.sectors_per_fat;
+            let mut data = self.read_sector(index)?;
+            let old = read_u32(&data, offset);
+            write_u32(&mut data, offset, (old & !FAT_MASK) | (value & FAT_MASK));
+            self.write_sector(index, &data)?;
+        }
+        Ok(())
+    }
+
+    /// 簇链中的下一个簇，已经是最后一个时返回 `None`
+    pub fn next_cluster(&self, cluster: u32) -> Result<Option<u32>, isize> {
+        let next = self.fat_entry(cluster)?;
+        if next >= END_OF_CHAIN_MIN {
+            Ok(None)
+        } else if self.is_valid_cluster(next) {
+            Ok(Some(next))
+        } else {
+            // 空闲或坏簇出现在簇链中，说明文件系统已经损坏
+            Err(EIO)
+        }
+    }
+
+    /// 簇链中第 `index` 个簇，簇链不够长时返回 `None`
+    pub fn cluster_at(&self, first: u32, index: usize) -> Result<Option<u32>, isize> {
+        if first == 0 {
+            return Ok(None);
+        }
+        let mut cluster = first;
+        for _ in 0..index {
+            cluster = match self.next_cluster(cluster)? {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(cluster))
+    }
+
+    /// 簇链的长度
+    pub fn chain_length(&self, first: u32) -> Result<usize, isize> {
+        if first == 0 {
+            return Ok(0);
+        }
+        let mut length = 1;
+        let mut cluster = first;
+        while let Some(next) = self.next_cluster(cluster)? {
+            cluster = next;
+            length += 1;
+            if length > self.bpb.cluster_count() {
+                return Err(EIO);
+            }
+        }
+        Ok(length)
+    }
+
+    /// 查找空闲簇时的下一个簇号，到达末尾后回到 2 号簇
+    fn following(&self, cluster: u32) -> u32 {
+        if (cluster as usize) + 1 < self.bpb.cluster_count() + 2 {
+            cluster + 1
+        } else {
+            2
+        }
+    }
+
+    /// 分配一个清零的簇，接在 `previous` 之后
+    pub fn alloc_cluster(&mut self, previous: Option<u32>) -> Result<u32, isize> {
+        let start = self.next_free;
+        let mut cluster = start;
+        loop {
+            if self.fat_entry(cluster)? == 0 {
+                break;
+            }
+            cluster = self.following(cluster);
+            if cluster == start {
+                return Err(ENOSPC);
+            }
+        }
+        self.set_fat_entry(cluster, END_OF_CHAIN)?;
+        let zero: Sector = [0; BLOCK_SIZE];
+        let first_sector = self.bpb.cluster_sector(cluster);
+        for sector in first_sector..first_sector + self.bpb.sectors_per_cluster {
+            self.write_sector(sector, &zero)?;
+        }
+        if let Some(previous) = previous {
+            self.set_fat_entry(previous, cluster)?;
+        }
+        self.next_free = self.following(cluster);
+        self.free_count = self.free_count.map(|free| free.saturating_sub(1));
+        Ok(cluster)
+    }
+
+    /// 释放从 `first` 开始的整个簇链
+    pub fn free_chain(&mut self, first: u32) -> Result<(), isize> {
+        let mut cluster = Some(first).filter(|&cluster| cluster != 0);
+        while let Some(current) = cluster {
+            cluster = self.next_cluster(current)?;
+            self.set_fat_entry(current, 0)?;
+            self.free_count = self.free_count.map(|free| free + 1);
+        }
+        Ok(())
+    }
+
+    /// 将簇链扩展到至少 `count` 个簇，返回簇链的第一个簇（原来为空时会改变）
+    pub fn extend_chain(&mut self, first: u32, count: usize) -> Result<u32, isize> {
+        let mut length = self.chain_length(first)?;
+        if length >= count {
+            return Ok(first);
+        }
+        let mut first = first;
+        let mut last = self.cluster_at(first, length.saturating_sub(1))?;
+        while length < count {
+            let cluster = self.alloc_cluster(last)?;
+            if first == 0 {
+                first = cluster;
+            }
+            last = Some(cluster);
+            length += 1;
+        }
+        Ok(first)
+    }
+
+    /// 将簇链缩短为 `count` 个簇，返回簇链的第一个簇（缩短为 0 时为 0）
+    pub fn truncate_chain(&mut self, first: u32, count: usize) -> Result<u32, isize> {
+        if count == 0 {
+            self.free_chain(first)?;
+            return Ok(0);
+        }
+        if let Some(last) = self.cluster_at(first, count - 1)? {
+            if let Some(rest) = self.next_cluster(last)? {
+                self.set_fat_entry(last, END_OF_CHAIN)?;
+                self.free_chain(rest)?;
+            }
+        }
+        Ok(first)
+    }
+
+    /// 在簇链上从 `offset` 开始读取，直到 `buffer` 填满或簇链结束，返回读取的字节数
+    pub fn read_chain(&self, first: u32, offset: usize, buffer: &mut [u8]) -> Result<usize, isize> {
+        self.access_chain(first, offset, buffer.len(), |sector, range, position| {
+            let data = self.read_sector(sector)?;
+            buffer[position..position + range.len()].copy_from_slice(&data[range]);
+            Ok(())
+        })
+    }
+
+    /// 在簇链上从 `offset` 开始写入，直到写完或簇链结束，返回写入的字节数
+    pub fn write_chain(&self, first: u32, offset: usize, buffer: &[u8]) -> Result<usize, isize> {
+        self.access_chain(first, offset, buffer.len(), |sector, range, position| {
+            // 整个扇区写入时不需要先读出
+            let mut data = if range.len() == BLOCK_SIZE {
+                [0; BLOCK_SIZE]
+            } else {
+                self.read_sector(sector)?
+            };
+            let len = range.len();
+            data[range].copy_from_slice(&buffer[position..position + len]);
+            self.write_sector(sector, &data)
+        })
+    }
+
+    /// 在簇链上将 `[offset, offset + len)` 清零
+    pub fn zero_chain(&self, first: u32, offset: usize, len: usize) -> Result<(), isize> {
+        self.access_chain(first, offset, len, |sector, range, _| {
+            let mut data = self.read_sector(sector)?;
+            for byte in data[range].iter_mut() {
+                *byte = 0;
+            }
+            self.write_sector(sector, &data)
+        })?;
+        Ok(())
+    }
+
+    /// 将簇链上 `[offset, offset + len)` 按扇区拆分，依次以（扇区号，扇区内范围，已处理的字节数）调用 `f`
+    fn access_chain(
+        &self,
+        first: u32,
+        offset: usize,
+        len: usize,
+        mut f: impl FnMut(usize, core::ops::Range<usize>, usize) -> Result<(), isize>,
+    ) -> Result<usize, isize> {
+        let cluster_size = self.cluster_size();
+        let mut cluster = match self.cluster_at(first, offset / cluster_size)? {
+            Some(cluster) => cluster,
+            None => return Ok(0),
+        };
+        let mut position = 0;
+        while position < len {
+            let cluster_offset = (offset + position) % cluster_size;
+            let sector = self.bpb.cluster_sector(cluster) + cluster_offset / BLOCK_SIZE;
+            let start = cluster_offset % BLOCK_SIZE;
+            let size = min(BLOCK_SIZE - start, len - position);
+            f(sector, start..start + size, position)?;
+            position += size;
+            if position < len && (offset + position) % cluster_size == 0 {
+                cluster = match self.next_cluster(cluster)? {
+                    Some(next) => next,
+                    None => break,
+                };
+            }
+        }
+        Ok(position)
+    }
+}
@@ -0,0 +1,224 @@
+//! initramfs：启动时解包到 [`TmpFs`] 中的 cpio 归档
+//!
+//! 归档有两个来源，都存在时依次解包，后者覆盖前者中的同名文件：
+//! - 编译时由环境变量 `INITRAMFS` 指定、由 build.rs 嵌入内核镜像的归档
+//! - QEMU `-initrd` 加载的归档，位置由设备树的 `/chosen` 给出，解包后归还其所在的物理页
+//!
+//! 归档为 `cpio -H newc` 格式，每一项由 110 字节的文本头、以 '\0' 结尾的文件名和文件内容组成，
+//! 文件名和内容分别对齐到 4 字节，以名为 `TRAILER!!!` 的项结束。
+//! 只解包目录和普通文件，其他类型（符号链接、设备文件等）被忽略
+
+use super::*;
+use crate::drivers::devicetree;
+use crate::memory::address::VirtualAddress;
+use crate::memory::frame;
+
+/// newc 格式的头部长度
+const HEADER_SIZE: usize = 110;
+
+/// `mode` 中表示文件类型的部分
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+/// 归档中的一项
+pub struct Entry<'a> {
+    /// 文件路径，保留归档中的原样，例如 `./bin/sh`
+    pub name: &'a str,
+    /// 类型和权限
+    pub mode: u32,
+    /// 文件内容
+    pub data: &'a [u8],
+}
+
+/// 依次读取 newc 格式归档中的每一项
+pub struct Reader<'a> {
+    archive: &'a [u8],
+    offset: usize,
+    /// 遇到结尾或格式错误后不再继续
+    finished: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(archive: &'a [u8]) -> Self {
+        Self {
+            archive,
+            offset: 0,
+            finished: false,
+        }
+    }
+
+    /// 读取头部中第 `index` 个 8 位十六进制数字段（不包括开头的 magic）
+    fn field(header: &[u8], index: usize) -> Option<u32> {
+        let text = core::str::from_utf8(&header[6 + index * 8..14 + index * 8]).ok()?;
+        u32::from_str_radix(text, 16).ok()
+    }
+
+    /// 读取 `offset` 处的一项，返回该项和下一项的位置；遇到结尾时返回 `None`
+    fn parse(&self) -> Result<Option<(Entry<'a>, usize)>, &'static str> {
+        let archive = self.archive;
+        let header = archive
+            .get(self.offset..self.offset + HEADER_SIZE)
+            .ok_or("truncated header")?;
+        if &header[..6] != b"070701" && &header[..6] != b"070702" {
+            return Err("bad magic");
+        }
+        let mode = Self::field(header, 1).ok_or("bad mode")?;
+        let file_size = Self::field(header, 6).ok_or("bad file size")? as usize;
+        let name_size = Self::field(header, 11).ok_or("bad name size")? as usize;
+
+        let name_start = self.offset + HEADER_SIZE;
+        let name = archive
+            .get(name_start..name_start + name_size)
+            .ok_or("truncated name")?;
+        // 文件名以 '\0' 结尾
+        let name = match name.split_last() {
+            Some((0, name)) => core::str::from_utf8(name).map_err(|_| "name is not utf-8")?,
+            _ => return Err("name is not terminated"),
+        };
+        if name == "TRAILER!!!" {
+            return Ok(None);
+        }
+        let data_start = align4(name_start + name_size);
+        let data = archive
+            .get(data_start..data_start + file_size)
+            .ok_or("truncated data")?;
+
+        let entry = Entry { name, mode, data };
+        Ok(Some((entry, align4(data_start + file_size))))
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<Entry<'a>, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.parse() {
+            Ok(Some((entry, next))) => {
+                self.offset = next;
+                Some(Ok(entry))
+            }
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(message) => {
+                self.finished = true;
+                Some(Err(message))
+            }
+        }
+    }
+}
+
+/// 向上对齐到 4 字节
+fn align4(value: usize) -> usize {
+    (value + 3) & !3
+}
+
+/// 从 `root` 开始依次进入路径中的每个目录，不存在时创建
+fn make_dirs<'a>(
+    root: &Arc<dyn INode>,
+    names: impl Iterator<Item = &'a str>,
+) -> Result<Arc<dyn INode>, isize> {
+    let mut current = root.clone();
+    for name in names {
+        current = match current.lookup(name) {
+            Ok(inode) => inode,
+            Err(ENOENT) => current.create(name, FileType::Directory)?,
+            Err(errno) => return Err(errno),
+        };
+    }
+    Ok(current)
+}
+
+/// 将归档解包到目录 `root` 中，返回解包的文件数
+///
+/// 已经存在的同名文件会被覆盖。路径中含有 `..` 的项被忽略，格式错误时返回 `EINVAL`
+pub fn unpack(archive: &[u8], root: &Arc<dyn INode>) -> Result<usize, isize> {
+    let mut files = 0;
+    for entry in Reader::new(archive) {
+        let entry = entry.map_err(|message| {
+            warn!("initramfs: {}", message);
+            EINVAL
+        })?;
+        let mut names = entry
+            .name
+            .split('/')
+            .filter(|name| !name.is_empty() && *name != ".");
+        if entry.name.split('/').any(|name| name == "..") {
+            warn!("initramfs: ignoring {}", entry.name);
+            continue;
+        }
+        match entry.mode & S_IFMT {
+            S_IFDIR => {
+                make_dirs(root, names)?;
+            }
+            S_IFREG => {
+                let name = match names.next_back() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let parent = make_dirs(root, names)?;
+                let file = match parent.lookup(name) {
+                    Ok(file) => {
+                        file.resize(0)?;
+                        file
+                    }
+                    Err(ENOENT) => parent.create(name, FileType::Regular)?,
+                    Err(errno) => return Err(errno),
+                };
+                file.write_at(0, entry.data)?;
+                files += 1;
+            }
+            _ => debug!("initramfs: skipping {} (mode {:o})", entry.name, entry.mode),
+        }
+    }
+    Ok(files)
+}
+
+// 由 build.rs 生成，包含编译时指定的 initramfs 归档
+global_asm!(include_str!(concat!(env!("OUT_DIR"), "/initramfs.S")));
+
+/// 嵌入内核镜像的归档，没有指定时为空
+fn embedded() -> &'static [u8] {
+    extern "C" {
+        fn _initramfs_start();
+        fn _initramfs_end();
+    }
+    let start = _initramfs_start as usize;
+    let end = _initramfs_end as usize;
+    unsafe { core::slice::from_raw_parts(start as *const u8, end - start) }
+}
+
+/// 将所有 initramfs 归档解包到一个新的 [`TmpFs`] 中，没有任何归档时返回 `None`
+///
+/// initrd 所在的物理页在解包后归还给帧分配器
+pub fn load() -> Option<Arc<TmpFs>> {
+    let embedded = embedded();
+    let initrd = devicetree::initrd();
+    if embedded.is_empty() && initrd.is_none() {
+        return None;
+    }
+    let fs = TmpFs::new();
+    let root = fs.root();
+    if !embedded.is_empty() {
+        match unpack(embedded, &root) {
+            Ok(files) => info!("initramfs: {} files unpacked from kernel image", files),
+            Err(errno) => warn!("initramfs: failed to unpack embedded archive: {}", errno),
+        }
+    }
+    if let Some((start, end)) = initrd {
+        let archive = unsafe {
+            core::slice::from_raw_parts(VirtualAddress::from(start).0 as *const u8, end.0 - start.0)
+        };
+        match unpack(archive, &root) {
+            Ok(files) => info!("initramfs: {} files unpacked from initrd", files),
+            Err(errno) => warn!("initramfs: failed to unpack initrd: {}", errno),
+        }
+        frame::release(start, end);
+    }
+    Some(fs)
+}
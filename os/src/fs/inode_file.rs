@@ -0,0 +1,131 @@
+//! 打开的文件 [`InodeFile`]
+
+use super::*;
+use crate::sync::Mutex;
+use bitflags::*;
+
+bitflags! {
+    /// 打开文件的方式，数值与 Linux 保持一致
+    pub struct OpenFlags: u32 {
+        /// 只写
+        const WRONLY = 1;
+        /// 读写
+        const RDWR = 2;
+        /// 文件不存在时创建
+        const CREATE = 0o100;
+        /// 与 `CREATE` 一起使用，文件已经存在时报错
+        const EXCL = 0o200;
+        /// 打开时清空文件
+        const TRUNC = 0o1000;
+        /// 每次写入前移动到文件末尾
+        const APPEND = 0o2000;
+        /// 只能打开目录
+        const DIRECTORY = 0o200000;
+    }
+}
+
+impl OpenFlags {
+    /// 是否可读，没有 `WRONLY` 即为可读
+    pub fn readable(self) -> bool {
+        !self.contains(Self::WRONLY)
+    }
+
+    /// 是否可写
+    pub fn writable(self) -> bool {
+        self.intersects(Self::WRONLY | Self::RDWR)
+    }
+}
+
+/// 打开的文件，记录读写位置和打开方式
+///
+/// 同一个 [`InodeFile`] 可以被多个文件描述符共享（`dup` 或 `fork`），它们共享读写位置
+pub struct InodeFile {
+    inode: Arc<dyn INode>,
+    flags: OpenFlags,
+    /// 读写位置，读写期间一直持有，保证并发的读写不会交错
+    offset: Mutex<usize>,
+}
+
+impl InodeFile {
+    pub fn new(inode: Arc<dyn INode>, flags: OpenFlags) -> Self {
+        Self {
+            inode,
+            flags,
+            offset: Mutex::new(0),
+        }
+    }
+
+    /// 文件对应的 inode
+    pub fn inode(&self) -> &Arc<dyn INode> {
+        &self.inode
+    }
+}
+
+impl File for InodeFile {
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, isize> {
+        if !self.flags.readable() {
+            return Err(EBADF);
+        }
+        let mut offset = self.offset.lock();
+        let size = self.inode.read_at(*offset, buffer)?;
+        *offset += size;
+        Ok(size)
+    }
+
+    fn write(&self, buffer: &[u8]) -> Result<usize, isize> {
+        if !self.flags.writable() {
+            return Err(EBADF);
+        }
+        let mut offset = self.offset.lock();
+        if self.flags.contains(OpenFlags::APPEND) {
+            *offset = self.inode.metadata()?.size;
+        }
+        let size = self.inode.write_at(*offset, buffer)?;
+        *offset += size;
+        Ok(size)
+    }
+
+    fn seek(&self, position: SeekFrom) -> Result<usize, isize> {
+        let mut offset = self.offset.lock();
+        let (base, delta) = match position {
+            SeekFrom::Start(position) => (0, position as isize),
+            SeekFrom::Current(delta) => (*offset, delta),
+            SeekFrom::End(delta) => (self.inode.metadata()?.size, delta),
+        };
+        // 偏移量来自用户程序，溢出或为负时返回错误
+        let new_offset = (base as isize)
+            .checked_add(delta)
+            .filter(|offset| *offset >= 0)
+            .ok_or(EINVAL)?;
+        *offset = new_offset as usize;
+        Ok(*offset)
+    }
+
+    fn metadata(&self) -> Result<Metadata, isize> {
+        self.inode.metadata()
+    }
+}
+
+/// 按照 `flags` 打开路径 `path` 对应的文件
+pub fn open(path: &str, flags: OpenFlags) -> Result<Arc<dyn File>, isize> {
+    let inode = match lookup(path) {
+        Ok(_) if flags.contains(OpenFlags::CREATE | OpenFlags::EXCL) => return Err(EEXIST),
+        Ok(inode) => inode,
+        Err(ENOENT) if flags.contains(OpenFlags::CREATE) => {
+            let (parent, name) = lookup_parent(path)?;
+            parent.create(&name, FileType::Regular)?
+        }
+        Err(errno) => return Err(errno),
+    };
+    let metadata = inode.metadata()?;
+    match metadata.file_type {
+        FileType::Directory if flags.writable() => return Err(EISDIR),
+        FileType::Directory => {}
+        _ if flags.contains(OpenFlags::DIRECTORY) => return Err(ENOTDIR),
+        FileType::Regular if flags.contains(OpenFlags::TRUNC) && flags.writable() => {
+            inode.resize(0)?
+        }
+        _ => {}
+    }
+    Ok(Arc::new(InodeFile::new(inode, flags)))
+}
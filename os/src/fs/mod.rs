@@ -0,0 +1,167 @@
+//! 文件相关的内核功能
+//!
+//! - [`vfs`] 定义文件系统需要实现的 [`INode`] 和 [`FileSystem`]
+//! - [`mount`] 管理挂载点并解析路径
+//! - 进程通过文件描述符访问实现了 [`File`] 的对象，打开的普通文件为 [`InodeFile`]
+//! - [`tmpfs`] 是内存中的文件系统，启动时 [`initramfs`] 中的归档解包到其中
+//! - [`sfs`] 是块设备上的文件系统，没有 initramfs 时作为根文件系统
+//! - [`fat32`] 用于和主机交换文件
+//! - [`devfs`] 和 [`procfs`] 分别挂载在 `/dev` 和 `/proc`，提供设备文件和内核状态
+
+mod devfs;
+mod fat32;
+pub mod initramfs;
+mod inode_file;
+mod mount;
+mod procfs;
+mod sfs;
+mod stdin;
+mod stdout;
+mod tmpfs;
+mod vfs;
+
+use crate::drivers::block::{self, BlockCache};
+use crate::kernel::errno::*;
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use lazy_static::*;
+
+pub use devfs::DevFs;
+pub use fat32::Fat32FileSystem;
+pub use inode_file::{open, InodeFile, OpenFlags};
+pub use mount::{lookup, lookup_parent, mount, mounts, sync_all};
+pub use procfs::ProcFs;
+pub use sfs::SimpleFileSystem;
+pub use stdin::Stdin;
+pub use stdout::Stdout;
+pub use tmpfs::TmpFs;
+pub use vfs::*;
+
+/// 读写位置的移动方式，用于 [`File::seek`]
+#[derive(Clone, Copy, Debug)]
+pub enum SeekFrom {
+    /// 从文件开头
+    Start(usize),
+    /// 从当前位置
+    Current(isize),
+    /// 从文件末尾
+    End(isize),
+}
+
+/// 可以通过文件描述符读写的对象
+///
+/// 出错时返回错误码
+pub trait File: Send + Sync {
+    /// 读取数据至 `buffer`，返回读取的字节数
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, isize>;
+    /// 写入 `buffer` 中的数据，返回写入的字节数
+    fn write(&self, buffer: &[u8]) -> Result<usize, isize>;
+    /// 移动读写位置，返回新的位置。不支持时返回 `ESPIPE`
+    fn seek(&self, _position: SeekFrom) -> Result<usize, isize> {
+        Err(ESPIPE)
+    }
+    /// 文件的信息
+    fn metadata(&self) -> Result<Metadata, isize>;
+}
+
+/// 控制台的文件信息
+fn console_metadata() -> Metadata {
+    Metadata {
+        dev: 0,
+        inode: 0,
+        file_type: FileType::CharDevice,
+        mode: 0o620,
+        nlinks: 1,
+        size: 0,
+        blocks: 0,
+    }
+}
+
+lazy_static! {
+    /// 控制台输入
+    pub static ref STDIN: Arc<dyn File> = Arc::new(Stdin);
+    /// 控制台输出
+    pub static ref STDOUT: Arc<dyn File> = Arc::new(Stdout);
+}
+
+/// 识别块设备上的文件系统
+fn probe(disk: Arc<BlockCache>) -> Option<Arc<dyn FileSystem>> {
+    if let Ok(fs) = SimpleFileSystem::open(disk.clone()) {
+        return Some(fs);
+    }
+    if let Ok(fs) = Fat32FileSystem::open(disk) {
+        return Some(fs);
+    }
+    None
+}
+
+/// 挂载根文件系统和块设备上的文件系统
+///
+/// 根文件系统依次选择：解包了 initramfs 的 tmpfs、块设备上的 SFS、第一个识别出的文件系统，
+/// 都没有时使用空的 tmpfs。devfs 和 procfs 挂载在 `/dev` 和 `/proc`，
+/// 其他文件系统挂载在 `/mnt/block<编号>`。需要读取块设备，只能在线程中调用
+pub fn init() {
+    // initramfs 不需要块设备，先于块设备上的文件系统挂载
+    let initramfs = initramfs::load();
+    let filesystems: Vec<(usize, Arc<dyn FileSystem>)> = (0..block::count())
+        .filter_map(|index| Some((index, probe(block::get(index)?)?)))
+        .collect();
+    let root = if initramfs.is_some() {
+        None
+    } else {
+        filesystems
+            .iter()
+            .position(|(_, fs)| fs.name() == "sfs")
+            .or_else(|| if filesystems.is_empty() { None } else { Some(0) })
+    };
+    match root {
+        Some(root) => {
+            let (index, fs) = &filesystems[root];
+            mount("/", fs.clone()).unwrap();
+            info!("{} on block{} mounted on /", fs.name(), index);
+        }
+        None => {
+            if initramfs.is_none() {
+                warn!("no initramfs or file system on block devices, using an empty tmpfs as root");
+            }
+            mount("/", initramfs.unwrap_or_else(TmpFs::new)).unwrap();
+            info!("tmpfs mounted on /");
+        }
+    }
+    let pseudo: [(&str, Arc<dyn FileSystem>); 2] =
+        [("/dev", DevFs::new()), ("/proc", ProcFs::new())];
+    for (path, fs) in pseudo.iter() {
+        match make_dirs(path).and_then(|_| mount(path, fs.clone())) {
+            Ok(()) => info!("{} mounted on {}", fs.name(), path),
+            Err(errno) => warn!("failed to mount {} on {}: {}", fs.name(), path, errno),
+        }
+    }
+    for (position, (index, fs)) in filesystems.iter().enumerate() {
+        if Some(position) == root {
+            continue;
+        }
+        let path = format!("/mnt/block{}", index);
+        match make_dirs(&path).and_then(|_| mount(&path, fs.clone())) {
+            Ok(()) => info!("{} on block{} mounted on {}", fs.name(), index, path),
+            Err(errno) => warn!("failed to mount block{} on {}: {}", index, path, errno),
+        }
+    }
+    info!("mod fs initialized");
+}
+
+/// 依次创建路径中不存在的目录
+fn make_dirs(path: &str) -> Result<(), isize> {
+    let mut current = String::new();
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        current.push('/');
+        current.push_str(name);
+        match lookup(&current) {
+            Ok(_) => {}
+            Err(ENOENT) => {
+                let (parent, name) = lookup_parent(&current)?;
+                parent.create(&name, FileType::Directory)?;
+            }
+            Err(errno) => return Err(errno),
+        }
+    }
+    Ok(())
+}
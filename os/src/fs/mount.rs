@@ -0,0 +1,143 @@
+//! 挂载点和路径解析
+//!
+//! 第一个挂载到 `/` 的文件系统作为根文件系统，其他文件系统挂载在某个已有的目录上。
+//! 路径解析时，走到挂载点目录就转到被挂载的文件系统的根目录。
+//!
+//! 目前没有工作目录，相对路径也从根目录开始解析。
+//! `..` 按照已经走过的路径回退，因此可以从被挂载的文件系统回到挂载点的上一级
+
+use super::*;
+use crate::kernel::errno::*;
+use crate::sync::Lock;
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+
+/// 一个挂载的文件系统
+struct Mount {
+    /// 挂载的路径
+    path: String,
+    /// 挂载点目录的（设备号，inode 编号），根文件系统为 `None`
+    point: Option<(usize, usize)>,
+    fs: Arc<dyn FileSystem>,
+}
+
+/// 所有挂载的文件系统，第一个为根文件系统
+static MOUNTS: Lock<Vec<Mount>> = Lock::new(Vec::new());
+
+/// 将 `fs` 挂载到目录 `path`，没有根文件系统时 `path` 必须为 `/`
+///
+/// 解析路径时需要获取 [`MOUNTS`] 的锁，因此先找到挂载点，再在同一次加锁中检查并插入
+pub fn mount(path: &str, fs: Arc<dyn FileSystem>) -> Result<(), isize> {
+    let point = if MOUNTS.lock().is_empty() {
+        if path != "/" {
+            return Err(ENOENT);
+        }
+        None
+    } else {
+        let metadata = lookup(path)?.metadata()?;
+        if metadata.file_type != FileType::Directory {
+            return Err(ENOTDIR);
+        }
+        Some((metadata.dev, metadata.inode))
+    };
+    let mut mounts = MOUNTS.lock();
+    // 根文件系统只能挂载一次，同一个挂载点也只能挂载一个文件系统
+    if mounts.iter().any(|mount| mount.point == point) {
+        return Err(EBUSY);
+    }
+    mounts.push(Mount {
+        path: String::from(path),
+        point,
+        fs,
+    });
+    Ok(())
+}
+
+/// 所有挂载的（路径，文件系统类型）
+pub fn mounts() -> Vec<(String, &'static str)> {
+    MOUNTS
+        .lock()
+        .iter()
+        .map(|mount| (mount.path.clone(), mount.fs.name()))
+        .collect()
+}
+
+/// 将所有文件系统的修改写回
+pub fn sync_all() -> Result<(), isize> {
+    let filesystems: Vec<_> = MOUNTS.lock().iter().map(|mount| mount.fs.clone()).collect();
+    for fs in filesystems {
+        fs.sync()?;
+    }
+    Ok(())
+}
+
+/// 根目录
+fn root() -> Result<Arc<dyn INode>, isize> {
+    let fs = MOUNTS
+        .lock()
+        .first()
+        .map(|mount| mount.fs.clone())
+        .ok_or(ENOENT)?;
+    Ok(fs.root())
+}
+
+/// 如果 `inode` 是挂载点，则返回被挂载的文件系统的根目录
+fn cross_mount(inode: Arc<dyn INode>) -> Result<Arc<dyn INode>, isize> {
+    let metadata = inode.metadata()?;
+    if metadata.file_type != FileType::Directory {
+        return Ok(inode);
+    }
+    let point = Some((metadata.dev, metadata.inode));
+    let fs = MOUNTS
+        .lock()
+        .iter()
+        .find(|mount| mount.point == point)
+        .map(|mount| mount.fs.clone());
+    Ok(match fs {
+        Some(fs) => fs.root(),
+        None => inode,
+    })
+}
+
+/// 解析路径，返回从根目录开始经过的所有目录和最终的文件
+fn resolve(path: &str) -> Result<Vec<Arc<dyn INode>>, isize> {
+    let mut stack = vec![root()?];
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        match name {
+            "." => {}
+            ".." => {
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+            }
+            name => {
+                let next = stack.last().unwrap().lookup(name)?;
+                stack.push(cross_mount(next)?);
+            }
+        }
+    }
+    Ok(stack)
+}
+
+/// 查找路径对应的文件或目录
+pub fn lookup(path: &str) -> Result<Arc<dyn INode>, isize> {
+    Ok(resolve(path)?.pop().unwrap())
+}
+
+/// 查找路径的上一级目录，返回目录和最后一项的名称
+///
+/// 最后一项不能是 `.` 或 `..`
+pub fn lookup_parent(path: &str) -> Result<(Arc<dyn INode>, String), isize> {
+    let path = path.trim_end_matches('/');
+    let (parent, name) = match path.rfind('/') {
+        Some(index) => (&path[..index], &path[index + 1..]),
+        None => ("", path),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(EINVAL);
+    }
+    let parent = lookup(parent)?;
+    if parent.metadata()?.file_type != FileType::Directory {
+        return Err(ENOTDIR);
+    }
+    Ok((parent, String::from(name)))
+}
@@ -0,0 +1,408 @@
+//! 进程文件系统 procfs，挂载在 `/proc`
+//!
+//! 文件的内容在每次读取时生成，不能写入：
+//! - `meminfo`：物理页、堆和各个缓存的使用情况
+//! - `uptime`：启动以来的秒数
+//! - `interrupts`：每个 hart 上各类中断和异常的次数
+//! - `processes`：所有进程的列表
+//! - `<pid>/status`、`<pid>/maps`：进程的状态和地址空间中的各个映射片段
+//! - `self`：当前进程的 `<pid>` 目录
+//!
+//! 读取时需要获取进程和分配器的锁，不能在中断处理中使用
+
+use super::*;
+use crate::hart::{self, MAX_HARTS};
+use crate::interrupt::{interrupt_counts, timer, INTERRUPT_KINDS};
+use crate::memory::config::PAGE_SIZE;
+use crate::memory::mapping::{Flags, MapType};
+use crate::memory::{frame, heap, stats};
+use crate::process::{current_process, processes, Process};
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::sync::Weak;
+use core::fmt::Write;
+
+/// 根目录的 inode 编号
+const ROOT_INODE: usize = 1;
+
+/// 进程目录的 inode 编号为 `PROCESS_INODE_BASE + pid * PROCESS_INODE_STRIDE`，
+/// 目录中的文件依次加 1
+const PROCESS_INODE_BASE: usize = 0x1000;
+const PROCESS_INODE_STRIDE: usize = 4;
+
+/// 生成文件内容的函数
+type Generator = Box<dyn Fn() -> Result<String, isize> + Send + Sync>;
+
+/// 根目录中的文件，`(文件名, inode 编号, 生成函数)`
+const ROOT_FILES: [(&str, usize, fn() -> Result<String, isize>); 4] = [
+    ("meminfo", 2, meminfo),
+    ("uptime", 3, uptime),
+    ("interrupts", 4, interrupts),
+    ("processes", 5, process_list),
+];
+
+/// procfs 文件系统
+pub struct ProcFs {
+    dev: usize,
+}
+
+impl ProcFs {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { dev: alloc_dev() })
+    }
+}
+
+impl FileSystem for ProcFs {
+    fn name(&self) -> &'static str {
+        "procfs"
+    }
+
+    fn root(&self) -> Arc<dyn INode> {
+        Arc::new(ProcRoot { dev: self.dev })
+    }
+}
+
+/// 目录的信息
+fn directory_metadata(dev: usize, inode: usize) -> Metadata {
+    Metadata {
+        dev,
+        inode,
+        file_type: FileType::Directory,
+        mode: 0o555,
+        nlinks: 2,
+        size: 0,
+        blocks: 0,
+    }
+}
+
+/// procfs 的根目录
+struct ProcRoot {
+    dev: usize,
+}
+
+impl ProcRoot {
+    fn process_directory(&self, process: &Arc<Process>) -> Arc<dyn INode> {
+        Arc::new(ProcessDirectory {
+            dev: self.dev,
+            pid: process.pid,
+            process: Arc::downgrade(process),
+        })
+    }
+}
+
+impl INode for ProcRoot {
+    fn metadata(&self) -> Result<Metadata, isize> {
+        Ok(directory_metadata(self.dev, ROOT_INODE))
+    }
+
+    fn read_at(&self, _offset: usize, _buffer: &mut [u8]) -> Result<usize, isize> {
+        Err(EISDIR)
+    }
+
+    fn write_at(&self, _offset: usize, _buffer: &[u8]) -> Result<usize, isize> {
+        Err(EISDIR)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn INode>, isize> {
+        if let Some(&(_, inode, generate)) = ROOT_FILES.iter().find(|file| file.0 == name) {
+            return Ok(Arc::new(ProcFile {
+                dev: self.dev,
+                inode,
+                generate: Box::new(generate),
+            }));
+        }
+        if name == "self" {
+            return Ok(self.process_directory(&current_process()));
+        }
+        let pid = name.parse::<usize>().map_err(|_| ENOENT)?;
+        processes()
+            .iter()
+            .find(|process| process.pid == pid)
+            .map(|process| self.process_directory(process))
+            .ok_or(ENOENT)
+    }
+
+    fn create(&self, _name: &str, _file_type: FileType) -> Result<Arc<dyn INode>, isize> {
+        Err(EACCES)
+    }
+
+    fn unlink(&self, _name: &str) -> Result<(), isize> {
+        Err(EACCES)
+    }
+
+    /// 依次为根目录中的文件、`self` 和每个进程的目录
+    ///
+    /// 进程目录按 pid 排列，每次调用都重新取得当前进程的列表并按序号取出。
+    /// 两次调用之间有进程创建或退出时，可能跳过或重复一个进程，与其他文件系统中
+    /// 遍历时目录被修改的情况相同
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, isize> {
+        if let Some(&(name, inode, _)) = ROOT_FILES.get(index) {
+            return Ok(Some(DirEntry {
+                name: name.to_string(),
+                inode,
+                file_type: FileType::Regular,
+            }));
+        }
+        let index = index - ROOT_FILES.len();
+        let process = if index == 0 {
+            current_process()
+        } else {
+            match processes().get(index - 1) {
+                Some(process) => process.clone(),
+                None => return Ok(None),
+            }
+        };
+        Ok(Some(DirEntry {
+            name: if index == 0 {
+                "self".to_string()
+            } else {
+                process.pid.to_string()
+            },
+            inode: PROCESS_INODE_BASE + process.pid * PROCESS_INODE_STRIDE,
+            file_type: FileType::Directory,
+        }))
+    }
+}
+
+/// 一个进程的目录
+struct ProcessDirectory {
+    dev: usize,
+    pid: usize,
+    /// 不阻止进程被回收，回收后目录中的文件不再存在
+    process: Weak<Process>,
+}
+
+/// 进程目录中的文件，`(文件名, 生成函数)`
+const PROCESS_FILES: [(&str, fn(&Process) -> String); 2] =
+    [("status", process_status), ("maps", process_maps)];
+
+impl INode for ProcessDirectory {
+    fn metadata(&self) -> Result<Metadata, isize> {
+        Ok(directory_metadata(
+            self.dev,
+            PROCESS_INODE_BASE + self.pid * PROCESS_INODE_STRIDE,
+        ))
+    }
+
+    fn read_at(&self, _offset: usize, _buffer: &mut [u8]) -> Result<usize, isize> {
+        Err(EISDIR)
+    }
+
+    fn write_at(&self, _offset: usize, _buffer: &[u8]) -> Result<usize, isize> {
+        Err(EISDIR)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn INode>, isize> {
+        let index = PROCESS_FILES
+            .iter()
+            .position(|file| file.0 == name)
+            .ok_or(ENOENT)?;
+        let generate = PROCESS_FILES[index].1;
+        let process = self.process.clone();
+        Ok(Arc::new(ProcFile {
+            dev: self.dev,
+            inode: PROCESS_INODE_BASE + self.pid * PROCESS_INODE_STRIDE + index + 1,
+            generate: Box::new(move || {
+                let process = process.upgrade().ok_or(ENOENT)?;
+                Ok(generate(&process))
+            }),
+        }))
+    }
+
+    fn create(&self, _name: &str, _file_type: FileType) -> Result<Arc<dyn INode>, isize> {
+        Err(EACCES)
+    }
+
+    fn unlink(&self, _name: &str) -> Result<(), isize> {
+        Err(EACCES)
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, isize> {
+        Ok(PROCESS_FILES.get(index).map(|(name, _)| DirEntry {
+            name: name.to_string(),
+            inode: PROCESS_INODE_BASE + self.pid * PROCESS_INODE_STRIDE + index + 1,
+            file_type: FileType::Regular,
+        }))
+    }
+}
+
+/// 内容在读取时生成的文件
+struct ProcFile {
+    dev: usize,
+    inode: usize,
+    generate: Generator,
+}
+
+impl INode for ProcFile {
+    /// 文件大小为当前生成的内容的长度
+    fn metadata(&self) -> Result<Metadata, isize> {
+        Ok(Metadata {
+            dev: self.dev,
+            inode: self.inode,
+            file_type: FileType::Regular,
+            mode: 0o444,
+            nlinks: 1,
+            size: (self.generate)()?.len(),
+            blocks: 0,
+        })
+    }
+
+    /// 每次读取都重新生成内容，分多次读取时内容可能不一致
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, isize> {
+        let content = (self.generate)()?;
+        let content = content.as_bytes();
+        if offset >= content.len() {
+            return Ok(0);
+        }
+        let len = buffer.len().min(content.len() - offset);
+        buffer[..len].copy_from_slice(&content[offset..offset + len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, _offset: usize, _buffer: &[u8]) -> Result<usize, isize> {
+        Err(EACCES)
+    }
+}
+
+/// 物理页、堆和缓存的使用情况，单位为 kB
+fn meminfo() -> Result<String, isize> {
+    let frames = frame::stats();
+    let heap = heap::stats();
+    let page_kb = PAGE_SIZE / 1024;
+    let mut text = String::new();
+    let _ = writeln!(text, "MemTotal:      {:>10} kB", frames.total * page_kb);
+    let _ = writeln!(text, "MemFree:       {:>10} kB", frames.free * page_kb);
+    let _ = writeln!(text, "FrameCached:   {:>10} kB", frames.cached * page_kb);
+    let _ = writeln!(text, "HeapTotal:     {:>10} kB", heap.total / 1024);
+    let _ = writeln!(text, "HeapAllocated: {:>10} kB", heap.allocated / 1024);
+    let _ = writeln!(text, "HeapRequested: {:>10} kB", heap.requested / 1024);
+    let _ = writeln!(
+        text,
+        "\n{:<12} {:>8} {:>8} {:>10} {:>10}",
+        "cache", "cached", "capacity", "hits", "misses"
+    );
+    for cache in stats::caches() {
+        let _ = writeln!(
+            text,
+            "{:<12} {:>8} {:>8} {:>10} {:>10}",
+            cache.name, cache.cached, cache.capacity, cache.hits, cache.misses
+        );
+    }
+    Ok(text)
+}
+
+/// 启动以来的秒数，保留两位小数
+fn uptime() -> Result<String, isize> {
+    let now = timer::now();
+    Ok(format!(
+        "{}.{:02}\n",
+        now.as_secs(),
+        now.subsec_millis() / 10
+    ))
+}
+
+/// 每个在线的 hart 上各类中断和异常的次数
+fn interrupts() -> Result<String, isize> {
+    let online = hart::online_mask();
+    let harts: Vec<usize> = (0..MAX_HARTS)
+        .filter(|hart| online & (1 << hart) != 0)
+        .collect();
+    let counts = interrupt_counts();
+    let mut text = format!("{:<14}", "");
+    for hart in harts.iter() {
+        let _ = write!(text, " {:>10}", format!("hart{}", hart));
+    }
+    text.push('\n');
+    for (kind, counts) in INTERRUPT_KINDS.iter().zip(counts.iter()) {
+        let _ = write!(text, "{:<14}", format!("{}:", kind));
+        for &hart in harts.iter() {
+            let _ = write!(text, " {:>10}", counts[hart]);
+        }
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// 进程的状态
+fn state(process: &Process) -> &'static str {
+    if process.exit_code().is_some() {
+        "zombie"
+    } else {
+        "running"
+    }
+}
+
+/// 父进程的 pid，内核进程和初始进程为 0
+fn parent_pid(process: &Process) -> usize {
+    process.parent().map_or(0, |parent| parent.pid)
+}
+
+/// 所有进程的 pid、父进程、状态、打开的文件数和分配的物理页数
+fn process_list() -> Result<String, isize> {
+    let mut text = format!(
+        "{:>5} {:>5} {:<8} {:>5} {:>6}\n",
+        "PID", "PPID", "STATE", "FDS", "PAGES"
+    );
+    for process in processes() {
+        let (descriptors, pages) = {
+            let inner = process.inner();
+            let descriptors = inner
+                .descriptors
+                .iter()
+                .filter(|file| file.is_some())
+                .count();
+            (descriptors, inner.memory_set.allocated_pairs.len())
+        };
+        let _ = writeln!(
+            text,
+            "{:>5} {:>5} {:<8} {:>5} {:>6}",
+            process.pid,
+            parent_pid(&process),
+            state(&process),
+            descriptors,
+            pages
+        );
+    }
+    Ok(text)
+}
+
+/// `<pid>/status`
+fn process_status(process: &Process) -> String {
+    let inner = process.inner();
+    format!(
+        "Pid:\t{}\nPPid:\t{}\nState:\t{}\nUser:\t{}\nFds:\t{}\nPages:\t{}\n",
+        process.pid,
+        parent_pid(process),
+        state(process),
+        if process.is_user { "yes" } else { "no" },
+        inner
+            .descriptors
+            .iter()
+            .filter(|file| file.is_some())
+            .count(),
+        inner.memory_set.allocated_pairs.len(),
+    )
+}
+
+/// `<pid>/maps`，每行为一个映射片段：起止地址、权限（读、写、执行、用户）和映射类型
+fn process_maps(process: &Process) -> String {
+    let flag = |flags: Flags, bit: Flags, c: char| if flags.contains(bit) { c } else { '-' };
+    let mut text = String::new();
+    for segment in process.inner().memory_set.segments.iter() {
+        let _ = writeln!(
+            text,
+            "{:016x}-{:016x} {}{}{}{} {}",
+            segment.range.start.0,
+            segment.range.end.0,
+            flag(segment.flags, Flags::READABLE, 'r'),
+            flag(segment.flags, Flags::WRITABLE, 'w'),
+            flag(segment.flags, Flags::EXECUTABLE, 'x'),
+            flag(segment.flags, Flags::USER, 'u'),
+            match segment.map_type {
+                MapType::Linear => "linear",
+                MapType::Framed => "framed",
+            }
+        );
+    }
+    text
+}
@@ -0,0 +1,212 @@
+//! 块设备上的 SFS 文件系统，磁盘格式由 `easy-fs` 实现
+//!
+//! `easy-fs` 本身不加锁，这里用 [`Mutex`] 保护整个文件系统，读写块设备时线程可以睡眠。
+//!
+//! 磁盘上不记录打开的次数，删除仍被打开的文件后，其 inode 可能被重新分配
+
+use super::*;
+use crate::drivers::block::{self, BlockCache};
+use crate::sync::Mutex;
+use alloc::string::String;
+use easy_fs::{EasyFileSystem, Error, InodeKind, BLOCK_SIZE};
+
+/// 将 `easy-fs` 的错误转换为错误码
+fn errno(error: Error) -> isize {
+    match error {
+        Error::Io => EIO,
+        Error::InvalidImage => EINVAL,
+        Error::NoSpace | Error::FileTooLarge => ENOSPC,
+        Error::NotFound => ENOENT,
+        Error::Exists => EEXIST,
+        Error::NotDir => ENOTDIR,
+        Error::NotEmpty => ENOTEMPTY,
+        Error::InvalidName => ENAMETOOLONG,
+    }
+}
+
+/// 通过块缓存读写的块设备
+struct Disk(Arc<BlockCache>);
+
+impl easy_fs::BlockDevice for Disk {
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> easy_fs::Result<()> {
+        block::BlockDevice::read_block(&*self.0, block_id, buffer).map_err(|_| Error::Io)
+    }
+
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> easy_fs::Result<()> {
+        block::BlockDevice::write_block(&*self.0, block_id, buffer).map_err(|_| Error::Io)
+    }
+}
+
+/// 文件系统和所有 inode 共享的部分
+struct Shared {
+    dev: usize,
+    disk: Arc<BlockCache>,
+    fs: Mutex<EasyFileSystem>,
+}
+
+/// SFS 文件系统
+pub struct SimpleFileSystem {
+    shared: Arc<Shared>,
+}
+
+/// 格式化时 inode 位图的块数，最多 4096 个文件
+const INODE_BITMAP_BLOCKS: usize = 1;
+
+impl SimpleFileSystem {
+    /// 打开块设备上的文件系统，需要读取块设备，只能在线程中调用
+    pub fn open(disk: Arc<BlockCache>) -> Result<Arc<Self>, isize> {
+        assert_eq!(BLOCK_SIZE, block::BLOCK_SIZE);
+        let fs = EasyFileSystem::open(Arc::new(Disk(disk.clone()))).map_err(errno)?;
+        Ok(Self::new(disk, fs))
+    }
+
+    /// 在块设备上建立一个空的文件系统
+    pub fn format(disk: Arc<BlockCache>) -> Result<Arc<Self>, isize> {
+        use block::BlockDevice;
+        let total_blocks = disk.num_blocks();
+        let fs = EasyFileSystem::format(
+            Arc::new(Disk(disk.clone())),
+            total_blocks,
+            INODE_BITMAP_BLOCKS,
+        )
+        .map_err(errno)?;
+        Ok(Self::new(disk, fs))
+    }
+
+    fn new(disk: Arc<BlockCache>, fs: EasyFileSystem) -> Arc<Self> {
+        Arc::new(Self {
+            shared: Arc::new(Shared {
+                dev: alloc_dev(),
+                disk,
+                fs: Mutex::new(fs),
+            }),
+        })
+    }
+}
+
+impl FileSystem for SimpleFileSystem {
+    fn name(&self) -> &'static str {
+        "sfs"
+    }
+
+    fn root(&self) -> Arc<dyn INode> {
+        let id = self.shared.fs.lock().root_inode();
+        Arc::new(SfsINode {
+            id,
+            shared: self.shared.clone(),
+        })
+    }
+
+    fn sync(&self) -> Result<(), isize> {
+        self.shared.disk.sync().map_err(|_| EIO)
+    }
+}
+
+/// SFS 中的文件或目录
+struct SfsINode {
+    id: u32,
+    shared: Arc<Shared>,
+}
+
+impl SfsINode {
+    fn child(&self, id: u32) -> Arc<dyn INode> {
+        Arc::new(SfsINode {
+            id,
+            shared: self.shared.clone(),
+        })
+    }
+}
+
+/// 将 `easy-fs` 的 inode 类型转换为 [`FileType`]
+fn file_type(kind: InodeKind) -> FileType {
+    match kind {
+        InodeKind::File => FileType::Regular,
+        InodeKind::Directory => FileType::Directory,
+    }
+}
+
+impl INode for SfsINode {
+    fn metadata(&self) -> Result<Metadata, isize> {
+        let stat = self.shared.fs.lock().stat(self.id).map_err(errno)?;
+        let file_type = file_type(stat.kind);
+        Ok(Metadata {
+            dev: self.shared.dev,
+            inode: self.id as usize,
+            file_type,
+            mode: if file_type == FileType::Directory {
+                0o755
+            } else {
+                0o644
+            },
+            nlinks: stat.nlinks,
+            size: stat.size,
+            blocks: stat.blocks,
+        })
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, isize> {
+        let fs = self.shared.fs.lock();
+        if fs.stat(self.id).map_err(errno)?.kind == InodeKind::Directory {
+            return Err(EISDIR);
+        }
+        fs.read_at(self.id, offset, buffer).map_err(errno)
+    }
+
+    fn write_at(&self, offset: usize, buffer: &[u8]) -> Result<usize, isize> {
+        let fs = self.shared.fs.lock();
+        if fs.stat(self.id).map_err(errno)?.kind == InodeKind::Directory {
+            return Err(EISDIR);
+        }
+        fs.write_at(self.id, offset, buffer).map_err(errno)
+    }
+
+    fn resize(&self, len: usize) -> Result<(), isize> {
+        let fs = self.shared.fs.lock();
+        if fs.stat(self.id).map_err(errno)?.kind == InodeKind::Directory {
+            return Err(EISDIR);
+        }
+        fs.resize(self.id, len).map_err(errno)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn INode>, isize> {
+        let id = self.shared.fs.lock().lookup(self.id, name).map_err(errno)?;
+        Ok(self.child(id))
+    }
+
+    fn create(&self, name: &str, file_type: FileType) -> Result<Arc<dyn INode>, isize> {
+        let kind = match file_type {
+            FileType::Regular => InodeKind::File,
+            FileType::Directory => InodeKind::Directory,
+            _ => return Err(EINVAL),
+        };
+        let id = self
+            .shared
+            .fs
+            .lock()
+            .create(self.id, name, kind)
+            .map_err(errno)?;
+        Ok(self.child(id))
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), isize> {
+        self.shared.fs.lock().unlink(self.id, name).map_err(errno)
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, isize> {
+        let fs = self.shared.fs.lock();
+        let (name, id): (String, u32) = match fs.readdir(self.id, index).map_err(errno)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let stat = fs.stat(id).map_err(errno)?;
+        Ok(Some(DirEntry {
+            name,
+            inode: id as usize,
+            file_type: file_type(stat.kind),
+        }))
+    }
+
+    fn sync(&self) -> Result<(), isize> {
+        self.shared.disk.sync().map_err(|_| EIO)
+    }
+}
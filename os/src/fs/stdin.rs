@@ -0,0 +1,40 @@
+//! 控制台输入 [`Stdin`]
+
+use super::*;
+use crate::drivers::uart;
+use crate::process::yield_current_thread;
+use crate::sbi::console_getchar;
+
+/// 控制台输入，读取时至少等到一个字符
+///
+/// 优先从串口的输入缓冲区读取，串口不可用时轮询 SBI
+pub struct Stdin;
+
+impl File for Stdin {
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, isize> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        if uart::is_ready() {
+            buffer[0] = uart::getchar();
+            return Ok(1);
+        }
+        // 没有输入时 SBI 返回 -1，让出处理器后再次查询
+        loop {
+            let c = console_getchar();
+            if c != usize::MAX {
+                buffer[0] = c as u8;
+                return Ok(1);
+            }
+            yield_current_thread();
+        }
+    }
+
+    fn write(&self, _buffer: &[u8]) -> Result<usize, isize> {
+        Err(EBADF)
+    }
+
+    fn metadata(&self) -> Result<Metadata, isize> {
+        Ok(console_metadata())
+    }
+}
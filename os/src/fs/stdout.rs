@@ -0,0 +1,21 @@
+//! 控制台输出 [`Stdout`]
+
+use super::*;
+
+/// 控制台输出
+pub struct Stdout;
+
+impl File for Stdout {
+    fn read(&self, _buffer: &mut [u8]) -> Result<usize, isize> {
+        Err(EBADF)
+    }
+
+    fn write(&self, buffer: &[u8]) -> Result<usize, isize> {
+        crate::console::write_bytes(buffer);
+        Ok(buffer.len())
+    }
+
+    fn metadata(&self) -> Result<Metadata, isize> {
+        Ok(console_metadata())
+    }
+}
@@ -0,0 +1,268 @@
+//! 内存中的文件系统 tmpfs
+//!
+//! 文件内容保存在从 [`frame::alloc`] 分配的物理页中，目录是从名称到 inode 的有序表。
+//! 不需要读写块设备，因此在块设备驱动初始化之前就可以使用，也可以在中断关闭时访问。
+//!
+//! 每个 inode 各自加锁；需要同时持有多个锁时总是先锁父目录再锁子项。
+//! 删除仍被打开的文件后，其内容保留到最后一个引用释放为止
+
+use super::*;
+use crate::memory::config::PAGE_SIZE;
+use crate::memory::frame::{self, FrameTracker};
+use crate::sync::Lock;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 文件名的最大长度
+const NAME_MAX: usize = 255;
+
+/// 扩大文件时至少保留的空闲物理页，留给内核栈和页表等使用
+const RESERVED_FRAMES: usize = 256;
+
+/// 文件系统和所有 inode 共享的部分
+struct Shared {
+    dev: usize,
+    /// 下一个可用的 inode 编号
+    next_inode: AtomicUsize,
+}
+
+/// tmpfs 文件系统
+pub struct TmpFs {
+    root: Arc<TmpINode>,
+}
+
+impl TmpFs {
+    /// 创建一个只有根目录的文件系统
+    pub fn new() -> Arc<Self> {
+        let shared = Arc::new(Shared {
+            dev: alloc_dev(),
+            next_inode: AtomicUsize::new(1),
+        });
+        Arc::new(Self {
+            root: TmpINode::new(&shared, FileType::Directory),
+        })
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn name(&self) -> &'static str {
+        "tmpfs"
+    }
+
+    fn root(&self) -> Arc<dyn INode> {
+        self.root.clone()
+    }
+}
+
+/// 普通文件的内容
+///
+/// 始终保证 `[size, pages.len() * PAGE_SIZE)` 中的字节为 0，扩大文件时无需再清零
+struct FileData {
+    size: usize,
+    pages: Vec<FrameTracker>,
+}
+
+impl FileData {
+    /// 将文件大小改为 `len`，内存不足时返回 `ENOSPC` 且不改变文件
+    fn resize(&mut self, len: usize) -> Result<(), isize> {
+        let pages = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        if len < self.size {
+            self.pages.truncate(pages);
+            if len % PAGE_SIZE != 0 {
+                self.pages[len / PAGE_SIZE][len % PAGE_SIZE..].fill(0);
+            }
+        }
+        if pages > self.pages.len() {
+            let old_pages = self.pages.len();
+            if pages - old_pages + RESERVED_FRAMES > frame::stats().free {
+                return Err(ENOSPC);
+            }
+            while self.pages.len() < pages {
+                match frame::alloc() {
+                    Ok(mut page) => {
+                        page.fill(0);
+                        self.pages.push(page);
+                    }
+                    // 分配失败时释放这次分配的页，文件保持原来的大小
+                    Err(_) => {
+                        self.pages.truncate(old_pages);
+                        return Err(ENOSPC);
+                    }
+                }
+            }
+        }
+        self.size = len;
+        Ok(())
+    }
+}
+
+/// inode 的内容
+enum Content {
+    File(FileData),
+    Directory(BTreeMap<String, Arc<TmpINode>>),
+}
+
+/// tmpfs 中的文件或目录
+struct TmpINode {
+    shared: Arc<Shared>,
+    inode: usize,
+    file_type: FileType,
+    content: Lock<Content>,
+}
+
+impl TmpINode {
+    fn new(shared: &Arc<Shared>, file_type: FileType) -> Arc<Self> {
+        let content = match file_type {
+            FileType::Directory => Content::Directory(BTreeMap::new()),
+            _ => Content::File(FileData {
+                size: 0,
+                pages: Vec::new(),
+            }),
+        };
+        Arc::new(Self {
+            shared: shared.clone(),
+            inode: shared.next_inode.fetch_add(1, Ordering::Relaxed),
+            file_type,
+            content: Lock::new(content),
+        })
+    }
+}
+
+impl INode for TmpINode {
+    fn metadata(&self) -> Result<Metadata, isize> {
+        let (nlinks, size, pages) = match &*self.content.lock() {
+            Content::File(data) => (1, data.size, data.pages.len()),
+            Content::Directory(children) => {
+                let subdirectories = children
+                    .values()
+                    .filter(|child| child.file_type == FileType::Directory)
+                    .count();
+                (2 + subdirectories, 0, 0)
+            }
+        };
+        Ok(Metadata {
+            dev: self.shared.dev,
+            inode: self.inode,
+            file_type: self.file_type,
+            mode: if self.file_type == FileType::Directory {
+                0o755
+            } else {
+                0o644
+            },
+            nlinks,
+            size,
+            blocks: pages * (PAGE_SIZE / 512),
+        })
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, isize> {
+        let content = self.content.lock();
+        let data = match &*content {
+            Content::File(data) => data,
+            Content::Directory(_) => return Err(EISDIR),
+        };
+        let end = data.size.min(offset.saturating_add(buffer.len()));
+        let mut position = offset;
+        while position < end {
+            let page = &data.pages[position / PAGE_SIZE];
+            let start = position % PAGE_SIZE;
+            let len = (PAGE_SIZE - start).min(end - position);
+            buffer[position - offset..position - offset + len]
+                .copy_from_slice(&page[start..start + len]);
+            position += len;
+        }
+        Ok(end.saturating_sub(offset))
+    }
+
+    fn write_at(&self, offset: usize, buffer: &[u8]) -> Result<usize, isize> {
+        let mut content = self.content.lock();
+        let data = match &mut *content {
+            Content::File(data) => data,
+            Content::Directory(_) => return Err(EISDIR),
+        };
+        let end = offset.checked_add(buffer.len()).ok_or(EINVAL)?;
+        if end > data.size {
+            data.resize(end)?;
+        }
+        let mut position = offset;
+        while position < end {
+            let page = &mut data.pages[position / PAGE_SIZE];
+            let start = position % PAGE_SIZE;
+            let len = (PAGE_SIZE - start).min(end - position);
+            page[start..start + len]
+                .copy_from_slice(&buffer[position - offset..position - offset + len]);
+            position += len;
+        }
+        Ok(buffer.len())
+    }
+
+    fn resize(&self, len: usize) -> Result<(), isize> {
+        match &mut *self.content.lock() {
+            Content::File(data) => data.resize(len),
+            Content::Directory(_) => Err(EISDIR),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn INode>, isize> {
+        match &*self.content.lock() {
+            Content::Directory(children) => match children.get(name) {
+                Some(child) => Ok(child.clone()),
+                None => Err(ENOENT),
+            },
+            Content::File(_) => Err(ENOTDIR),
+        }
+    }
+
+    fn create(&self, name: &str, file_type: FileType) -> Result<Arc<dyn INode>, isize> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(EINVAL);
+        }
+        if name.len() > NAME_MAX {
+            return Err(ENAMETOOLONG);
+        }
+        if file_type != FileType::Regular && file_type != FileType::Directory {
+            return Err(EINVAL);
+        }
+        let mut content = self.content.lock();
+        let children = match &mut *content {
+            Content::Directory(children) => children,
+            Content::File(_) => return Err(ENOTDIR),
+        };
+        if children.contains_key(name) {
+            return Err(EEXIST);
+        }
+        let child = TmpINode::new(&self.shared, file_type);
+        children.insert(name.to_string(), child.clone());
+        Ok(child)
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), isize> {
+        let mut content = self.content.lock();
+        let children = match &mut *content {
+            Content::Directory(children) => children,
+            Content::File(_) => return Err(ENOTDIR),
+        };
+        let child = children.get(name).ok_or(ENOENT)?;
+        if let Content::Directory(grandchildren) = &*child.content.lock() {
+            if !grandchildren.is_empty() {
+                return Err(ENOTEMPTY);
+            }
+        }
+        children.remove(name);
+        Ok(())
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, isize> {
+        match &*self.content.lock() {
+            Content::Directory(children) => {
+                Ok(children.iter().nth(index).map(|(name, child)| DirEntry {
+                    name: name.clone(),
+                    inode: child.inode,
+                    file_type: child.file_type,
+                }))
+            }
+            Content::File(_) => Err(ENOTDIR),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! 虚拟文件系统（VFS）的接口
+//!
+//! 每个文件系统实现 [`FileSystem`]，其中的文件和目录实现 [`INode`]。
+//! 接口出错时直接返回错误码，与系统调用一致
+
+use crate::kernel::errno::*;
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 文件的类型
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileType {
+    Regular,
+    Directory,
+    CharDevice,
+    BlockDevice,
+}
+
+impl FileType {
+    /// `st_mode` 中表示类型的位
+    pub fn mode_bits(self) -> u32 {
+        match self {
+            FileType::Regular => 0o100000,
+            FileType::Directory => 0o040000,
+            FileType::CharDevice => 0o020000,
+            FileType::BlockDevice => 0o060000,
+        }
+    }
+}
+
+/// 文件的信息
+#[derive(Clone, Copy, Debug)]
+pub struct Metadata {
+    /// 所在文件系统的设备号
+    pub dev: usize,
+    /// 在文件系统中的编号，与 `dev` 一起唯一确定一个文件
+    pub inode: usize,
+    pub file_type: FileType,
+    /// 权限位，例如 `0o644`
+    pub mode: u32,
+    /// 硬链接数
+    pub nlinks: usize,
+    /// 文件大小，单位为字节
+    pub size: usize,
+    /// 占用的 512 字节块数
+    pub blocks: usize,
+}
+
+/// 与 Linux（riscv64）布局相同的 `struct stat`，由 `fstat` 写给用户程序
+#[repr(C)]
+#[derive(Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    _pad0: u64,
+    pub size: i64,
+    pub blksize: u32,
+    _pad1: u32,
+    pub blocks: u64,
+    pub atime: [i64; 2],
+    pub mtime: [i64; 2],
+    pub ctime: [i64; 2],
+    _unused: [u32; 2],
+}
+
+impl From<Metadata> for Stat {
+    fn from(metadata: Metadata) -> Self {
+        Stat {
+            dev: metadata.dev as u64,
+            ino: metadata.inode as u64,
+            mode: metadata.file_type.mode_bits() | metadata.mode,
+            nlink: metadata.nlinks as u32,
+            size: metadata.size as i64,
+            blksize: 512,
+            blocks: metadata.blocks as u64,
+            ..Stat::default()
+        }
+    }
+}
+
+/// 目录中的一项
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    pub name: String,
+    pub inode: usize,
+    pub file_type: FileType,
+}
+
+/// 文件系统中的一个文件或目录
+///
+/// 目录相关的操作默认返回 `ENOTDIR`，只有目录需要实现
+pub trait INode: Send + Sync {
+    /// 文件的信息
+    fn metadata(&self) -> Result<Metadata, isize>;
+
+    /// 从 `offset` 开始读取，返回读取的字节数，到达文件末尾时返回 0
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, isize>;
+
+    /// 读取文件的全部内容
+    fn read_all(&self) -> Result<Vec<u8>, isize> {
+        let mut data = vec![0u8; self.metadata()?.size];
+        let mut position = 0;
+        while position < data.len() {
+            match self.read_at(position, &mut data[position..])? {
+                0 => break,
+                size => position += size,
+            }
+        }
+        data.truncate(position);
+        Ok(data)
+    }
+
+    /// 从 `offset` 开始写入，必要时扩展文件，返回写入的字节数
+    fn write_at(&self, offset: usize, buffer: &[u8]) -> Result<usize, isize>;
+
+    /// 将文件大小改为 `len`
+    fn resize(&self, _len: usize) -> Result<(), isize> {
+        Err(EINVAL)
+    }
+
+    /// 在目录中查找名为 `name` 的项
+    fn lookup(&self, _name: &str) -> Result<Arc<dyn INode>, isize> {
+        Err(ENOTDIR)
+    }
+
+    /// 在目录中创建名为 `name` 的文件或目录
+    fn create(&self, _name: &str, _file_type: FileType) -> Result<Arc<dyn INode>, isize> {
+        Err(ENOTDIR)
+    }
+
+    /// 从目录中删除名为 `name` 的项，目录必须为空
+    fn unlink(&self, _name: &str) -> Result<(), isize> {
+        Err(ENOTDIR)
+    }
+
+    /// 目录中的第 `index` 项（不包括 `.` 和 `..`），超出范围时返回 `None`
+    fn readdir(&self, _index: usize) -> Result<Option<DirEntry>, isize> {
+        Err(ENOTDIR)
+    }
+
+    /// 将修改写回存储设备
+    fn sync(&self) -> Result<(), isize> {
+        Ok(())
+    }
+}
+
+/// 一个文件系统
+pub trait FileSystem: Send + Sync {
+    /// 文件系统的类型名，例如 `tmpfs`
+    fn name(&self) -> &'static str;
+
+    /// 根目录
+    fn root(&self) -> Arc<dyn INode>;
+
+    /// 将所有修改写回存储设备
+    fn sync(&self) -> Result<(), isize> {
+        Ok(())
+    }
+}
+
+/// 下一个可用的设备号
+static DEV_COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+/// 为新的文件系统分配设备号
+pub fn alloc_dev() -> usize {
+    DEV_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
@@ -0,0 +1,58 @@
+//! 多个 hart（处理器核）的启动和编号
+//!
+//! 每个 hart 的 `tp` 寄存器保存其编号，内核中不会修改 `tp`。
+//! 从用户态进入中断时，`tp` 由 `interrupt.asm` 从 [`Context`](crate::interrupt::Context) 中恢复
+
+use crate::memory::config::KERNEL_MAP_OFFSET;
+use crate::sbi::{self, hsm};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 最多支持的 hart 数量，需要与 `entry.asm` 中的 `MAX_HARTS` 一致
+pub const MAX_HARTS: usize = 8;
+
+/// 已经完成初始化的 hart，第 i 位表示 hart i
+static ONLINE_MASK: AtomicUsize = AtomicUsize::new(0);
+
+/// 当前 hart 的编号
+#[inline(always)]
+pub fn hart_id() -> usize {
+    let id: usize;
+    unsafe { llvm_asm!("mv $0, tp" : "=r"(id) ::: "volatile") };
+    id
+}
+
+/// 已经完成初始化的 hart 的掩码
+pub fn online_mask() -> usize {
+    ONLINE_MASK.load(Ordering::Acquire)
+}
+
+/// 已经完成初始化的 hart 数量
+pub fn online_count() -> usize {
+    online_mask().count_ones() as usize
+}
+
+/// 标记当前 hart 已经完成初始化
+pub fn mark_online() {
+    ONLINE_MASK.fetch_or(1 << hart_id(), Ordering::Release);
+}
+
+/// 通过 SBI HSM 扩展启动其他处于停止状态的 hart，从 `_secondary_start` 开始执行
+pub fn start_secondary_harts() {
+    extern "C" {
+        fn _secondary_start();
+    }
+    if !sbi::has_hsm() {
+        warn!("SBI HSM extension is not available, running on hart {} only", hart_id());
+        return;
+    }
+    // HSM 启动的 hart 没有开启页表，需要使用物理地址
+    let start_address = _secondary_start as usize - KERNEL_MAP_OFFSET;
+    for id in (0..MAX_HARTS).filter(|&id| id != hart_id()) {
+        // 不存在的 hart 会返回错误
+        if let Ok(hsm::HartStatus::Stopped) = hsm::hart_get_status(id) {
+            if let Err(error) = hsm::hart_start(id, start_address, 0) {
+                warn!("failed to start hart {}: {:?}", id, error);
+            }
+        }
+    }
+}
@@ -1,9 +1,67 @@
-use riscv::register::{sstatus::Sstatus, scause::Scause};
+use core::mem::zeroed;
+use riscv::register::sstatus::{self, Sstatus, SPP};
 
+/// 发生中断时保存的寄存器
+///
+/// 从用户态进入中断时，`Context` 保存在当前线程内核栈的栈顶
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct Context {
     pub x: [usize; 32], // 32个通用寄存器
     pub sstatus: Sstatus,
     pub sepc: usize,
-}
\ No newline at end of file
+    /// 内核的 `tp`，即当前 hart 的编号
+    ///
+    /// 返回用户态时由 `__restore` 写入，从用户态进入中断时恢复
+    pub kernel_tp: usize,
+    /// 保持 `Context` 的大小为 16 字节的整数倍
+    _reserved: usize,
+}
+
+impl Context {
+    /// 获取栈指针
+    pub fn sp(&self) -> usize {
+        self.x[2]
+    }
+
+    /// 设置栈指针
+    pub fn set_sp(&mut self, value: usize) -> &mut Self {
+        self.x[2] = value;
+        self
+    }
+
+    /// 按照函数调用规则写入参数
+    ///
+    /// 没有考虑一些特殊情况，例如超过 8 个参数，或 struct 空间展开
+    pub fn set_arguments(&mut self, arguments: &[usize]) -> &mut Self {
+        assert!(arguments.len() <= 8);
+        self.x[10..(10 + arguments.len())].copy_from_slice(arguments);
+        self
+    }
+
+    /// 为线程构建初始 `Context`
+    ///
+    /// `sret` 之后跳转至 `entry_point`，并且打开中断
+    pub fn new(stack_top: usize, entry_point: usize, arguments: Option<&[usize]>, is_user: bool) -> Self {
+        let mut context: Self = unsafe { zeroed() };
+        // 设置栈顶指针
+        context.set_sp(stack_top);
+        // 设置初始参数
+        if let Some(args) = arguments {
+            context.set_arguments(args);
+        }
+        // 设置入口地址
+        context.sepc = entry_point;
+        // 设置 sstatus
+        context.sstatus = sstatus::read();
+        if is_user {
+            context.sstatus.set_spp(SPP::User);
+        } else {
+            context.sstatus.set_spp(SPP::Supervisor);
+        }
+        // 这样设置 SPIE 位，使得替换 sstatus 后关闭中断，
+        // 而在 sret 到线程时开启中断。详见 SPIE 和 SIE 的定义
+        context.sstatus.set_spie(true);
+        context
+    }
+}
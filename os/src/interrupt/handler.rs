@@ -1,18 +1,80 @@
 use super::context::Context;
-use riscv::register::stvec;
+use riscv::register::{sscratch, stvec};
 use riscv::register::scause::{Scause, Trap, Exception, Interrupt};
-use crate::interrupt::timer;
+use riscv::register::sstatus::SPP;
+use crate::drivers::plic;
+use crate::hart::{hart_id, MAX_HARTS};
+use crate::interrupt::{ipi, timer};
+use crate::kernel::{exit_current_process, syscall_handler};
+use crate::memory::user::search_exception_table;
+use crate::process::yield_current_thread;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
 
 global_asm!(include_str!("./interrupt.asm"));
 
+/// 分别计数的中断和异常类型，顺序与 [`kind`] 的返回值对应
+pub const INTERRUPT_KINDS: [&str; 8] = [
+    "timer",
+    "external",
+    "software",
+    "syscall",
+    "page fault",
+    "access fault",
+    "breakpoint",
+    "other",
+];
+
+lazy_static! {
+    /// 每个 hart 上各类中断和异常的次数
+    static ref COUNTERS: Vec<Vec<AtomicUsize>> = (0..MAX_HARTS)
+        .map(|_| INTERRUPT_KINDS.iter().map(|_| AtomicUsize::new(0)).collect())
+        .collect();
+}
+
+/// 中断或异常在 [`INTERRUPT_KINDS`] 中的下标
+fn kind(cause: Trap) -> usize {
+    match cause {
+        Trap::Interrupt(Interrupt::SupervisorTimer) => 0,
+        Trap::Interrupt(Interrupt::SupervisorExternal) => 1,
+        Trap::Interrupt(Interrupt::SupervisorSoft) => 2,
+        Trap::Exception(Exception::UserEnvCall) => 3,
+        Trap::Exception(Exception::InstructionPageFault)
+        | Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::StorePageFault) => 4,
+        // 物理内存保护等原因导致的访问错误，与缺页分开计数
+        Trap::Exception(Exception::InstructionFault)
+        | Trap::Exception(Exception::LoadFault)
+        | Trap::Exception(Exception::StoreFault) => 5,
+        Trap::Exception(Exception::Breakpoint) => 6,
+        _ => 7,
+    }
+}
+
+/// 各类中断和异常在每个 hart 上的次数，`counts[类型][hart]`，类型的顺序同 [`INTERRUPT_KINDS`]
+pub fn interrupt_counts() -> Vec<Vec<usize>> {
+    (0..INTERRUPT_KINDS.len())
+        .map(|kind| {
+            COUNTERS
+                .iter()
+                .map(|counters| counters[kind].load(Ordering::Relaxed))
+                .collect()
+        })
+        .collect()
+}
+
 /// 初始化中断处理
 ///
 /// 把中断入口 “__interrupt” 写入 'stvec' 中，并且开启中断使能
+///
+/// `sscratch` 置 0 表示当前处于内核态
 pub fn init() {
     unsafe {
         extern "C" {
             fn __interrupt();
         }
+        sscratch::write(0);
         stvec::write(__interrupt as usize, stvec::TrapMode::Direct);
     }
 }
@@ -23,12 +85,25 @@ pub fn init() {
 /// 具体的中断类型需要根据 scause 来推断, 然后分别处理
 #[no_mangle]
 pub fn handle_interrupt(context: &mut Context, scause: Scause, stval: usize) {
+    // 中断处理期间不会被打断，也不会迁移到其他 hart
+    COUNTERS[hart_id()][kind(scause.cause())].fetch_add(1, Ordering::Relaxed);
     // 可以通过 Debug 来查看发生了什么中断
     match scause.cause() {
         // 断点中断
         Trap::Exception(Exception::Breakpoint) => breakpoint(context),
+        // 系统调用
+        Trap::Exception(Exception::UserEnvCall) => syscall_handler(context),
         // 时钟中断
         Trap::Interrupt(Interrupt::SupervisorTimer) => supervisor_timer(context),
+        // 外部中断
+        Trap::Interrupt(Interrupt::SupervisorExternal) => supervisor_external(context),
+        // 软件中断，即其他 hart 发来的 IPI
+        Trap::Interrupt(Interrupt::SupervisorSoft) => supervisor_soft(context),
+        // 访存异常，可能是内核访问用户内存时出错
+        Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::LoadFault)
+        | Trap::Exception(Exception::StoreFault) => page_fault(context, scause, stval),
         // 其他情况，终止当前线程
         _ => fault(context, scause, stval),
     }
@@ -41,13 +116,50 @@ fn breakpoint(context: &mut Context) {
 
 /// 处理时钟中断
 ///
-/// 目前只会在 [`timer`] 模块中进行计数
+/// 在 [`timer`] 模块中进行计数，并切换到下一个线程
 fn supervisor_timer(_: &Context){
     timer::tick();
+    yield_current_thread();
+}
+
+/// 处理外部中断
+///
+/// 由 [`plic`] 分发给各个驱动
+fn supervisor_external(_: &Context) {
+    plic::handle_external();
+}
+
+/// 处理软件中断
+///
+/// 由 [`ipi`] 执行其他 hart 发来的调用
+fn supervisor_soft(_: &Context) {
+    ipi::handle();
 }
 
+/// 处理访存异常
+///
+/// 如果出错的指令登记在异常表中，说明是内核访问用户内存时出错，跳转至修复代码使其返回错误；
+/// 否则按 [`fault`] 处理
+fn page_fault(context: &mut Context, scause: Scause, stval: usize) {
+    match search_exception_table(context.sepc) {
+        Some(fixup) => context.sepc = fixup,
+        None => fault(context, scause, stval),
+    }
+}
 
+/// 无法处理的异常
+///
+/// 来自用户态的异常会结束当前进程，来自内核态的异常则 panic
 fn fault(context: &mut Context, scause: Scause, stval: usize) {
+    if context.sstatus.spp() == SPP::User {
+        warn!(
+            "{:?} at 0x{:x}, stval: 0x{:x}, process killed",
+            scause.cause(),
+            context.sepc,
+            stval,
+        );
+        exit_current_process(-1);
+    }
     panic!(
         "Unresolved interrupt: {:?}\n{:x?}\nstval: {:x}",
         scause.cause(),
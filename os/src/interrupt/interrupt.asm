@@ -3,7 +3,7 @@
 # 寄存器宽度对应的字节数
 .set    REG_SIZE, 8
 # Context 的大小
-.set    CONTEXT_SIZE, 34
+.set    CONTEXT_SIZE, 36
 
 # 宏：将寄存器存到栈上
 .macro SAVE reg, offset
@@ -29,14 +29,23 @@
 # 进入中断
 # 保存 Context 并且进入 Rust 中的中断处理函数 interrupt::handler::handle_interrupt()
 __interrupt:
+    # 从用户态进入时，sscratch 保存了当前线程的内核栈顶，需要切换到内核栈；
+    # 从内核态进入时，sscratch 为 0，继续使用当前的栈
+    csrrw   sp, sscratch, sp
+    bnez    sp, 1f
+    # 从内核态进入，恢复原来的 sp，此时 sscratch 中同样是原来的 sp
+    csrr    sp, sscratch
+1:
     # 在栈上开辟 Context 所需的空间
-    addi    sp, sp, -34*8
+    addi    sp, sp, -36*8
 
     # 保存通用寄存器，除了 x0（固定为 0）
     SAVE    x1, 1
     # 将原来的 sp（sp 又名 x2）写入 2 位置
-    addi    x1, sp, 34*8
+    csrr    x1, sscratch
     SAVE    x1, 2
+    # 已经进入内核态，将 sscratch 置 0
+    csrw    sscratch, x0
     # 保存 x3 至 x31
     .set    n, 3
     .rept   29
@@ -50,6 +59,12 @@ __interrupt:
     SAVE    s1, 32
     SAVE    s2, 33
 
+    # 从用户态进入时，tp 是用户程序的值，需要恢复内核的 tp（当前 hart 的编号）
+    andi    s3, s1, 0x100
+    bnez    s3, 2f
+    LOAD    tp, 34
+2:
+
     # 调用 handle_interrupt，传入参数
     # context: &mut Context
     mv      a0, sp
@@ -61,6 +76,7 @@ __interrupt:
 
     .globl __restore
 # 离开中断
+# 此时 sp 指向 Context（从 handle_interrupt 返回，或是由 __switch 切换至新线程）
 # 从 Context 中恢复所有寄存器，并跳转至 Context 中 sepc 的位置
 __restore:
     # 恢复 CSR
@@ -69,6 +85,20 @@ __restore:
     csrw    sstatus, s1
     csrw    sepc, s2
 
+    # 如果将返回用户态（SPP 为 0），则将内核栈顶写入 sscratch，供下次进入中断时使用，
+    # 并记录内核的 tp，下次从用户态进入中断时恢复
+    andi    s1, s1, 0x100
+    bnez    s1, 1f
+    addi    s1, sp, 36*8
+    csrw    sscratch, s1
+    SAVE    tp, 34
+    j       2f
+1:
+    # 返回内核态时 tp 必须是当前 hart 的编号。线程在中断中让出后可能在其他 hart 上恢复，
+    # 保存的 tp 已经过时，因此用当前的 tp 覆盖，下面恢复 x4 时保持不变
+    SAVE    tp, 4
+2:
+
     # 恢复通用寄存器
     LOAD    x1, 1
     # 恢复 x3 至 x31
@@ -0,0 +1,108 @@
+//! 处理器间中断（IPI）
+//!
+//! - [`send`] 向一组 hart 发送 IPI，例如唤醒空闲的 hart
+//! - [`call`] / [`call_others`] 在其他 hart 上执行函数，可以等待执行完成
+//! - [`remote_sfence_vma`] 刷新其他 hart 的 TLB
+//!
+//! IPI 通过 SBI 发送，到达后表现为 `SupervisorSoft` 中断，由 [`handle`] 执行队列中的函数
+
+use crate::hart::{self, hart_id, MAX_HARTS};
+use crate::sbi;
+use crate::sync::{without_interrupts, Lock};
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc, vec::Vec};
+use core::sync::atomic::{spin_loop_hint, AtomicUsize, Ordering};
+use lazy_static::*;
+use riscv::register::sie;
+
+/// 一次跨 hart 的函数调用
+struct Call {
+    /// 需要执行的函数
+    function: Box<dyn Fn() + Send + Sync>,
+    /// 尚未执行完的 hart 数量
+    remaining: AtomicUsize,
+}
+
+lazy_static! {
+    /// 每个 hart 等待执行的函数
+    static ref QUEUES: Vec<Lock<VecDeque<Arc<Call>>>> =
+        (0..MAX_HARTS).map(|_| Lock::new(VecDeque::new())).collect();
+}
+
+/// 开启当前 hart 的软件中断 `sie.SSIE`
+pub fn init_hart() {
+    unsafe { sie::set_ssoft() };
+}
+
+/// 向 `hart_mask` 中已经启动的 hart 发送 IPI
+pub fn send(hart_mask: usize) {
+    let hart_mask = hart_mask & hart::online_mask();
+    if hart_mask != 0 {
+        sbi::send_ipi(hart_mask);
+    }
+}
+
+/// 在 `hart_mask` 中已经启动的每个 hart 上执行一次 `function`
+///
+/// 包含当前 hart 时直接执行。`wait` 为真时等待所有 hart 执行完毕才返回，
+/// 等待期间会执行其他 hart 发来的调用，避免两个 hart 相互等待
+pub fn call(hart_mask: usize, function: impl Fn() + Send + Sync + 'static, wait: bool) {
+    without_interrupts(|| {
+        let hart_mask = hart_mask & hart::online_mask();
+        let current = 1 << hart_id();
+        let remote = hart_mask & !current;
+        let call = Arc::new(Call {
+            function: Box::new(function),
+            remaining: AtomicUsize::new(remote.count_ones() as usize),
+        });
+        for hart in (0..MAX_HARTS).filter(|hart| remote & (1 << hart) != 0) {
+            QUEUES[hart].lock().push_back(call.clone());
+        }
+        send(remote);
+        if hart_mask & current != 0 {
+            (call.function)();
+        }
+        if wait {
+            while call.remaining.load(Ordering::Acquire) != 0 {
+                handle_calls();
+                spin_loop_hint();
+            }
+        }
+    })
+}
+
+/// 在除当前 hart 以外的所有 hart 上执行 `function`
+pub fn call_others(function: impl Fn() + Send + Sync + 'static, wait: bool) {
+    without_interrupts(|| call(!(1 << hart_id()), function, wait))
+}
+
+/// 在 `hart_mask` 中的 hart 上刷新 `[start, start + size)` 的 TLB，`size` 为 0 表示全部
+///
+/// SBI 会等待远端的 hart 完成刷新才返回
+pub fn remote_sfence_vma(hart_mask: usize, start: usize, size: usize) {
+    let hart_mask = hart_mask & hart::online_mask();
+    if hart_mask != 0 {
+        sbi::remote_sfence_vma(hart_mask, start, size);
+    }
+}
+
+/// 处理 `SupervisorSoft` 中断
+///
+/// 先清除 `sip.SSIP`，之后到达的 IPI 会再次触发中断，不会遗漏
+pub fn handle() {
+    unsafe { llvm_asm!("csrc sip, $0" :: "r"(1 << 1) :: "volatile") };
+    handle_calls();
+}
+
+/// 执行当前 hart 队列中的所有调用，调用者需要关闭中断
+fn handle_calls() {
+    loop {
+        let call = QUEUES[hart_id()].lock().pop_front();
+        match call {
+            Some(call) => {
+                (call.function)();
+                call.remaining.fetch_sub(1, Ordering::Release);
+            }
+            None => break,
+        }
+    }
+}
@@ -1,14 +1,25 @@
 mod context;
 mod handler;
-mod timer;
+pub mod ipi;
+pub mod timer;
+
+pub use context::Context;
+pub use handler::{interrupt_counts, INTERRUPT_KINDS};
 
 /// 初始化中断相关的子模块
 ///
 /// - ['handler::init']
+/// - ['ipi::init_hart']
 /// - ['timer::init']
 
 pub fn init() {
+    init_hart();
+    info!("mod interrupt initialized");
+}
+
+/// 初始化当前 hart 的中断处理和时钟中断，每个 hart 都需要调用
+pub fn init_hart() {
     handler::init();
+    ipi::init_hart();
     timer::init();
-    println!("mod interrupt initialized");
 }
\ No newline at end of file
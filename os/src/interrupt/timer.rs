@@ -1,11 +1,85 @@
-//! 预约和处理时钟中断
+//! 时钟与定时器
+//!
+//! - [`now`] 提供单调递增的时间，由 `time` 寄存器和时基频率换算得到
+//! - [`add_oneshot`] / [`add_periodic`] 登记定时器回调，回调在时钟中断中执行
+//! - [`sleep`] 让当前线程睡眠一段时间
+//!
+//! 下一次时钟中断总是预约在最早到期的定时器处；有线程在执行时再加上调度的时间片，
+//! 处理器空闲时则只等待定时器，不再周期性地产生中断
+//!
+//! 每个 hart 有各自的定时器队列，定时器在登记它的 hart 上触发
 
+use crate::hart::{hart_id, MAX_HARTS};
+use crate::process::{
+    current_thread, try_current_thread, wake_thread, yield_current_thread, ThreadStatus,
+};
 use crate::sbi::set_timer;
-use riscv::register::{time, sie, sstatus};
+use crate::sync::{without_interrupts, Lock};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+use lazy_static::*;
+use riscv::register::{sie, sstatus, time};
 
-static INTERVAL: usize = 100_000;
-pub static mut TICKS: usize = 0;
+/// 默认的时基频率，即 QEMU virt 平台 `time` 寄存器每秒增加的数值
+const DEFAULT_TIMEBASE_FREQUENCY: usize = 10_000_000;
 
+/// 调度的时间片
+const TIME_SLICE: Duration = Duration::from_millis(10);
+
+/// 时基频率，可以由设备树中的 `timebase-frequency` 修改
+static TIMEBASE_FREQUENCY: AtomicUsize = AtomicUsize::new(DEFAULT_TIMEBASE_FREQUENCY);
+
+/// 时钟中断的次数
+static TICKS: AtomicUsize = AtomicUsize::new(0);
+
+/// 定时器的编号
+pub type TimerID = usize;
+
+/// 定时器编号计数器
+static TIMER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 一个定时器
+struct Timer {
+    /// 周期，为 `None` 表示只触发一次，单位为 `time` 寄存器的计数
+    period: Option<usize>,
+    /// 到期时执行的回调
+    callback: Box<dyn FnMut() + Send>,
+}
+
+/// 一个 hart 上的所有定时器
+struct Timers {
+    /// 按照（到期时间，编号）排序的定时器
+    queue: BTreeMap<(usize, TimerID), Timer>,
+    /// 已经预约的下一次时钟中断时间
+    next_deadline: usize,
+    /// 正在执行回调、暂时不在队列中的周期定时器，以及它是否已被取消
+    running: Option<(TimerID, bool)>,
+}
+
+lazy_static! {
+    /// 每个 hart 的定时器
+    static ref TIMERS: Vec<Lock<Timers>> = (0..MAX_HARTS)
+        .map(|_| {
+            Lock::new(Timers {
+                queue: BTreeMap::new(),
+                next_deadline: usize::MAX,
+                running: None,
+            })
+        })
+        .collect();
+}
+
+/// 当前 hart 的定时器
+///
+/// 调用者需要关闭中断，否则线程可能在读取 hart 编号之后迁移到其他 hart
+fn local_timers() -> &'static Lock<Timers> {
+    &TIMERS[hart_id()]
+}
+
+/// 初始化当前 hart 的时钟中断
 pub fn init() {
     unsafe {
         // 开启 STIE, 允许时钟中断
@@ -14,22 +88,182 @@ pub fn init() {
         sstatus::set_sie();
     }
     // 设置下一次时钟中断
-    set_next_timeout();
+    program(true);
 }
 
-fn set_next_timeout(){
-    set_timer(time::read() + INTERVAL);
+/// 设置时基频率
+pub fn set_timebase_frequency(frequency: usize) {
+    TIMEBASE_FREQUENCY.store(frequency, Ordering::Relaxed);
+}
+
+/// 时基频率
+pub fn timebase_frequency() -> usize {
+    TIMEBASE_FREQUENCY.load(Ordering::Relaxed)
+}
+
+/// 时钟中断的次数
+pub fn ticks() -> usize {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// 将时间换算为 `time` 寄存器的计数
+fn to_cycles(duration: Duration) -> usize {
+    (duration.as_nanos() * timebase_frequency() as u128 / 1_000_000_000) as usize
+}
+
+/// 启动以来的时间
+pub fn now() -> Duration {
+    Duration::from_nanos(now_ns())
+}
+
+/// 启动以来的纳秒数
+pub fn now_ns() -> u64 {
+    (time::read() as u128 * 1_000_000_000 / timebase_frequency() as u128) as u64
+}
+
+/// 启动以来的微秒数
+pub fn now_us() -> u64 {
+    (time::read() as u128 * 1_000_000 / timebase_frequency() as u128) as u64
+}
+
+/// 登记一个 `delay` 之后触发一次的定时器
+///
+/// 回调在时钟中断中执行，不能睡眠
+pub fn add_oneshot(delay: Duration, callback: impl FnMut() + Send + 'static) -> TimerID {
+    let callback = Box::new(callback);
+    without_interrupts(|| insert(&mut local_timers().lock(), to_cycles(delay), None, callback))
+}
+
+/// 登记一个每隔 `period` 触发一次的定时器
+///
+/// 回调在时钟中断中执行，不能睡眠
+pub fn add_periodic(period: Duration, callback: impl FnMut() + Send + 'static) -> TimerID {
+    let period = to_cycles(period).max(1);
+    let callback = Box::new(callback);
+    without_interrupts(|| insert(&mut local_timers().lock(), period, Some(period), callback))
+}
+
+/// 取消一个定时器，返回其是否仍在等待
+///
+/// 定时器可能登记在任意一个 hart 上，需要逐个查找。
+/// 周期定时器正在执行回调时（包括在自己的回调中取消）只做标记，回调结束后不再放回队列
+pub fn cancel(id: TimerID) -> bool {
+    for timers in TIMERS.iter() {
+        let mut timers = timers.lock();
+        let key = timers.queue.keys().find(|(_, timer)| *timer == id).copied();
+        if let Some(key) = key {
+            timers.queue.remove(&key);
+            return true;
+        }
+        if let Some((running, cancelled)) = &mut timers.running {
+            if *running == id && !*cancelled {
+                *cancelled = true;
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// 当前线程睡眠 `duration`
+pub fn sleep(duration: Duration) {
+    let thread = current_thread();
+    // 在登记定时器之前标记睡眠，并保持关中断，避免错过唤醒
+    without_interrupts(|| {
+        let mut timers = local_timers().lock();
+        thread.inner().status = ThreadStatus::Blocking;
+        let waiting = thread.clone();
+        insert(
+            &mut timers,
+            to_cycles(duration),
+            None,
+            Box::new(move || wake_thread(waiting.clone())),
+        );
+    });
+    yield_current_thread();
+}
+
+/// 登记一个 `delay` 个计数之后到期的定时器，必要时提前预约时钟中断
+///
+/// `timers` 应当是当前 hart 的定时器，预约的时钟中断只对当前 hart 有效
+fn insert(
+    timers: &mut Timers,
+    delay: usize,
+    period: Option<usize>,
+    callback: Box<dyn FnMut() + Send>,
+) -> TimerID {
+    let id = TIMER_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let deadline = time::read().saturating_add(delay);
+    timers.queue.insert((deadline, id), Timer { period, callback });
+    if deadline < timers.next_deadline {
+        timers.next_deadline = deadline;
+        set_timer(deadline);
+    }
+    id
+}
+
+/// 按照最早到期的定时器预约下一次时钟中断
+///
+/// `preempt` 为真时最迟在一个时间片之后中断，用于调度
+fn program(preempt: bool) {
+    let mut timers = local_timers().lock();
+    let mut deadline = timers
+        .queue
+        .keys()
+        .next()
+        .map_or(usize::MAX, |(deadline, _)| *deadline);
+    if preempt {
+        deadline = deadline.min(time::read() + to_cycles(TIME_SLICE));
+    }
+    timers.next_deadline = deadline;
+    set_timer(deadline);
+}
+
+/// 处理器空闲时调用，只为定时器预约时钟中断
+///
+/// 有线程加入调度队列时，空闲的 hart 由 IPI 唤醒
+pub fn enter_idle() {
+    program(false);
+}
+
+/// 处理器结束空闲时调用，重新开始计算时间片
+pub fn leave_idle() {
+    program(true);
 }
 
 /// 每一次时钟中断调用
 ///
-/// 设置下一次时钟中断，同时计数 + 1
+/// 执行所有到期的定时器，然后预约下一次时钟中断。
+/// 空闲的 hart 上没有需要抢占的线程，不预约时间片，保持 tickless
 pub fn tick() {
-    set_next_timeout();
-    unsafe {
-        TICKS += 1;
-        if TICKS % 100 == 0 {
-            println!("{} tick", TICKS);
+    TICKS.fetch_add(1, Ordering::Relaxed);
+    loop {
+        // 执行回调时不持有锁，回调中可以再登记定时器
+        let ((deadline, id), mut timer) = {
+            let mut timers = local_timers().lock();
+            let key = match timers.queue.keys().next() {
+                Some(&key) if key.0 <= time::read() => key,
+                _ => break,
+            };
+            let timer = timers.queue.remove(&key).unwrap();
+            if timer.period.is_some() {
+                timers.running = Some((key.1, false));
+            }
+            (key, timer)
+        };
+        (timer.callback)();
+        // 周期定时器保留原来的编号，错过的周期不再补偿，下一次在当前时间之后到期
+        if let Some(period) = timer.period {
+            let mut timers = local_timers().lock();
+            if let Some((_, false)) = timers.running.take() {
+                let now = time::read();
+                let mut deadline = deadline + period;
+                if deadline <= now {
+                    deadline = now + period - (now - deadline) % period;
+                }
+                timers.queue.insert((deadline, id), timer);
+            }
         }
     }
-}
\ No newline at end of file
+    program(try_current_thread().is_some());
+}
@@ -0,0 +1,42 @@
+//! 错误码，数值与 Linux 保持一致
+//!
+//! 系统调用出错时返回错误码的相反数，例如 `-EFAULT`
+
+/// 文件或目录不存在
+pub const ENOENT: isize = 2;
+/// 输入输出错误
+pub const EIO: isize = 5;
+/// 不是合法的可执行文件
+pub const ENOEXEC: isize = 8;
+/// 错误的文件描述符
+pub const EBADF: isize = 9;
+/// 没有可以等待的子进程
+pub const ECHILD: isize = 10;
+/// 内存不足
+pub const ENOMEM: isize = 12;
+/// 没有访问权限
+pub const EACCES: isize = 13;
+/// 错误的地址
+pub const EFAULT: isize = 14;
+/// 设备或资源正在使用
+pub const EBUSY: isize = 16;
+/// 文件已经存在
+pub const EEXIST: isize = 17;
+/// 不是目录
+pub const ENOTDIR: isize = 20;
+/// 是目录
+pub const EISDIR: isize = 21;
+/// 参数错误
+pub const EINVAL: isize = 22;
+/// 打开的文件过多
+pub const EMFILE: isize = 24;
+/// 设备上没有剩余空间
+pub const ENOSPC: isize = 28;
+/// 不支持移动读写位置
+pub const ESPIPE: isize = 29;
+/// 文件名过长
+pub const ENAMETOOLONG: isize = 36;
+/// 未实现的系统调用
+pub const ENOSYS: isize = 38;
+/// 目录不为空
+pub const ENOTEMPTY: isize = 39;
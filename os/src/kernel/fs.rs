@@ -0,0 +1,127 @@
+//! 文件相关的系统调用
+
+use super::*;
+use crate::fs::{open, File, OpenFlags, SeekFrom, Stat};
+use crate::memory::user::{copy_from_user, copy_to_user};
+use alloc::{sync::Arc, vec};
+use core::cmp::min;
+use core::mem::size_of;
+
+/// 每次读写在内核中使用的缓冲区大小
+const IO_BUFFER_SIZE: usize = 0x1000;
+
+/// `openat` 中表示从当前工作目录开始解析的 `dirfd`
+const AT_FDCWD: isize = -100;
+
+/// `lseek` 的 `whence`
+const SEEK_SET: usize = 0;
+const SEEK_CUR: usize = 1;
+const SEEK_END: usize = 2;
+
+/// 取得当前进程中文件描述符对应的文件
+fn get_file(fd: usize) -> Result<Arc<dyn File>, isize> {
+    current_process()
+        .inner()
+        .descriptors
+        .get(fd)
+        .cloned()
+        .flatten()
+        .ok_or(EBADF)
+}
+
+/// 从文件中读取至多 `len` 字节到用户缓冲区 `buffer`
+///
+/// 每次至多读取 [`IO_BUFFER_SIZE`] 字节
+pub(super) fn sys_read(fd: usize, buffer: usize, len: usize) -> Result<isize, isize> {
+    let file = get_file(fd)?;
+    let mut data = vec![0u8; min(len, IO_BUFFER_SIZE)];
+    // 读取时可能会睡眠，此时不能持有进程的锁
+    let size = file.read(&mut data)?;
+    let process = current_process();
+    copy_to_user(&process.inner().memory_set, VirtualAddress(buffer), &data[..size])?;
+    Ok(size as isize)
+}
+
+/// 将用户缓冲区 `buffer` 中的 `len` 字节写入文件
+pub(super) fn sys_write(fd: usize, buffer: usize, len: usize) -> Result<isize, isize> {
+    let file = get_file(fd)?;
+    let process = current_process();
+    let mut data = vec![0u8; min(len, IO_BUFFER_SIZE)];
+    let mut written = 0;
+    while written < len {
+        let chunk = &mut data[..min(len - written, IO_BUFFER_SIZE)];
+        copy_from_user(
+            &process.inner().memory_set,
+            chunk,
+            VirtualAddress(buffer + written),
+        )?;
+        let size = file.write(chunk)?;
+        written += size;
+        if size < chunk.len() {
+            break;
+        }
+    }
+    Ok(written as isize)
+}
+
+/// 打开路径 `path` 对应的文件，返回文件描述符
+///
+/// 目前没有工作目录，`dirfd` 只支持 `AT_FDCWD`，相对路径从根目录开始解析。
+/// 不支持权限，忽略 `mode`；与 Linux 相同，忽略不认识的标志，例如 `O_CLOEXEC`
+pub(super) fn sys_openat(dirfd: isize, path: usize, flags: u32) -> Result<isize, isize> {
+    if dirfd != AT_FDCWD {
+        return Err(EINVAL);
+    }
+    let flags = OpenFlags::from_bits_truncate(flags);
+    let process = current_process();
+    let path = read_string(&process.inner().memory_set, path)?;
+    let path = core::str::from_utf8(&path).map_err(|_| EINVAL)?;
+    // 打开时可能访问存储设备而睡眠，此时不能持有进程的锁
+    let file = open(path, flags)?;
+    let fd = process.inner().alloc_descriptor(file)?;
+    Ok(fd as isize)
+}
+
+/// 关闭文件描述符
+pub(super) fn sys_close(fd: usize) -> Result<isize, isize> {
+    let process = current_process();
+    let file = process
+        .inner()
+        .descriptors
+        .get_mut(fd)
+        .and_then(Option::take)
+        .ok_or(EBADF)?;
+    // 文件可能在这里被释放并写回，此时不能持有进程的锁
+    drop(file);
+    Ok(0)
+}
+
+/// 移动文件的读写位置，返回新的位置
+pub(super) fn sys_lseek(fd: usize, offset: isize, whence: usize) -> Result<isize, isize> {
+    let position = match whence {
+        SEEK_SET if offset >= 0 => SeekFrom::Start(offset as usize),
+        SEEK_CUR => SeekFrom::Current(offset),
+        SEEK_END => SeekFrom::End(offset),
+        _ => return Err(EINVAL),
+    };
+    Ok(get_file(fd)?.seek(position)? as isize)
+}
+
+/// 复制文件描述符，新的文件描述符与原来的共享读写位置
+pub(super) fn sys_dup(fd: usize) -> Result<isize, isize> {
+    let file = get_file(fd)?;
+    let fd = current_process().inner().alloc_descriptor(file)?;
+    Ok(fd as isize)
+}
+
+/// 将文件的信息写入用户的 `struct stat`
+pub(super) fn sys_fstat(fd: usize, stat: usize) -> Result<isize, isize> {
+    let metadata = get_file(fd)?.metadata()?;
+    let stat_data = Stat::from(metadata);
+    let bytes = unsafe {
+        core::slice::from_raw_parts(&stat_data as *const Stat as *const u8, size_of::<Stat>())
+    };
+    let process = current_process();
+    copy_to_user(&process.inner().memory_set, VirtualAddress(stat), bytes)?;
+    Ok(0)
+}
@@ -0,0 +1,18 @@
+//! 为进程提供系统调用等内核功能
+
+pub mod errno;
+mod fs;
+mod process;
+mod syscall;
+mod syslog;
+
+use crate::interrupt::*;
+use crate::memory::address::VirtualAddress;
+use crate::process::*;
+use errno::*;
+use fs::*;
+use process::*;
+use syslog::*;
+
+pub use process::exit_current_process;
+pub use syscall::syscall_handler;
@@ -0,0 +1,95 @@
+//! 进程相关的系统调用
+
+use super::*;
+use crate::memory::mapping::MemorySet;
+use crate::memory::user::{copy_from_user, strncpy_from_user};
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+/// 路径或参数字符串的最大长度
+const MAX_STRING_LENGTH: usize = 256;
+
+/// 参数的最大个数
+const MAX_ARGUMENTS: usize = 32;
+
+/// 结束当前进程，不再返回
+pub fn exit_current_process(exit_code: i32) -> ! {
+    let process = current_process();
+    process.exit(exit_code);
+    // 进程的地址空间在被回收时释放，切换到内核的地址空间以免其页表被提前释放
+    KERNEL_PROCESS.inner().memory_set.activate();
+    drop(process);
+    exit_current_thread()
+}
+
+pub(super) fn sys_exit(exit_code: i32) -> Result<isize, isize> {
+    exit_current_process(exit_code)
+}
+
+pub(super) fn sys_yield() -> Result<isize, isize> {
+    yield_current_thread();
+    Ok(0)
+}
+
+pub(super) fn sys_getpid() -> Result<isize, isize> {
+    Ok(current_process().pid as isize)
+}
+
+/// 复制当前进程，父进程得到子进程的 pid，子进程得到 0
+pub(super) fn sys_fork(context: &Context) -> Result<isize, isize> {
+    let child = current_process().fork(context)?;
+    Ok(child.pid as isize)
+}
+
+/// 执行程序 `path`，`argv` 为以 0 结尾的参数指针数组（可以为 0）
+///
+/// 成功时不会回到原来的程序，而是从新程序的入口开始执行，此时 `a0` 为参数个数
+pub(super) fn sys_exec(path: usize, argv: usize, context: &mut Context) -> Result<isize, isize> {
+    let process = current_process();
+    let (name, arguments) = {
+        let inner = process.inner();
+        let name = read_string(&inner.memory_set, path)?;
+        let mut arguments = Vec::new();
+        if argv != 0 {
+            loop {
+                if arguments.len() == MAX_ARGUMENTS {
+                    return Err(EINVAL);
+                }
+                let mut pointer = [0u8; size_of::<usize>()];
+                let address = argv + arguments.len() * size_of::<usize>();
+                copy_from_user(
+                    &inner.memory_set,
+                    &mut pointer,
+                    VirtualAddress(address),
+                )?;
+                match usize::from_ne_bytes(pointer) {
+                    0 => break,
+                    pointer => arguments.push(read_string(&inner.memory_set, pointer)?),
+                }
+            }
+        }
+        (name, arguments)
+    };
+    let name = core::str::from_utf8(&name).map_err(|_| EINVAL)?;
+    let elf_data = loader::load(name)?;
+    process.exec(&elf_data, &arguments, context)?;
+    Ok(arguments.len() as isize)
+}
+
+/// 等待子进程 `pid` 退出，`pid` 为 -1 时等待任意子进程
+///
+/// 返回子进程的 pid，退出码写入 `exit_code`
+pub(super) fn sys_waitpid(pid: isize, exit_code: usize) -> Result<isize, isize> {
+    let child = current_process().wait(pid, VirtualAddress(exit_code))?;
+    Ok(child as isize)
+}
+
+/// 从用户地址读取以 `'\0'` 结尾的字符串
+pub(super) fn read_string(memory_set: &MemorySet, address: usize) -> Result<Vec<u8>, isize> {
+    let mut buffer = [0u8; MAX_STRING_LENGTH];
+    let len = strncpy_from_user(memory_set, &mut buffer, VirtualAddress(address))?;
+    if len == MAX_STRING_LENGTH {
+        return Err(EINVAL);
+    }
+    Ok(buffer[..len].to_vec())
+}
@@ -0,0 +1,56 @@
+//! 实现各种系统调用
+
+use super::*;
+
+pub const SYS_DUP: usize = 23;
+pub const SYS_OPENAT: usize = 56;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_LSEEK: usize = 62;
+pub const SYS_READ: usize = 63;
+pub const SYS_WRITE: usize = 64;
+pub const SYS_FSTAT: usize = 80;
+pub const SYS_EXIT: usize = 93;
+pub const SYS_SYSLOG: usize = 116;
+pub const SYS_YIELD: usize = 124;
+pub const SYS_GETPID: usize = 172;
+pub const SYS_FORK: usize = 220;
+pub const SYS_EXEC: usize = 221;
+pub const SYS_WAITPID: usize = 260;
+
+/// 系统调用的总入口
+///
+/// 系统调用号在 `a7` 中，参数依次在 `a0` 至 `a2` 中，返回值写入 `a0`。
+/// 出错时返回错误码的相反数
+pub fn syscall_handler(context: &mut Context) {
+    // 无论如何处理，一定会跳过当前的 ecall 指令
+    context.sepc += 4;
+
+    let syscall_id = context.x[17];
+    let args = [context.x[10], context.x[11], context.x[12]];
+
+    let result = match syscall_id {
+        SYS_DUP => sys_dup(args[0]),
+        SYS_OPENAT => sys_openat(args[0] as isize, args[1], args[2] as u32),
+        SYS_CLOSE => sys_close(args[0]),
+        SYS_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
+        SYS_READ => sys_read(args[0], args[1], args[2]),
+        SYS_WRITE => sys_write(args[0], args[1], args[2]),
+        SYS_FSTAT => sys_fstat(args[0], args[1]),
+        SYS_EXIT => sys_exit(args[0] as i32),
+        SYS_SYSLOG => sys_syslog(args[0], args[1], args[2]),
+        SYS_YIELD => sys_yield(),
+        SYS_GETPID => sys_getpid(),
+        SYS_FORK => sys_fork(context),
+        SYS_EXEC => sys_exec(args[0], args[1], context),
+        SYS_WAITPID => sys_waitpid(args[0] as isize, args[1]),
+        _ => {
+            warn!("unimplemented syscall: {}", syscall_id);
+            Err(ENOSYS)
+        }
+    };
+
+    context.x[10] = match result {
+        Ok(value) => value as usize,
+        Err(errno) => (-errno) as usize,
+    };
+}
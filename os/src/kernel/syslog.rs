@@ -0,0 +1,29 @@
+//! 读取内核日志的系统调用
+
+use super::*;
+use crate::logger::dmesg;
+use crate::memory::user::copy_to_user;
+use alloc::vec;
+
+/// 读取缓冲区中全部（最新的）日志
+const SYSLOG_ACTION_READ_ALL: usize = 3;
+/// 缓冲区大小
+const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// 与 Linux 的 `syslog` 相同，目前支持读取全部日志和获取缓冲区大小
+///
+/// 读取时如果 `len` 小于日志长度，则读取最新的 `len` 字节，返回读取的字节数
+pub(super) fn sys_syslog(action: usize, buffer: usize, len: usize) -> Result<isize, isize> {
+    match action {
+        SYSLOG_ACTION_READ_ALL => {
+            let (start, end) = dmesg::range();
+            let mut data = vec![0u8; len.min(end - start)];
+            let (_, size) = dmesg::read(end - data.len(), &mut data);
+            let process = current_process();
+            copy_to_user(&process.inner().memory_set, VirtualAddress(buffer), &data[..size])?;
+            Ok(size as isize)
+        }
+        SYSLOG_ACTION_SIZE_BUFFER => Ok(dmesg::DMESG_SIZE as isize),
+        _ => Err(EINVAL),
+    }
+}
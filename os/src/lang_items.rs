@@ -1,13 +1,33 @@
 use core::panic::PanicInfo;
-use crate::sbi::shutdown;
+use crate::sbi::shutdown_failure;
 
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
-    println!("\x1b[1;31mpanic: '{}'\x1b[0m", info.message().unwrap());
-    shutdown()
+    // panic 可能发生在持有控制台锁时，不能再等待其释放
+    unsafe { crate::console::force_unlock() };
+    match info.location() {
+        Some(location) => print!(
+            "\x1b[1;31mpanic at {}:{}:{}: ",
+            location.file(),
+            location.line(),
+            location.column()
+        ),
+        None => print!("\x1b[1;31mpanic: "),
+    }
+    match info.message() {
+        Some(message) => println!("'{}'\x1b[0m", message),
+        None => println!("(no message)\x1b[0m"),
+    }
+    crate::backtrace::backtrace();
+    // 缓冲区中的日志都已经输出过，只在需要时重新输出，放在最后以免淹没 panic 信息
+    if crate::logger::dmesg::dump_on_panic() {
+        crate::logger::dmesg::dump();
+    }
+    // panic 时总是以非 0 退出，测试中即为测试失败
+    shutdown_failure(1)
 }
 
 #[no_mangle]
 extern "C" fn abort() -> ! {
     panic!("abort()")
-}
\ No newline at end of file
+}
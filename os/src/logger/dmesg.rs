@@ -0,0 +1,95 @@
+//! 内核日志的环形缓冲区
+//!
+//! 所有输出的日志都会以不带颜色的文本追加到缓冲区中，缓冲区满时覆盖最早的内容。
+//! 读取时使用从启动开始累计的字节序号作为位置，可以分多次读取，也能知道是否有内容被覆盖。
+//!
+//! 缓冲区中的日志都已经输出到控制台，因此只有启动参数中给出 `dmesg_on_panic` 时才在 panic 时重新输出
+
+use crate::sync::Lock;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// 缓冲区大小
+pub const DMESG_SIZE: usize = 0x1_0000;
+
+/// 环形缓冲区
+struct Dmesg {
+    data: [u8; DMESG_SIZE],
+    /// 累计写入的字节数，下一个字节写入 `data[end % DMESG_SIZE]`
+    end: usize,
+}
+
+impl Dmesg {
+    /// 缓冲区中最早的字节的序号
+    fn start(&self) -> usize {
+        self.end.saturating_sub(DMESG_SIZE)
+    }
+}
+
+impl Write for Dmesg {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &byte in s.as_bytes() {
+            self.data[self.end % DMESG_SIZE] = byte;
+            self.end += 1;
+        }
+        Ok(())
+    }
+}
+
+static DMESG: Lock<Dmesg> = Lock::new(Dmesg {
+    data: [0; DMESG_SIZE],
+    end: 0,
+});
+
+/// panic 时是否输出缓冲区的内容
+static DUMP_ON_PANIC: AtomicBool = AtomicBool::new(false);
+
+/// 设置 panic 时是否输出缓冲区的内容
+pub fn set_dump_on_panic(enabled: bool) {
+    DUMP_ON_PANIC.store(enabled, Ordering::Relaxed);
+}
+
+/// panic 时是否输出缓冲区的内容
+pub fn dump_on_panic() -> bool {
+    DUMP_ON_PANIC.load(Ordering::Relaxed)
+}
+
+/// 追加一段格式化的内容
+pub fn append(args: fmt::Arguments) {
+    DMESG.lock().write_fmt(args).unwrap();
+}
+
+/// 缓冲区中现有内容的序号范围
+pub fn range() -> (usize, usize) {
+    let dmesg = DMESG.lock();
+    (dmesg.start(), dmesg.end)
+}
+
+/// 从序号 `position` 开始读取到 `buffer`，返回实际开始的序号和读取的字节数
+///
+/// 如果 `position` 处的内容已经被覆盖，则从现存最早的内容开始读取
+pub fn read(position: usize, buffer: &mut [u8]) -> (usize, usize) {
+    let dmesg = DMESG.lock();
+    let start = position.max(dmesg.start()).min(dmesg.end);
+    let len = buffer.len().min(dmesg.end - start);
+    for (i, byte) in buffer[..len].iter_mut().enumerate() {
+        *byte = dmesg.data[(start + i) % DMESG_SIZE];
+    }
+    (start, len)
+}
+
+/// 将缓冲区中的全部内容输出到控制台，用于 panic 时
+///
+/// 不再等待缓冲区的锁
+pub fn dump() {
+    unsafe { DMESG.force_unlock() };
+    let (mut position, end) = range();
+    let mut buffer = [0u8; 256];
+    println!("---- dmesg ----");
+    while position < end {
+        let (start, len) = read(position, &mut buffer);
+        crate::console::write_bytes(&buffer[..len]);
+        position = start + len;
+    }
+    println!("---- end of dmesg ----");
+}
@@ -0,0 +1,151 @@
+//! 基于 [`log`] crate 的内核日志
+//!
+//! 每条日志带有 hart 编号、时钟中断次数和模块路径，并按照级别着色。
+//!
+//! # 过滤规则
+//!
+//! 规则形如 `info,memory=warn,process::processor=trace`：
+//! - 单独的级别作为默认级别
+//! - `模块=级别` 对该模块及其子模块生效，模块路径可以省略开头的 `os::`，匹配最长的规则
+//!
+//! 编译时通过环境变量 `LOG` 指定（默认为 `info`），运行时可以通过 [`set_filter`] 修改
+//!
+//! 通过过滤的日志同时会记录在 [`dmesg`] 缓冲区中
+
+pub mod dmesg;
+
+use crate::hart::hart_id;
+use crate::interrupt::timer;
+use crate::sync::Lock;
+use alloc::{string::String, vec::Vec};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// 编译时指定的过滤规则
+const DEFAULT_FILTER: Option<&str> = option_env!("LOG");
+
+/// 当前的过滤规则
+struct Filter {
+    /// 默认级别
+    default: LevelFilter,
+    /// 各个模块的级别，模块路径不含 `os::`
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// 模块适用的级别
+    fn level(&self, module: &str) -> LevelFilter {
+        let module = without_crate_name(module);
+        self.modules
+            .iter()
+            .filter(|(prefix, _)| {
+                module == prefix.as_str()
+                    || (module.starts_with(prefix.as_str())
+                        && module[prefix.len()..].starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// 所有规则中最详细的级别
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, LevelFilter::max)
+    }
+}
+
+static FILTER: Lock<Filter> = Lock::new(Filter {
+    default: LevelFilter::Info,
+    modules: Vec::new(),
+});
+
+struct KernelLogger;
+
+static LOGGER: KernelLogger = KernelLogger;
+
+impl Log for KernelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= FILTER.lock().level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let module = record.module_path().unwrap_or_else(|| record.target());
+        let (hart, ticks) = (hart_id(), timer::ticks());
+        println!(
+            "\x1b[{}m[{:>5}][hart {}][{:>6}][{}] {}\x1b[0m",
+            color(record.level()),
+            record.level(),
+            hart,
+            ticks,
+            module,
+            record.args()
+        );
+        dmesg::append(format_args!(
+            "[{:>5}][hart {}][{:>6}][{}] {}\n",
+            record.level(),
+            hart,
+            ticks,
+            module,
+            record.args()
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+/// 各个级别的 ANSI 颜色
+fn color(level: Level) -> u8 {
+    match level {
+        Level::Error => 31,
+        Level::Warn => 93,
+        Level::Info => 34,
+        Level::Debug => 32,
+        Level::Trace => 90,
+    }
+}
+
+/// 去掉模块路径开头的 `os::`
+fn without_crate_name(module: &str) -> &str {
+    if module.starts_with("os::") {
+        &module[4..]
+    } else {
+        module
+    }
+}
+
+/// 初始化日志，使用编译时指定的过滤规则
+pub fn init() {
+    log::set_logger(&LOGGER).unwrap();
+    set_filter(DEFAULT_FILTER.unwrap_or("info"));
+}
+
+/// 按照规则设置过滤级别，无法解析的部分会被忽略
+pub fn set_filter(spec: &str) {
+    let mut filter = Filter {
+        default: LevelFilter::Info,
+        modules: Vec::new(),
+    };
+    for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let mut parts = item.splitn(2, '=');
+        let first = parts.next().unwrap();
+        match parts.next() {
+            Some(level) => {
+                if let Ok(level) = level.trim().parse() {
+                    let module = without_crate_name(first.trim());
+                    filter.modules.push((String::from(module), level));
+                }
+            }
+            None => {
+                if let Ok(level) = first.parse() {
+                    filter.default = level;
+                }
+            }
+        }
+    }
+    log::set_max_level(filter.max_level());
+    *FILTER.lock() = filter;
+}
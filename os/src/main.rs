@@ -30,29 +30,73 @@
 //! - `#![feature(slice_fill)]`
 //!   允许将 slice 填充值
 #![feature(slice_fill)]
+//!
+//! - `#![feature(custom_test_frameworks)]`
+//!   使用自定义的测试框架，在 QEMU 中运行以 `#[test_case]` 标记的内核测试
+#![feature(custom_test_frameworks)]
+#![test_runner(crate::test::runner)]
+#![reexport_test_harness_main = "test_main"]
 
 #[macro_use]
 mod console;
+mod logger;
 mod lang_items;
+mod hart;
+mod backtrace;
 mod sbi;
 mod interrupt;
 mod memory;
+mod drivers;
+mod kernel;
+mod fs;
+mod process;
+mod sync;
+#[cfg(test)]
 mod test;
 
 extern crate alloc;
+#[macro_use]
+extern crate log;
 // 汇编编写的程序入口，具体见该文件
 global_asm!(include_str!("entry.asm"));
 
 /// Rust 的入口函数
 ///
-/// 在 `_start` 为我们进行了一系列准备之后，这是第一个被调用的 Rust 函数
+/// 在 `_start` 为我们进行了一系列准备之后，这是第一个被调用的 Rust 函数。
+/// 参数为 OpenSBI 传入的启动 hart 编号和设备树地址
 #[no_mangle]
-pub extern "C" fn rust_main() {
+pub extern "C" fn rust_main(_hart_id: usize, device_tree: usize) -> ! {
     // 初始化各种模块
     println!("Hello rCore-Tutorial");
-    interrupt::init();
+    // 日志的过滤规则需要使用堆，因此在内存模块之后初始化
     memory::init();
+    logger::init();
+    // 设备树给出内存范围和时基频率，需要在分配物理页和设置时钟中断之前解析
+    drivers::devicetree::init(memory::address::PhysicalAddress(device_tree));
+    sbi::init();
+    interrupt::init();
+    drivers::init();
 
-    test::physical_memory_test();
-    panic!()
+    // 测试时不启动用户进程，而是在内核线程中运行所有测试
+    #[cfg(test)]
+    test::start();
+    #[cfg(not(test))]
+    process::init();
+
+    hart::mark_online();
+    hart::start_secondary_harts();
+    process::run()
+}
+
+/// 其他 hart 的 Rust 入口函数
+///
+/// 由 SBI HSM 扩展启动，经过 `_secondary_start` 之后调用。
+/// 全局的初始化已经由启动 hart 完成，这里只初始化 hart 局部的状态
+#[no_mangle]
+pub extern "C" fn rust_main_secondary(hart_id: usize) -> ! {
+    interrupt::init_hart();
+    drivers::init_hart();
+    hart::mark_online();
+    info!("hart {} started", hart_id);
+    process::run()
 }
\ No newline at end of file
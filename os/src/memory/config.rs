@@ -1,5 +1,6 @@
 use lazy_static::*;
 use crate::memory::address::{PhysicalAddress, VirtualAddress};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// 操作系统动态分配内存所作用的堆大小(8M)
 pub const KERNEL_HEAP_SIZE: usize = 0x80_0000;
@@ -10,8 +11,24 @@ pub const PAGE_SIZE: usize = 0x1_000;
 /// 可以访问的内存区域起始地址
 pub const MEMORY_START_ADDRESS: PhysicalAddress = PhysicalAddress(0x8000_0000);
 
+/// 设备树中没有给出内存范围时，默认的内存区域结束地址
+const DEFAULT_MEMORY_END_ADDRESS: usize = 0x8800_0000;
+
+/// 启动页表只线性映射了 `[0x8000_0000, 0xc000_0000)` 的内存，可用内存不能超过这里
+pub const MEMORY_MAP_LIMIT: PhysicalAddress = PhysicalAddress(0xc000_0000);
+
+/// 可以访问的内存区域结束地址，可以由设备树修改
+static MEMORY_END_ADDRESS: AtomicUsize = AtomicUsize::new(DEFAULT_MEMORY_END_ADDRESS);
+
 /// 可以访问的内存区域结束地址
-pub const MEMORY_END_ADDRESS: PhysicalAddress = PhysicalAddress(0x8800_0000);
+pub fn memory_end() -> PhysicalAddress {
+    PhysicalAddress(MEMORY_END_ADDRESS.load(Ordering::Relaxed))
+}
+
+/// 设置内存区域结束地址，必须在第一次分配物理页之前调用
+pub fn set_memory_end(end: PhysicalAddress) {
+    MEMORY_END_ADDRESS.store(end.0.min(MEMORY_MAP_LIMIT.0), Ordering::Relaxed);
+}
 
 lazy_static! {
  pub static ref KERNEL_END_ADDRESS: VirtualAddress = VirtualAddress(kernel_end as usize);
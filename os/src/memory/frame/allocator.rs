@@ -1,43 +1,121 @@
 use lazy_static::*;
 use crate::memory::address::{PhysicalPageNumber, PhysicalAddress};
-use crate::memory::config:: {MEMORY_END_ADDRESS, MEMORY_START_ADDRESS, KERNEL_END_ADDRESS};
+use crate::memory::config::{memory_end, KERNEL_END_ADDRESS};
 use algorithm::{Allocator, AllocatorImpl};
-use crate::memory::frame::frame_tracker::FrameTracker;
-use spin::Mutex;
+use crate::sync::Lock;
 use crate::memory::range::Range;
 use crate::memory::MemoryResult;
+use super::frame_tracker::FrameTracker;
+use alloc::vec::Vec;
 
 lazy_static! {
-    /// 帧分配
-    pub static ref FRAME_ALLOCATOR: Mutex<FrameAllocator<AllocatorImpl>> = Mutex::new(FrameAllocator::new(Range::from(
-        PhysicalPageNumber::ceil(PhysicalAddress::from(*KERNEL_END_ADDRESS))..PhysicalPageNumber::floor(MEMORY_END_ADDRESS),
+    /// 全局的帧分配，各个 hart 通过 [`cache`](super::cache) 批量地从这里取出和归还
+    pub static ref FRAME_ALLOCATOR: Lock<FrameAllocator<AllocatorImpl>> = Lock::new(FrameAllocator::new(Range::from(
+        PhysicalPageNumber::ceil(PhysicalAddress::from(*KERNEL_END_ADDRESS))..PhysicalPageNumber::floor(memory_end()),
     )));
 }
 
+/// 保留一段物理内存（例如 initrd），其中的帧不会被分配出去
+///
+/// 只能在第一次分配物理页之前调用
+pub fn reserve(start: PhysicalAddress, end: PhysicalAddress) {
+    FRAME_ALLOCATOR
+        .lock()
+        .reserve(Range::from(PhysicalPageNumber::floor(start)..PhysicalPageNumber::ceil(end)));
+}
+
+/// 分配物理地址连续的 `count` 个物理页，例如供设备 DMA 使用
+///
+/// 直接从全局分配器中分配，不经过各个 hart 的缓存
+pub fn alloc_contiguous(count: usize) -> MemoryResult<Vec<FrameTracker>> {
+    let start = FRAME_ALLOCATOR.lock().alloc_contiguous(count)?;
+    Ok((0..count).map(|i| FrameTracker(start + i)).collect())
+}
+
+/// 归还由 [`reserve`] 保留的物理内存
+pub fn release(start: PhysicalAddress, end: PhysicalAddress) {
+    FRAME_ALLOCATOR
+        .lock()
+        .release(Range::from(PhysicalPageNumber::floor(start)..PhysicalPageNumber::ceil(end)));
+}
+
 /// 基于线段树的帧分配 / 回收
 pub struct FrameAllocator<T: Allocator> {
     /// 可用区间的起始
     start_ppn: PhysicalPageNumber,
+    /// 可用的帧总数
+    total: usize,
+    /// 尚未分配的帧数
+    free: usize,
     /// 分配器
     allocator: T,
 }
 
 impl<T: Allocator> FrameAllocator<T> {
     pub fn new(range: impl Into<Range<PhysicalPageNumber>> + Copy) -> Self {
+        let total = range.into().len();
         FrameAllocator {
             start_ppn: range.into().start,
-            allocator: T::new(range.into().len()),
+            total,
+            free: total,
+            allocator: T::new(total),
         }
     }
 
-    pub fn alloc(&mut self) -> MemoryResult<FrameTracker> {
-        self.allocator
+    pub fn alloc(&mut self) -> MemoryResult<PhysicalPageNumber> {
+        let offset = self
+            .allocator
             .alloc()
-            .ok_or("no available frame to allocate")
-            .map(|offset| FrameTracker(self.start_ppn + offset))
+            .ok_or("no available frame to allocate")?;
+        self.free -= 1;
+        Ok(self.start_ppn + offset)
+    }
+
+    pub fn alloc_contiguous(&mut self, count: usize) -> MemoryResult<PhysicalPageNumber> {
+        let offset = self
+            .allocator
+            .alloc_contiguous(count)
+            .ok_or("no contiguous frames to allocate")?;
+        self.free -= count;
+        Ok(self.start_ppn + offset)
+    }
+
+    pub fn dealloc(&mut self, ppn: PhysicalPageNumber) {
+        self.allocator.dealloc(ppn - self.start_ppn);
+        self.free += 1;
     }
 
-    pub(super) fn dealloc(&mut self, frame: &FrameTracker) {
-        self.allocator.dealloc(frame.page_number() - self.start_ppn);
+    /// `range` 中由分配器管理的部分，表示为分配器中的下标
+    fn offsets(&self, range: Range<PhysicalPageNumber>) -> core::ops::Range<usize> {
+        let base = usize::from(self.start_ppn);
+        let start = (usize::from(range.start).max(base) - base).min(self.total);
+        let end = (usize::from(range.end).max(base) - base).min(self.total);
+        start..end.max(start)
     }
-}
\ No newline at end of file
+
+    /// 保留 `range` 中的帧，只能在第一次分配之前调用
+    pub fn reserve(&mut self, range: Range<PhysicalPageNumber>) {
+        let offsets = self.offsets(range);
+        self.allocator.reserve(offsets.start, offsets.end);
+        self.free -= offsets.len();
+    }
+
+    /// 归还由 [`reserve`](FrameAllocator::reserve) 保留的帧
+    pub fn release(&mut self, range: Range<PhysicalPageNumber>) {
+        let offsets = self.offsets(range);
+        self.free += offsets.len();
+        for offset in offsets {
+            self.allocator.dealloc(offset);
+        }
+    }
+
+    /// 可用的帧总数
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// 尚未分配的帧数，不包括各个 hart 缓存的帧
+    pub fn free(&self) -> usize {
+        self.free
+    }
+}
@@ -0,0 +1,140 @@
+//! 每个 hart 的空闲帧缓存
+//!
+//! 分配和回收先在当前 hart 的缓存中进行，不需要争用全局的 [`FRAME_ALLOCATOR`]。
+//! 缓存为空时从全局分配器批量取出 [`BATCH_SIZE`] 个帧，缓存满时批量归还
+
+use super::allocator::FRAME_ALLOCATOR;
+use super::frame_tracker::FrameTracker;
+use crate::hart::{hart_id, MAX_HARTS};
+use crate::memory::address::PhysicalPageNumber;
+use crate::memory::stats::{CacheCounters, CacheStats};
+use crate::memory::MemoryResult;
+use crate::sync::{without_interrupts, Lock};
+use alloc::{vec, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+
+/// 每个 hart 最多缓存的帧数
+const CACHE_CAPACITY: usize = 64;
+
+/// 每次从全局分配器取出或归还的帧数
+const BATCH_SIZE: usize = 32;
+
+lazy_static! {
+    /// 每个 hart 缓存的空闲帧
+    static ref CACHES: Vec<Lock<Vec<PhysicalPageNumber>>> = (0..MAX_HARTS)
+        .map(|_| Lock::new(Vec::with_capacity(CACHE_CAPACITY)))
+        .collect();
+}
+
+/// 直接从缓存中分配为命中，缓存为空、需要从全局分配器取出为未命中
+static COUNTERS: CacheCounters = CacheCounters::new();
+/// 缓存满，向全局分配器归还的次数
+static DRAINS: AtomicUsize = AtomicUsize::new(0);
+
+/// 帧分配的统计信息
+#[derive(Clone, Copy, Debug)]
+pub struct FrameStats {
+    /// 可用的帧总数
+    pub total: usize,
+    /// 空闲的帧数，包括各个 hart 缓存的帧
+    pub free: usize,
+    /// 各个 hart 缓存的帧数
+    pub cached: usize,
+    /// 直接从缓存中分配的次数
+    pub hits: usize,
+    /// 需要从全局分配器取出的次数
+    pub misses: usize,
+    /// 向全局分配器归还的次数
+    pub drains: usize,
+}
+
+/// 分配一个物理页
+///
+/// 全局分配器也没有空闲的帧时，收回所有 hart 缓存的帧再尝试一次
+pub fn alloc() -> MemoryResult<FrameTracker> {
+    without_interrupts(|| {
+        if let Some(ppn) = CACHES[hart_id()].lock().pop() {
+            COUNTERS.hit();
+            return Ok(FrameTracker(ppn));
+        }
+        COUNTERS.miss();
+        if !refill() {
+            drain_all();
+            refill();
+        }
+        CACHES[hart_id()]
+            .lock()
+            .pop()
+            .map(FrameTracker)
+            .ok_or("no available frame to allocate")
+    })
+}
+
+/// 回收一个物理页，由 [`FrameTracker`] 在 drop 时调用
+pub(super) fn dealloc(ppn: PhysicalPageNumber) {
+    without_interrupts(|| {
+        let mut cache = CACHES[hart_id()].lock();
+        if cache.len() >= CACHE_CAPACITY {
+            DRAINS.fetch_add(1, Ordering::Relaxed);
+            let mut allocator = FRAME_ALLOCATOR.lock();
+            for _ in 0..BATCH_SIZE {
+                allocator.dealloc(cache.pop().unwrap());
+            }
+        }
+        cache.push(ppn);
+    })
+}
+
+/// 从全局分配器为当前 hart 取出一批帧，返回是否取到了帧
+fn refill() -> bool {
+    let mut cache = CACHES[hart_id()].lock();
+    let mut allocator = FRAME_ALLOCATOR.lock();
+    while cache.len() < BATCH_SIZE {
+        match allocator.alloc() {
+            Ok(ppn) => cache.push(ppn),
+            Err(_) => break,
+        }
+    }
+    !cache.is_empty()
+}
+
+/// 将所有 hart 缓存的帧归还给全局分配器
+fn drain_all() {
+    for cache in CACHES.iter() {
+        let mut cache = cache.lock();
+        let mut allocator = FRAME_ALLOCATOR.lock();
+        for ppn in cache.drain(..) {
+            allocator.dealloc(ppn);
+        }
+    }
+}
+
+/// 帧分配的统计信息
+pub fn stats() -> FrameStats {
+    let cached = CACHES.iter().map(|cache| cache.lock().len()).sum::<usize>();
+    let (total, free) = {
+        let allocator = FRAME_ALLOCATOR.lock();
+        (allocator.total(), allocator.free())
+    };
+    FrameStats {
+        total,
+        free: free + cached,
+        cached,
+        hits: COUNTERS.hits(),
+        misses: COUNTERS.misses(),
+        drains: DRAINS.load(Ordering::Relaxed),
+    }
+}
+
+/// 所有 hart 的帧缓存合计的统计信息，登记到 [`stats`](crate::memory::stats)
+pub fn cache_stats() -> Vec<CacheStats> {
+    let stats = stats();
+    vec![CacheStats {
+        name: "frame",
+        cached: stats.cached,
+        capacity: CACHE_CAPACITY * MAX_HARTS,
+        hits: stats.hits,
+        misses: stats.misses,
+    }]
+}
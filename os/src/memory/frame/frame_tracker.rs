@@ -1,5 +1,6 @@
 use crate::memory::address::{PhysicalAddress, PhysicalPageNumber};
-use crate::memory::frame::allocator::FRAME_ALLOCATOR;
+use crate::memory::config::PAGE_SIZE;
+use crate::memory::frame::cache;
 
 /// 分配出的物理内存页
 ///
@@ -25,8 +26,23 @@ impl FrameTracker {
     }
 }
 
+/// `FrameTracker` 可以 deref 得到对应的 `[u8; PAGE_SIZE]`
+impl core::ops::Deref for FrameTracker {
+    type Target = [u8; PAGE_SIZE];
+    fn deref(&self) -> &Self::Target {
+        self.page_number().deref_kernel()
+    }
+}
+
+/// `FrameTracker` 可以 deref 得到对应的 `[u8; PAGE_SIZE]`
+impl core::ops::DerefMut for FrameTracker {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.page_number().deref_kernel()
+    }
+}
+
 impl Drop for FrameTracker {
     fn drop(&mut self) {
-        FRAME_ALLOCATOR.lock().dealloc(self);
+        cache::dealloc(self.0);
     }
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
 mod frame_tracker;
 mod allocator;
+mod cache;
 
-pub use allocator::FRAME_ALLOCATOR;
-pub use frame_tracker::FrameTracker;
\ No newline at end of file
+pub use allocator::{alloc_contiguous, release, reserve};
+pub use cache::{alloc, cache_stats, stats, FrameStats};
+pub use frame_tracker::FrameTracker;
@@ -1,5 +1,8 @@
 use crate::memory::config::KERNEL_HEAP_SIZE;
-use buddy_system_allocator::LockedHeap;
+use crate::sync::Lock;
+use alloc::alloc::{GlobalAlloc, Layout};
+use buddy_system_allocator::Heap;
+use core::ptr::{null_mut, NonNull};
 
 /// 进行动态内存分配所有的堆空间
 ///
@@ -7,24 +10,64 @@ use buddy_system_allocator::LockedHeap;
 /// 这段空间编译后被放在操作系统执行程序的 bss 段
 static mut HEAP_SPACE: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
 
+/// 用关闭中断的 [`Lock`] 包装的堆
+///
+/// 中断处理和调度循环中也会分配 / 释放堆空间，
+/// 如果线程持有堆的锁时被时钟中断打断，使用普通的自旋锁会导致死锁
+struct KernelHeap(Lock<Heap>);
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0
+            .lock()
+            .alloc(layout)
+            .map_or(null_mut(), |pointer| pointer.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(pointer), layout)
+    }
+}
+
 /// 堆， 动态内存分配
 ///
 /// ### ‘#[global_allocator]’
-/// ['LockedHeap'] 实现了 ['alloc::alloc::GlobalAlloc'] trait,
+/// [`KernelHeap`] 实现了 ['alloc::alloc::GlobalAlloc'] trait,
 /// 可以为全局需要到堆的地方分配空间. 例如 ‘Box’ 'Arc' 等
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: KernelHeap = KernelHeap(Lock::new(Heap::empty()));
 
 /// 初始化操作系统运行时堆空间
 pub fn init(){
     // 告诉配置器使用这一段预留的空间作为堆
     unsafe {
-        HEAP.lock().init(
+        HEAP.0.lock().init(
             HEAP_SPACE.as_ptr() as usize, KERNEL_HEAP_SIZE,
         );
     };
 }
 
+/// 堆的使用情况
+#[derive(Clone, Copy, Debug)]
+pub struct HeapStats {
+    /// 堆的总大小
+    pub total: usize,
+    /// 已经分配出去的空间，按伙伴系统的块大小计算
+    pub allocated: usize,
+    /// 分配时实际请求的空间
+    pub requested: usize,
+}
+
+/// 堆的使用情况
+pub fn stats() -> HeapStats {
+    let heap = HEAP.0.lock();
+    HeapStats {
+        total: heap.stats_total_bytes(),
+        allocated: heap.stats_alloc_actual(),
+        requested: heap.stats_alloc_user(),
+    }
+}
+
 /// 空间分配错误的回调， 直接 panic 推出
 #[alloc_error_handler]
 fn alloc_error_handler(_: alloc::alloc::Layout) -> ! {
@@ -0,0 +1,239 @@
+//! Rv39 页表的构建 [`Mapping`]
+//!
+//! 许多方法返回 [`Result`]，如果出现错误会返回 `Err(message)`。设计目标是，此时如果终止线程，则不会产生后续问题
+//!
+//! 同一个地址空间可能同时在多个 hart 上使用。修改页表之后，
+//! 除了刷新当前 hart 的 TLB，还要通过 IPI 刷新其他正在使用这个地址空间的 hart
+
+use crate::hart::{hart_id, MAX_HARTS};
+use crate::interrupt::ipi;
+use crate::memory::{
+    address::*,
+    config::PAGE_SIZE,
+    frame::{self, FrameTracker},
+    mapping::{Flags, MapType, PageTable, PageTableEntry, PageTableTracker, Segment},
+    MemoryResult,
+};
+use crate::sync::without_interrupts;
+use alloc::{vec, vec::Vec};
+use core::cmp::min;
+use core::ptr::slice_from_raw_parts_mut;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+
+lazy_static! {
+    /// 每个 hart 当前加载的根页表的物理页号
+    static ref ACTIVE_ROOTS: Vec<AtomicUsize> =
+        (0..MAX_HARTS).map(|_| AtomicUsize::new(0)).collect();
+}
+
+#[derive(Default)]
+/// 某个线程的内存映射关系
+pub struct Mapping {
+    /// 保存所有使用到的页表
+    page_tables: Vec<PageTableTracker>,
+    /// 根页表的物理页号
+    root_ppn: PhysicalPageNumber,
+}
+
+impl Mapping {
+    /// 将当前的映射加载到 `satp` 寄存器
+    ///
+    /// 同时记录当前 hart 使用的地址空间，关中断以免记录到其他 hart 上
+    pub fn activate(&self) {
+        // satp 低 27 位为页号，高 4 位为模式，8 表示 Sv39
+        let new_satp = self.root_ppn.0 | (8 << 60);
+        without_interrupts(|| {
+            ACTIVE_ROOTS[hart_id()].store(self.root_ppn.0, Ordering::SeqCst);
+            unsafe {
+                // 将 new_satp 的值写到 satp 寄存器
+                llvm_asm!("csrw satp, $0" :: "r"(new_satp) :: "volatile");
+                // 刷新 TLB
+                llvm_asm!("sfence.vma" :::: "volatile");
+            }
+        })
+    }
+
+    /// 除当前 hart 以外，正在使用这个地址空间的 hart
+    ///
+    /// 切换地址空间时会刷新全部 TLB，因此没有在使用的 hart 不会保留过期的 TLB 项
+    fn remote_harts(&self) -> usize {
+        without_interrupts(|| {
+            let current = hart_id();
+            ACTIVE_ROOTS
+                .iter()
+                .enumerate()
+                .filter(|(hart, root)| {
+                    *hart != current && root.load(Ordering::SeqCst) == self.root_ppn.0
+                })
+                .fold(0, |mask, (hart, _)| mask | (1 << hart))
+        })
+    }
+
+    /// 修改页表项之后，刷新其他 hart 上 `segment` 范围内的 TLB
+    fn shootdown(&self, segment: &Segment) {
+        let remote = self.remote_harts();
+        if remote != 0 {
+            let range = segment.page_range();
+            let start = VirtualAddress::from(range.start).0;
+            ipi::remote_sfence_vma(remote, start, range.len() * PAGE_SIZE);
+        }
+    }
+
+    /// 创建一个有根节点的映射
+    pub fn new() -> MemoryResult<Mapping> {
+        let root_table = PageTableTracker::new(frame::alloc()?);
+        let root_ppn = root_table.page_number();
+        Ok(Mapping {
+            page_tables: vec![root_table],
+            root_ppn,
+        })
+    }
+
+    /// 根页表的物理页号
+    pub fn root_ppn(&self) -> PhysicalPageNumber {
+        self.root_ppn
+    }
+
+    /// 加入一段映射，可能会相应地分配物理页面
+    ///
+    /// - `init_data`
+    ///     复制一段内存区域来初始化新的内存区域，其长度必须不超过 `segment` 的大小。
+    ///
+    /// 返回按帧映射时分配的所有物理页，由调用者负责保存。
+    /// 规范不保证无效的页表项不会被缓存，因此新建的映射同样需要刷新其他 hart 的 TLB
+    pub fn map(
+        &mut self,
+        segment: &Segment,
+        init_data: Option<&[u8]>,
+    ) -> MemoryResult<Vec<(VirtualPageNumber, FrameTracker)>> {
+        match segment.map_type {
+            // 线性映射，直接对虚拟地址进行转换
+            MapType::Linear => {
+                for vpn in segment.page_range().iter() {
+                    self.map_one(vpn, Some(vpn.into()), segment.flags | Flags::VALID)?;
+                }
+                // 拷贝数据
+                if let Some(data) = init_data {
+                    unsafe {
+                        (&mut *slice_from_raw_parts_mut(segment.range.start.deref::<u8>(), data.len()))
+                            .copy_from_slice(data);
+                    }
+                }
+                self.shootdown(segment);
+                Ok(Vec::new())
+            }
+            // 需要分配帧进行映射
+            MapType::Framed => {
+                // 记录所有成功分配的页面映射
+                let mut allocated_pairs = Vec::new();
+                for vpn in segment.page_range().iter() {
+                    // 分配物理页面并清零
+                    let mut frame = frame::alloc()?;
+                    frame.fill(0);
+                    // 拷贝数据，注意页表尚未应用，无法直接从刚刚映射的虚拟地址访问
+                    if let Some(data) = init_data {
+                        let page_address = VirtualAddress::from(vpn);
+                        // 本页内需要写入的区间 [start, stop)
+                        let start = if segment.range.start > page_address {
+                            segment.range.start - page_address
+                        } else {
+                            0
+                        };
+                        let stop = min(PAGE_SIZE, segment.range.end - page_address);
+                        // 数据在 data 中的区间，超出 data 的部分（例如 .bss）保持为 0
+                        let data_start = page_address + start - segment.range.start;
+                        let data_stop = min(data.len(), page_address + stop - segment.range.start);
+                        if data_start < data_stop {
+                            frame[start..start + (data_stop - data_start)]
+                                .copy_from_slice(&data[data_start..data_stop]);
+                        }
+                    }
+                    self.map_one(vpn, Some(frame.page_number()), segment.flags | Flags::VALID)?;
+                    allocated_pairs.push((vpn, frame));
+                }
+                self.shootdown(segment);
+                Ok(allocated_pairs)
+            }
+        }
+    }
+
+    /// 移除一段映射
+    ///
+    /// 其他 hart 上的 TLB 刷新完成后才返回，此后可以安全地释放物理页
+    pub fn unmap(&mut self, segment: &Segment) {
+        for vpn in segment.page_range().iter() {
+            if let Some(entry) = self.find_entry(vpn) {
+                entry.clear();
+                // 清除对应的 TLB 项
+                let address = VirtualAddress::from(vpn).0;
+                unsafe { llvm_asm!("sfence.vma $0, x0" :: "r"(address) :: "volatile") };
+            }
+        }
+        self.shootdown(segment);
+    }
+
+    /// 查找虚拟地址对应的物理地址，未映射则返回 `None`
+    pub fn lookup(&self, va: VirtualAddress) -> Option<PhysicalAddress> {
+        let entry = self.find_entry(VirtualPageNumber::floor(va))?;
+        if entry.flags().contains(Flags::VALID) {
+            Some(entry.address() + va.page_offset())
+        } else {
+            None
+        }
+    }
+
+    /// 找到虚拟页号对应的页表项，中间的页表不存在时返回 `None`
+    pub fn find_entry(&self, vpn: VirtualPageNumber) -> Option<&'static mut PageTableEntry> {
+        let root_table: &mut PageTable = PhysicalAddress::from(self.root_ppn).deref_kernel();
+        let mut entry = &mut root_table.entries[vpn.levels()[0]];
+        for vpn_slice in &vpn.levels()[1..] {
+            if entry.is_empty() {
+                return None;
+            }
+            entry = &mut entry.get_next_table().entries[*vpn_slice];
+        }
+        Some(entry)
+    }
+
+    /// 找到虚拟页号对应的页表项，如果不存在中间的页表则分配
+    fn find_or_create_entry(
+        &mut self,
+        vpn: VirtualPageNumber,
+    ) -> MemoryResult<&'static mut PageTableEntry> {
+        // 从根页表开始向下查询
+        // 这里不用 self.page_tables[0] 避免后面产生 borrow-check 冲突（我太菜了）
+        let root_table: &mut PageTable = PhysicalAddress::from(self.root_ppn).deref_kernel();
+        let mut entry = &mut root_table.entries[vpn.levels()[0]];
+        for vpn_slice in &vpn.levels()[1..] {
+            if entry.is_empty() {
+                // 如果页表不存在，则需要分配一个新的页表
+                let new_table = PageTableTracker::new(frame::alloc()?);
+                let new_ppn = new_table.page_number();
+                // 将新页表的页号写入当前的页表项
+                *entry = PageTableEntry::new(Some(new_ppn), Flags::VALID);
+                // 保存页表
+                self.page_tables.push(new_table);
+            }
+            // 进入下一级页表（使用偏移量来访问物理地址）
+            entry = &mut entry.get_next_table().entries[*vpn_slice];
+        }
+        // 此时 entry 位于第三级页表
+        Ok(entry)
+    }
+
+    /// 为给定的虚拟 / 物理页号建立映射关系
+    fn map_one(
+        &mut self,
+        vpn: VirtualPageNumber,
+        ppn: Option<PhysicalPageNumber>,
+        flags: Flags,
+    ) -> MemoryResult<()> {
+        // 定位到页表项
+        let entry = self.find_or_create_entry(vpn)?;
+        assert!(entry.is_empty(), "virtual address is already mapped");
+        // 页表项为空，则写入内容
+        *entry = PageTableEntry::new(ppn, flags);
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+//! 一个线程中关于内存空间的所有信息 [`MemorySet`]
+//!
+
+use crate::memory::{
+    address::*,
+    config::*,
+    frame::FrameTracker,
+    mapping::{Flags, MapType, Mapping, Segment},
+    mmio,
+    range::Range,
+    MemoryResult,
+};
+use alloc::{vec, vec::Vec};
+use xmas_elf::{
+    program::{SegmentData, Type},
+    ElfFile,
+};
+
+/// 一个地址空间中关于内存空间的所有信息
+pub struct MemorySet {
+    /// 维护页表和映射关系
+    pub mapping: Mapping,
+    /// 每个字段
+    pub segments: Vec<Segment>,
+    /// 所有分配的物理页面映射信息
+    pub allocated_pairs: Vec<(VirtualPageNumber, FrameTracker)>,
+}
+
+impl MemorySet {
+    /// 创建内核重映射
+    pub fn new_kernel() -> MemoryResult<MemorySet> {
+        // 在 linker.ld 里面标记的各个字段的起始点，均为 4K 对齐
+        extern "C" {
+            fn text_start();
+            fn rodata_start();
+            fn data_start();
+            fn bss_start();
+        }
+
+        // 建立字段
+        let mut segments = vec![
+            // .text 段，r-x
+            Segment {
+                map_type: MapType::Linear,
+                range: Range::from((text_start as usize)..(rodata_start as usize)),
+                flags: Flags::READABLE | Flags::EXECUTABLE,
+            },
+            // .rodata 段，r--
+            Segment {
+                map_type: MapType::Linear,
+                range: Range::from((rodata_start as usize)..(data_start as usize)),
+                flags: Flags::READABLE,
+            },
+            // .data 段，rw-
+            Segment {
+                map_type: MapType::Linear,
+                range: Range::from((data_start as usize)..(bss_start as usize)),
+                flags: Flags::READABLE | Flags::WRITABLE,
+            },
+            // .bss 段，rw-
+            Segment {
+                map_type: MapType::Linear,
+                range: Range::from(VirtualAddress::from(bss_start as usize)..*KERNEL_END_ADDRESS),
+                flags: Flags::READABLE | Flags::WRITABLE,
+            },
+            // 剩余内存空间，rw-
+            Segment {
+                map_type: MapType::Linear,
+                range: Range::from(
+                    *KERNEL_END_ADDRESS..VirtualAddress::from(memory_end()),
+                ),
+                flags: Flags::READABLE | Flags::WRITABLE,
+            },
+        ];
+        // 驱动登记的 MMIO 区域，rw-
+        segments.extend(mmio::regions().into_iter().map(|(start, len)| Segment {
+            map_type: MapType::Linear,
+            range: Range::from(VirtualAddress::from(start)..VirtualAddress::from(start + len)),
+            flags: Flags::READABLE | Flags::WRITABLE,
+        }));
+        let mut mapping = Mapping::new()?;
+
+        // 每个字段在页表中进行映射
+        for segment in segments.iter() {
+            mapping.map(segment, None)?;
+        }
+        Ok(MemorySet {
+            mapping,
+            segments,
+            allocated_pairs: Vec::new(),
+        })
+    }
+
+    /// 通过 elf 文件创建用户地址空间
+    ///
+    /// 地址空间中同样包含内核映射，中断时无需切换页表
+    pub fn from_elf(file: &ElfFile) -> MemoryResult<MemorySet> {
+        // 建立带有内核映射的 MemorySet
+        let mut memory_set = MemorySet::new_kernel()?;
+
+        // 遍历 elf 文件的所有部分
+        for program_header in file.program_iter() {
+            if program_header.get_type() != Ok(Type::Load) {
+                continue;
+            }
+            // 从每个字段读取「起始地址」「大小」和「数据」
+            let start = VirtualAddress(program_header.virtual_addr() as usize);
+            let size = program_header.mem_size() as usize;
+            let data: &[u8] =
+                if let SegmentData::Undefined(data) = program_header.get_data(file).unwrap() {
+                    data
+                } else {
+                    return Err("unsupported elf format");
+                };
+
+            // 将每一部分作为 Segment 进行映射
+            let segment = Segment {
+                map_type: MapType::Framed,
+                range: Range::from(start..(start + size)),
+                flags: Flags::USER
+                    | Flags::readable(program_header.flags().is_read())
+                    | Flags::writable(program_header.flags().is_write())
+                    | Flags::executable(program_header.flags().is_execute()),
+            };
+
+            // 建立映射并复制数据
+            memory_set.add_segment(segment, Some(data))?;
+        }
+
+        Ok(memory_set)
+    }
+
+    /// 复制地址空间，用于 `fork`
+    ///
+    /// 内核映射重新建立，按帧映射的字段分配新的物理页并逐页复制数据
+    pub fn fork(&self) -> MemoryResult<MemorySet> {
+        let mut memory_set = MemorySet::new_kernel()?;
+        for segment in self
+            .segments
+            .iter()
+            .filter(|segment| segment.map_type == MapType::Framed)
+        {
+            memory_set.add_segment(*segment, None)?;
+            for vpn in segment.page_range().iter() {
+                let address = VirtualAddress::from(vpn);
+                let source = self.mapping.lookup(address).unwrap();
+                let destination = memory_set.mapping.lookup(address).unwrap();
+                destination
+                    .deref_kernel::<[u8; PAGE_SIZE]>()
+                    .copy_from_slice(source.deref_kernel::<[u8; PAGE_SIZE]>());
+            }
+        }
+        Ok(memory_set)
+    }
+
+    /// 替换 `satp` 以激活页表
+    ///
+    /// 如果当前页表就是自身，则不会替换，但仍然会刷新 TLB。
+    pub fn activate(&self) {
+        self.mapping.activate()
+    }
+
+    /// 添加一个 [`Segment`] 的内存映射
+    pub fn add_segment(&mut self, segment: Segment, init_data: Option<&[u8]>) -> MemoryResult<()> {
+        // 检测 segment 没有重合
+        if self.overlap_with(segment.page_range()) {
+            return Err("segment overlaps with existing segments");
+        }
+        // 映射
+        self.allocated_pairs
+            .extend(self.mapping.map(&segment, init_data)?);
+        self.segments.push(segment);
+        Ok(())
+    }
+
+    /// 移除一个 [`Segment`] 的内存映射
+    ///
+    /// `segment` 必须已经映射
+    pub fn remove_segment(&mut self, segment: &Segment) -> MemoryResult<()> {
+        // 找到对应的 segment
+        let segment_index = self
+            .segments
+            .iter()
+            .position(|s| s == segment)
+            .ok_or("segment to remove cannot be found")?;
+        self.segments.remove(segment_index);
+        // 移除映射
+        self.mapping.unmap(segment);
+        // 释放被移除的页面
+        let page_range = segment.page_range();
+        self.allocated_pairs
+            .retain(|(vpn, _frame)| *vpn < page_range.start || *vpn >= page_range.end);
+        Ok(())
+    }
+
+    /// 检测一段内存区域和已有的是否存在重叠区域
+    pub fn overlap_with(&self, range: Range<VirtualPageNumber>) -> bool {
+        for seg in self.segments.iter() {
+            if range.overlap_with(&seg.page_range()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 从 `start` 开始，最多 `max_len` 字节中可以由用户以 `flags` 权限访问的连续长度
+    ///
+    /// 所访问的每一个字节都必须位于带有 `USER` 和 `flags` 标志的 [`Segment`] 中
+    pub fn user_accessible_len(&self, start: VirtualAddress, max_len: usize, flags: Flags) -> usize {
+        let end = match start.0.checked_add(max_len) {
+            Some(end) => VirtualAddress(end),
+            None => return 0,
+        };
+        let mut address = start;
+        while address < end {
+            match self.segments.iter().find(|segment| {
+                segment.range.start <= address
+                    && address < segment.range.end
+                    && segment.flags.contains(flags | Flags::USER)
+            }) {
+                Some(segment) => address = segment.range.end,
+                None => return address - start,
+            }
+        }
+        max_len
+    }
+
+    /// 检查一段用户地址是否全部可以由用户以 `flags` 权限访问
+    pub fn check_user_range(&self, start: VirtualAddress, len: usize, flags: Flags) -> bool {
+        self.user_accessible_len(start, len, flags) == len
+    }
+}
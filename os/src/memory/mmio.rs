@@ -0,0 +1,38 @@
+//! 需要在内核地址空间中线性映射的 MMIO 区域
+//!
+//! 启动页表已经映射了低 1G 的物理地址，内核线程可以直接访问设备寄存器；
+//! 而进程的地址空间由 [`MemorySet::new_kernel`](crate::memory::mapping::MemorySet::new_kernel)
+//! 建立，只会映射驱动通过 [`add_region`] 登记的区域
+
+use crate::memory::address::PhysicalAddress;
+use crate::memory::config::PAGE_SIZE;
+use crate::sync::Lock;
+use alloc::vec::Vec;
+
+/// 所有登记的区域（物理地址，长度），均按页对齐
+static REGIONS: Lock<Vec<(PhysicalAddress, usize)>> = Lock::new(Vec::new());
+
+/// 登记一段 MMIO 区域，与已有区域重叠的部分会被合并
+///
+/// 需要在创建进程之前登记，已经创建的地址空间不会更新
+pub fn add_region(start: PhysicalAddress, len: usize) {
+    let mut end = (start.0 + len + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+    let mut start = start.0 / PAGE_SIZE * PAGE_SIZE;
+    let mut regions = REGIONS.lock();
+    regions.retain(|&(region_start, region_len)| {
+        let region_end = region_start.0 + region_len;
+        if region_start.0 <= end && start <= region_end {
+            start = start.min(region_start.0);
+            end = end.max(region_end);
+            false
+        } else {
+            true
+        }
+    });
+    regions.push((PhysicalAddress(start), end - start));
+}
+
+/// 所有登记的区域
+pub fn regions() -> Vec<(PhysicalAddress, usize)> {
+    REGIONS.lock().clone()
+}
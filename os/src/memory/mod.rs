@@ -11,13 +11,16 @@ pub mod heap;
 pub mod address;
 pub mod frame;
 pub mod range;
-mod mapping;
+pub mod mapping;
+pub mod mmio;
+pub mod stats;
+pub mod user;
 
 pub type MemoryResult<T> = Result<T, &'static str>;
 
 pub fn init(){
     heap::init();
-    // 允许内核读写用户态内存
-    unsafe { riscv::register::sstatus::set_sum() };
+    stats::register_cache(frame::cache_stats);
+    // 内核只能通过 [`user`] 模块中的函数读写用户态内存，不再全局打开 SUM
     println!("mod memory initialized")
 }
\ No newline at end of file
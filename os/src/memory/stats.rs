@@ -0,0 +1,73 @@
+//! 内核中各种缓存的统计信息
+//!
+//! 缓存使用 [`CacheCounters`] 计数命中和未命中，并通过 [`register_cache`] 登记一个汇报函数，
+//! [`caches`] 汇总所有登记的缓存
+
+use crate::sync::Lock;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 命中和未命中的计数
+pub struct CacheCounters {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl CacheCounters {
+    pub const fn new() -> Self {
+        CacheCounters {
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// 记录一次命中
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次未命中
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 命中次数
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// 未命中次数
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+/// 一个缓存的统计信息
+#[derive(Clone, Copy, Debug)]
+pub struct CacheStats {
+    /// 缓存的名称
+    pub name: &'static str,
+    /// 当前缓存的项数
+    pub cached: usize,
+    /// 最多缓存的项数
+    pub capacity: usize,
+    /// 命中次数
+    pub hits: usize,
+    /// 未命中次数
+    pub misses: usize,
+}
+
+/// 所有登记的缓存的汇报函数
+static SOURCES: Lock<Vec<fn() -> Vec<CacheStats>>> = Lock::new(Vec::new());
+
+/// 登记一个汇报函数，一个函数可以汇报多个缓存
+pub fn register_cache(source: fn() -> Vec<CacheStats>) {
+    SOURCES.lock().push(source);
+}
+
+/// 所有缓存的统计信息
+pub fn caches() -> Vec<CacheStats> {
+    // 汇报函数可能需要加锁，不在持有 `SOURCES` 时调用
+    let sources = SOURCES.lock().clone();
+    sources.iter().flat_map(|source| source()).collect()
+}
@@ -0,0 +1,56 @@
+# 内核访问用户内存的拷贝过程
+#
+# 每一条可能访问用户内存的指令都会在 __ex_table 段中登记一项 (出错指令地址, 修复代码地址)。
+# 如果这些指令触发了缺页等异常，中断处理会查询异常表并将 sepc 改为修复代码，使拷贝返回错误
+
+    .section .text
+    .globl __copy_user
+# __copy_user(dst: *mut u8, src: *const u8, len: usize) -> usize
+# 逐字节拷贝，成功返回 0，出错返回 1
+__copy_user:
+    beqz    a2, .Lcopy_user_done
+.Lcopy_user_loop:
+.Lcopy_user_load:
+    lbu     t0, 0(a1)
+.Lcopy_user_store:
+    sb      t0, 0(a0)
+    addi    a0, a0, 1
+    addi    a1, a1, 1
+    addi    a2, a2, -1
+    bnez    a2, .Lcopy_user_loop
+.Lcopy_user_done:
+    li      a0, 0
+    ret
+.Lcopy_user_fault:
+    li      a0, 1
+    ret
+
+    .globl __strncpy_user
+# __strncpy_user(dst: *mut u8, src: *const u8, max: usize) -> isize
+# 拷贝至 '\0'（包括）或 max 字节为止
+# 返回不含 '\0' 的字符串长度，未遇到 '\0' 则返回 max，出错返回 -1
+__strncpy_user:
+    li      t1, 0
+.Lstrncpy_user_loop:
+    beq     t1, a2, .Lstrncpy_user_done
+    add     t2, a1, t1
+.Lstrncpy_user_load:
+    lbu     t0, 0(t2)
+    add     t2, a0, t1
+    sb      t0, 0(t2)
+    beqz    t0, .Lstrncpy_user_done
+    addi    t1, t1, 1
+    j       .Lstrncpy_user_loop
+.Lstrncpy_user_done:
+    mv      a0, t1
+    ret
+.Lstrncpy_user_fault:
+    li      a0, -1
+    ret
+
+    # 异常表，由 linker.ld 汇总至 ex_table_start 和 ex_table_end 之间
+    .section __ex_table, "a"
+    .balign 8
+    .dword  .Lcopy_user_load, .Lcopy_user_fault
+    .dword  .Lcopy_user_store, .Lcopy_user_fault
+    .dword  .Lstrncpy_user_load, .Lstrncpy_user_fault
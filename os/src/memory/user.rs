@@ -0,0 +1,118 @@
+//! 内核访问用户态内存的接口
+//!
+//! `sstatus.SUM` 平时处于关闭状态，内核无法直接读写用户内存，只有下列函数执行期间才会打开：
+//! - [`copy_from_user`]
+//! - [`copy_to_user`]
+//! - [`strncpy_from_user`]
+//!
+//! 这些函数首先根据 [`MemorySet`] 检查地址区间，再由 `user.asm` 中的汇编过程完成拷贝。
+//! 如果拷贝过程中仍然发生了缺页等异常，中断处理会通过 [`search_exception_table`]
+//! 跳转到修复代码，最终返回 [`EFAULT`] 而不会让内核 panic
+
+use crate::kernel::errno::EFAULT;
+use crate::memory::{address::VirtualAddress, mapping::Flags, mapping::MemorySet};
+use core::mem::size_of;
+use riscv::register::sstatus;
+
+global_asm!(include_str!("user.asm"));
+
+extern "C" {
+    fn __copy_user(dst: *mut u8, src: *const u8, len: usize) -> usize;
+    fn __strncpy_user(dst: *mut u8, src: *const u8, max: usize) -> isize;
+    /// 由 `linker.ld` 指定的异常表起始位置
+    fn ex_table_start();
+    /// 由 `linker.ld` 指定的异常表结束位置
+    fn ex_table_end();
+}
+
+/// 访问用户内存的结果，错误值为错误码
+pub type UserResult<T> = Result<T, isize>;
+
+/// 异常表中的一项，由 `user.asm` 生成
+#[repr(C)]
+struct ExceptionTableEntry {
+    /// 可能出错的指令地址
+    instruction: usize,
+    /// 出错后跳转的修复代码地址
+    fixup: usize,
+}
+
+/// 查找出错指令对应的修复代码地址
+///
+/// 只有登记在异常表中的指令（即访问用户内存的指令）会返回 `Some`
+pub fn search_exception_table(pc: usize) -> Option<usize> {
+    let table = unsafe {
+        core::slice::from_raw_parts(
+            ex_table_start as usize as *const ExceptionTableEntry,
+            (ex_table_end as usize - ex_table_start as usize) / size_of::<ExceptionTableEntry>(),
+        )
+    };
+    table
+        .iter()
+        .find(|entry| entry.instruction == pc)
+        .map(|entry| entry.fixup)
+}
+
+/// 在作用域内打开 `sstatus.SUM`，离开作用域时关闭
+struct SumGuard;
+
+impl SumGuard {
+    fn new() -> Self {
+        unsafe { sstatus::set_sum() };
+        SumGuard
+    }
+}
+
+impl Drop for SumGuard {
+    fn drop(&mut self) {
+        unsafe { sstatus::clear_sum() };
+    }
+}
+
+/// 从用户地址 `src` 拷贝 `dst.len()` 字节到内核缓冲区 `dst`
+pub fn copy_from_user(memory_set: &MemorySet, dst: &mut [u8], src: VirtualAddress) -> UserResult<()> {
+    if !memory_set.check_user_range(src, dst.len(), Flags::READABLE) {
+        return Err(EFAULT);
+    }
+    let _guard = SumGuard::new();
+    match unsafe { __copy_user(dst.as_mut_ptr(), src.0 as *const u8, dst.len()) } {
+        0 => Ok(()),
+        _ => Err(EFAULT),
+    }
+}
+
+/// 将内核缓冲区 `src` 拷贝到用户地址 `dst`
+pub fn copy_to_user(memory_set: &MemorySet, dst: VirtualAddress, src: &[u8]) -> UserResult<()> {
+    if !memory_set.check_user_range(dst, src.len(), Flags::WRITABLE) {
+        return Err(EFAULT);
+    }
+    let _guard = SumGuard::new();
+    match unsafe { __copy_user(dst.0 as *mut u8, src.as_ptr(), src.len()) } {
+        0 => Ok(()),
+        _ => Err(EFAULT),
+    }
+}
+
+/// 从用户地址 `src` 拷贝以 `'\0'` 结尾的字符串到 `dst`
+///
+/// 返回不含 `'\0'` 的长度；如果 `dst` 被填满仍未遇到 `'\0'`，则返回 `dst.len()`
+pub fn strncpy_from_user(
+    memory_set: &MemorySet,
+    dst: &mut [u8],
+    src: VirtualAddress,
+) -> UserResult<usize> {
+    // 字符串长度未知，只拷贝可以访问的部分
+    let len = memory_set.user_accessible_len(src, dst.len(), Flags::READABLE);
+    let copied = {
+        let _guard = SumGuard::new();
+        unsafe { __strncpy_user(dst.as_mut_ptr(), src.0 as *const u8, len) }
+    };
+    if copied < 0 {
+        Err(EFAULT)
+    } else if copied as usize == len && len < dst.len() {
+        // 在可访问的区域内没有遇到 '\0'
+        Err(EFAULT)
+    } else {
+        Ok(copied as usize)
+    }
+}
@@ -0,0 +1,13 @@
+//! 定义一些进程相关的常量
+
+/// 每个线程的内核栈大小 32 KB
+pub const KERNEL_STACK_SIZE: usize = 0x8000;
+
+/// 用户程序的栈大小 64 KB
+pub const USER_STACK_SIZE: usize = 0x1_0000;
+
+/// 用户栈顶地址，位于 Sv39 用户地址空间（低 256G）的顶端
+pub const USER_STACK_TOP: usize = 0x40_0000_0000;
+
+/// 每个进程最多打开的文件数
+pub const MAX_DESCRIPTORS: usize = 256;
@@ -0,0 +1,61 @@
+//! 线程的内核栈 [`KernelStack`]
+//!
+//! 每个线程拥有独立的内核栈：
+//! - 用户线程进入中断时，[`Context`] 保存在内核栈的栈顶，中断处理也在其上进行；
+//! - 内核线程直接在内核栈上运行
+//!
+//! 因此线程可以在内核中的任何位置睡眠，切换回来之后继续执行
+
+use super::config::KERNEL_STACK_SIZE;
+use crate::interrupt::Context;
+use crate::memory::config::PAGE_SIZE;
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
+use core::mem::size_of;
+
+/// 从堆上分配的一段内核栈
+pub struct KernelStack {
+    /// 栈底（低地址）
+    bottom: usize,
+}
+
+impl KernelStack {
+    /// 内核栈的内存布局，按页对齐
+    fn layout() -> Layout {
+        Layout::from_size_align(KERNEL_STACK_SIZE, PAGE_SIZE).unwrap()
+    }
+
+    /// 分配一个新的内核栈
+    pub fn new() -> Self {
+        let bottom = unsafe { alloc_zeroed(Self::layout()) } as usize;
+        assert!(bottom != 0, "failed to allocate kernel stack");
+        Self { bottom }
+    }
+
+    /// 栈底地址
+    pub fn bottom(&self) -> usize {
+        self.bottom
+    }
+
+    /// 栈顶地址
+    pub fn top(&self) -> usize {
+        self.bottom + KERNEL_STACK_SIZE
+    }
+
+    /// 从用户态进入中断时 [`Context`] 所在的位置，即栈顶
+    pub fn context_ptr(&self) -> *mut Context {
+        (self.top() - size_of::<Context>()) as *mut Context
+    }
+
+    /// 将 [`Context`] 写入栈顶，并返回其地址
+    pub fn push_context(&self, context: Context) -> *mut Context {
+        let ptr = self.context_ptr();
+        unsafe { *ptr = context };
+        ptr
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.bottom as *mut u8, Self::layout()) };
+    }
+}
@@ -0,0 +1,67 @@
+//! 用户程序的来源
+//!
+//! `exec` 时优先从文件系统中按路径读取 ELF 文件；
+//! 没有挂载文件系统或找不到时，再按名称查找嵌入内核镜像的程序
+
+use crate::fs;
+use crate::kernel::errno::ENOENT;
+use alloc::{borrow::Cow, vec::Vec};
+use spin::Mutex;
+
+/// 已经登记的用户程序，`(名称, ELF 数据)`
+static PROGRAMS: Mutex<Vec<(&'static str, &'static [u8])>> = Mutex::new(Vec::new());
+
+/// 登记一个用户程序
+pub fn register(name: &'static str, elf_data: &'static [u8]) {
+    PROGRAMS.lock().push((name, elf_data));
+}
+
+/// 按名称查找用户程序的 ELF 数据
+pub fn find(name: &str) -> Option<&'static [u8]> {
+    PROGRAMS
+        .lock()
+        .iter()
+        .find(|(program, _)| *program == name)
+        .map(|(_, elf_data)| *elf_data)
+}
+
+/// 读取路径 `path` 对应的用户程序
+///
+/// 需要读取块设备，只能在线程中调用
+pub fn load(path: &str) -> Result<Cow<'static, [u8]>, isize> {
+    match fs::lookup(path) {
+        Ok(inode) => return Ok(Cow::Owned(inode.read_all()?)),
+        Err(ENOENT) => {}
+        Err(errno) => return Err(errno),
+    }
+    find(path).map(Cow::Borrowed).ok_or(ENOENT)
+}
+
+// 由 build.rs 生成，包含所有嵌入内核的用户程序
+global_asm!(include_str!(concat!(env!("OUT_DIR"), "/link_app.S")));
+
+/// 登记所有嵌入内核镜像的用户程序
+pub fn init() {
+    extern "C" {
+        /// 程序个数，其后依次为每个程序的起止地址
+        fn _app_count();
+        /// 依次以 '\0' 结尾的程序名称
+        fn _app_names();
+    }
+    unsafe {
+        let count_pointer = _app_count as usize as *const usize;
+        let count = count_pointer.read();
+        let ranges = core::slice::from_raw_parts(count_pointer.add(1), count * 2);
+        let mut name_pointer = _app_names as usize as *const u8;
+        for i in 0..count {
+            let len = (0..).find(|&j| *name_pointer.add(j) == 0).unwrap();
+            let name = core::str::from_utf8_unchecked(core::slice::from_raw_parts(name_pointer, len));
+            let elf_data = core::slice::from_raw_parts(
+                ranges[i * 2] as *const u8,
+                ranges[i * 2 + 1] - ranges[i * 2],
+            );
+            register(name, elf_data);
+            name_pointer = name_pointer.add(len + 1);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! 管理进程 / 线程
+//!
+//! - [`Process`] 拥有地址空间、文件描述符，并维护父子关系和退出码
+//! - [`Thread`] 拥有独立的内核栈，由 [`processor`] 中的调度循环切换执行
+
+mod config;
+mod kernel_stack;
+pub mod loader;
+mod process;
+mod processor;
+mod thread;
+
+use crate::interrupt::*;
+use crate::memory::{address::*, MemoryResult};
+use crate::sync::{Lock, LockGuard};
+use alloc::{sync::Arc, vec, vec::Vec};
+
+pub use config::*;
+pub use kernel_stack::KernelStack;
+pub use process::{processes, Process, ProcessInner, INIT_PROCESS, KERNEL_PROCESS};
+pub use processor::*;
+pub use thread::{TaskContext, Thread, ThreadID, ThreadStatus};
+
+/// 登记嵌入内核的用户程序，并创建启动初始进程的内核线程
+pub fn init() {
+    loader::init();
+    add_thread(Thread::new_kernel(start_init_process as usize, None));
+    info!("mod process initialized");
+}
+
+/// 挂载根文件系统，并启动初始进程 `initproc`
+///
+/// 读取块设备时需要睡眠，因此在内核线程中执行
+fn start_init_process() {
+    crate::fs::init();
+    let elf_data = loader::load("initproc").expect("initproc is not found");
+    let process = Process::from_elf(&elf_data, alloc::sync::Weak::new()).unwrap();
+    INIT_PROCESS.call_once(|| process);
+}
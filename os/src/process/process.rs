@@ -0,0 +1,291 @@
+//! 进程 [`Process`]
+
+use super::*;
+use crate::fs::{File, STDIN, STDOUT};
+use crate::kernel::errno::*;
+use crate::memory::mapping::{Flags, MapType, MemorySet, Segment};
+use crate::memory::range::Range;
+use crate::memory::user::copy_to_user;
+use crate::sync::WaitQueue;
+use alloc::sync::Weak;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use spin::Once;
+use xmas_elf::ElfFile;
+
+/// 进程 ID 计数器，0 号为内核进程
+static PID_COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+lazy_static! {
+    /// 内核进程，所有内核线程属于这个进程
+    pub static ref KERNEL_PROCESS: Arc<Process> = Process::new_kernel().unwrap();
+}
+
+/// 初始进程，父进程退出后，其子进程会被交给初始进程
+pub static INIT_PROCESS: Once<Arc<Process>> = Once::new();
+
+/// 进程的信息
+///
+/// 为了避免不同进程之间互相加锁产生死锁，`parent` 和 `exit_code` 单独加锁，
+/// 持有它们时不会再获取其他锁
+pub struct Process {
+    /// 进程 ID
+    pub pid: usize,
+    /// 是否为用户进程
+    pub is_user: bool,
+    /// 父进程，父进程退出后变为初始进程
+    parent: Lock<Weak<Process>>,
+    /// 退出码，为 `Some` 表示进程已经退出
+    exit_code: Lock<Option<i32>>,
+    /// 正在等待子进程退出的线程
+    child_exit: WaitQueue,
+    /// 用 [`Lock`] 包装一些可变的变量
+    inner: Lock<ProcessInner>,
+}
+
+/// 进程中需要可变的部分
+pub struct ProcessInner {
+    /// 进程中的线程公用页表 / 内存映射
+    pub memory_set: MemorySet,
+    /// 打开的文件描述符
+    pub descriptors: Vec<Option<Arc<dyn File>>>,
+    /// 子进程，包括已经退出但还没有被回收的进程
+    pub children: Vec<Arc<Process>>,
+}
+
+impl Process {
+    /// 创建内核进程
+    fn new_kernel() -> MemoryResult<Arc<Self>> {
+        Ok(Arc::new(Self {
+            pid: 0,
+            is_user: false,
+            parent: Lock::new(Weak::new()),
+            exit_code: Lock::new(None),
+            child_exit: WaitQueue::new(),
+            inner: Lock::new(ProcessInner::new(MemorySet::new_kernel()?)),
+        }))
+    }
+
+    /// 加载 ELF 文件创建用户进程，并创建其主线程加入调度
+    pub fn from_elf(elf_data: &[u8], parent: Weak<Process>) -> Result<Arc<Self>, isize> {
+        let (memory_set, entry_point) = load_elf(elf_data)?;
+        let process = Arc::new(Self {
+            pid: PID_COUNTER.fetch_add(1, Ordering::Relaxed),
+            is_user: true,
+            parent: Lock::new(parent),
+            exit_code: Lock::new(None),
+            child_exit: WaitQueue::new(),
+            inner: Lock::new(ProcessInner::new(memory_set)),
+        });
+        let context = Context::new(USER_STACK_TOP, entry_point, None, true);
+        add_thread(Thread::new(process.clone(), context));
+        Ok(process)
+    }
+
+    /// 上锁并获得可变部分的引用
+    pub fn inner(&self) -> LockGuard<ProcessInner> {
+        self.inner.lock()
+    }
+
+    /// 父进程
+    pub fn parent(&self) -> Option<Arc<Process>> {
+        self.parent.lock().upgrade()
+    }
+
+    /// 退出码，进程尚未退出时为 `None`
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit_code.lock()
+    }
+
+    /// 复制当前进程，子进程从 `context` 处开始执行，返回值为 0
+    ///
+    /// 地址空间逐页复制，文件描述符与父进程共享
+    pub fn fork(self: &Arc<Self>, context: &Context) -> Result<Arc<Self>, isize> {
+        let mut inner = self.inner();
+        let memory_set = inner.memory_set.fork().map_err(|_| ENOMEM)?;
+        let child = Arc::new(Self {
+            pid: PID_COUNTER.fetch_add(1, Ordering::Relaxed),
+            is_user: self.is_user,
+            parent: Lock::new(Arc::downgrade(self)),
+            exit_code: Lock::new(None),
+            child_exit: WaitQueue::new(),
+            inner: Lock::new(ProcessInner {
+                memory_set,
+                descriptors: inner.descriptors.clone(),
+                children: Vec::new(),
+            }),
+        });
+        inner.children.push(child.clone());
+        drop(inner);
+
+        let mut child_context = *context;
+        child_context.x[10] = 0;
+        add_thread(Thread::new(child.clone(), child_context));
+        Ok(child)
+    }
+
+    /// 将进程替换为新的 ELF 文件，并将 `context` 设置为从其入口开始执行
+    ///
+    /// `arguments` 会被放在新的用户栈上，以 `argc` 和 `argv` 的形式传给程序
+    pub fn exec(
+        &self,
+        elf_data: &[u8],
+        arguments: &[Vec<u8>],
+        context: &mut Context,
+    ) -> Result<(), isize> {
+        let (memory_set, entry_point) = load_elf(elf_data)?;
+        let mut inner = self.inner();
+        // 先激活新的地址空间，再释放旧的
+        memory_set.activate();
+        inner.memory_set = memory_set;
+
+        // 在用户栈上依次放置参数字符串和指针数组
+        let mut stack_top = USER_STACK_TOP;
+        let mut pointers = Vec::new();
+        for argument in arguments.iter() {
+            stack_top -= argument.len() + 1;
+            copy_to_user(&inner.memory_set, VirtualAddress(stack_top), argument)?;
+            copy_to_user(
+                &inner.memory_set,
+                VirtualAddress(stack_top + argument.len()),
+                &[0],
+            )?;
+            pointers.push(stack_top);
+        }
+        pointers.push(0);
+        stack_top -= pointers.len() * size_of::<usize>();
+        stack_top -= stack_top % 16;
+        let argv = stack_top;
+        for (i, pointer) in pointers.iter().enumerate() {
+            copy_to_user(
+                &inner.memory_set,
+                VirtualAddress(argv + i * size_of::<usize>()),
+                &pointer.to_ne_bytes(),
+            )?;
+        }
+
+        *context = Context::new(stack_top, entry_point, Some(&[arguments.len(), argv]), true);
+        Ok(())
+    }
+
+    /// 进程退出，记录退出码并将子进程交给初始进程
+    ///
+    /// 进程的其余资源在父进程回收时释放
+    pub fn exit(self: &Arc<Self>, exit_code: i32) {
+        if let Some(init) = INIT_PROCESS.get() {
+            assert!(!Arc::ptr_eq(self, init), "init process exited with {}", exit_code);
+        }
+        *self.exit_code.lock() = Some(exit_code);
+
+        let children = {
+            let mut inner = self.inner();
+            inner.descriptors.clear();
+            core::mem::take(&mut inner.children)
+        };
+        // 将子进程交给初始进程
+        if !children.is_empty() {
+            let init = INIT_PROCESS.get().expect("init process is not running");
+            let mut init_inner = init.inner();
+            for child in children {
+                *child.parent.lock() = Arc::downgrade(init);
+                init_inner.children.push(child);
+            }
+            // 其中可能有已经退出的进程，需要初始进程来回收
+            init.child_exit.wake_all();
+        }
+
+        // 唤醒等待的父进程。持有父进程的锁，保证父进程要么已经登记等待，要么能看到退出码
+        if let Some(parent) = self.parent() {
+            let _parent_inner = parent.inner();
+            parent.child_exit.wake_all();
+        }
+    }
+
+    /// 等待子进程退出并回收，`pid` 为 -1 时等待任意子进程
+    ///
+    /// 返回子进程的 pid，并将退出码写入用户地址 `exit_code`（为 0 时不写入）
+    pub fn wait(&self, pid: isize, exit_code: VirtualAddress) -> Result<usize, isize> {
+        let matches = |child: &Arc<Process>| pid == -1 || child.pid as isize == pid;
+        loop {
+            let mut inner = self.inner();
+            if !inner.children.iter().any(matches) {
+                return Err(ECHILD);
+            }
+            let zombie = inner
+                .children
+                .iter()
+                .position(|child| matches(child) && child.exit_code().is_some());
+            if let Some(index) = zombie {
+                let child = inner.children.remove(index);
+                if exit_code.valid() {
+                    let code = child.exit_code().unwrap();
+                    copy_to_user(&inner.memory_set, exit_code, &code.to_ne_bytes())?;
+                }
+                return Ok(child.pid);
+            }
+            // 等待子进程退出，登记之后才释放锁，避免错过唤醒
+            self.child_exit.sleep_with(inner);
+        }
+    }
+}
+
+/// 所有进程，包括内核进程和已经退出但还没有被回收的进程，按 pid 排序
+///
+/// 父进程退出后子进程会交给初始进程，因此所有用户进程都可以从初始进程出发找到
+pub fn processes() -> Vec<Arc<Process>> {
+    let mut processes = vec![KERNEL_PROCESS.clone()];
+    let mut pending: Vec<Arc<Process>> = INIT_PROCESS.get().cloned().into_iter().collect();
+    while let Some(process) = pending.pop() {
+        // 不同时持有多个进程的锁
+        pending.extend(process.inner().children.iter().cloned());
+        processes.push(process);
+    }
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+impl ProcessInner {
+    /// 新进程的可变部分，文件描述符 0 / 1 / 2 对应控制台
+    fn new(memory_set: MemorySet) -> Self {
+        Self {
+            memory_set,
+            descriptors: vec![
+                Some(STDIN.clone()),
+                Some(STDOUT.clone()),
+                Some(STDOUT.clone()),
+            ],
+            children: Vec::new(),
+        }
+    }
+
+    /// 将文件放入编号最小的空闲文件描述符，返回文件描述符
+    pub fn alloc_descriptor(&mut self, file: Arc<dyn File>) -> Result<usize, isize> {
+        if let Some(fd) = self.descriptors.iter().position(Option::is_none) {
+            self.descriptors[fd] = Some(file);
+            return Ok(fd);
+        }
+        if self.descriptors.len() >= MAX_DESCRIPTORS {
+            return Err(EMFILE);
+        }
+        self.descriptors.push(Some(file));
+        Ok(self.descriptors.len() - 1)
+    }
+}
+
+/// 从 ELF 文件建立用户地址空间，包括用户栈，返回地址空间和入口地址
+fn load_elf(elf_data: &[u8]) -> Result<(MemorySet, usize), isize> {
+    let elf = ElfFile::new(elf_data).map_err(|_| ENOEXEC)?;
+    let mut memory_set = MemorySet::from_elf(&elf).map_err(|_| ENOEXEC)?;
+    memory_set
+        .add_segment(
+            Segment {
+                map_type: MapType::Framed,
+                range: Range::from((USER_STACK_TOP - USER_STACK_SIZE)..USER_STACK_TOP),
+                flags: Flags::USER | Flags::READABLE | Flags::WRITABLE,
+            },
+            None,
+        )
+        .map_err(|_| ENOMEM)?;
+    Ok((memory_set, elf.header.pt2.entry_point() as usize))
+}
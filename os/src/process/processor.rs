@@ -0,0 +1,214 @@
+//! 实现线程的调度和管理 [`Processor`]
+
+use super::*;
+use crate::hart::{hart_id, MAX_HARTS};
+use crate::interrupt::{ipi, timer};
+use crate::sync::without_interrupts;
+use algorithm::*;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use riscv::register::sstatus;
+
+global_asm!(include_str!("switch.asm"));
+
+extern "C" {
+    /// 保存当前的寄存器至 `current`，并切换至 `next`
+    fn __switch(current: *mut TaskContext, next: *const TaskContext);
+}
+
+lazy_static! {
+    /// 所有就绪线程的调度队列
+    static ref SCHEDULER: Lock<SchedulerImpl<Arc<Thread>>> = Lock::new(SchedulerImpl::default());
+}
+
+/// 处理器的调度状态，每个 hart 一个
+///
+/// 只会在关中断的情况下由 hart 自身访问，因此不需要加锁。
+/// 开中断时线程可能被抢占并迁移到其他 hart，因此访问期间必须关中断
+pub struct Processor {
+    /// 当前正在执行的线程
+    current: Option<Arc<Thread>>,
+    /// 调度循环 [`run`] 的 `TaskContext`，线程让出处理器时切换至此
+    idle_context: TaskContext,
+}
+
+/// 包装 [`Processor`] 以放在 `static` 中
+struct ProcessorCell(UnsafeCell<Processor>);
+
+unsafe impl Sync for ProcessorCell {}
+
+lazy_static! {
+    /// 所有 hart 的调度状态
+    static ref PROCESSORS: Vec<ProcessorCell> = (0..MAX_HARTS)
+        .map(|_| {
+            ProcessorCell(UnsafeCell::new(Processor {
+                current: None,
+                idle_context: TaskContext::zero(),
+            }))
+        })
+        .collect();
+}
+
+/// 正在等待线程的 hart，第 i 位表示 hart i
+static IDLE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 获取当前 hart 的调度状态，调用者需要关闭中断
+fn processor() -> &'static mut Processor {
+    unsafe { &mut *PROCESSORS[hart_id()].0.get() }
+}
+
+/// 当前正在执行的线程
+///
+/// 只能在线程中调用
+pub fn current_thread() -> Arc<Thread> {
+    try_current_thread().expect("no thread is running on this processor")
+}
+
+/// 当前正在执行的线程，在调度循环中则为 `None`
+pub fn try_current_thread() -> Option<Arc<Thread>> {
+    without_interrupts(|| processor().current.clone())
+}
+
+/// 当前线程所属的进程
+pub fn current_process() -> Arc<Process> {
+    current_thread().process.clone()
+}
+
+/// 将一个线程加入调度队列
+pub fn add_thread(thread: Arc<Thread>) {
+    thread.inner().status = ThreadStatus::Ready;
+    push_ready(thread);
+}
+
+/// 将就绪的线程放入调度队列，并唤醒一个空闲的 hart
+fn push_ready(thread: Arc<Thread>) {
+    SCHEDULER.lock().add_thread(thread);
+    let idle = IDLE_HARTS.load(Ordering::SeqCst);
+    if idle != 0 {
+        // 只唤醒编号最小的一个
+        ipi::send(idle & idle.wrapping_neg());
+    }
+}
+
+/// 调度循环，不断取出就绪的线程执行
+///
+/// 线程让出处理器后会回到这里，根据其状态决定是否放回调度队列
+pub fn run() -> ! {
+    // 调度循环只在等待时打开中断
+    unsafe { sstatus::clear_sie() };
+    let processor = processor();
+    let hart_bit = 1 << hart_id();
+    let mut idle = false;
+    loop {
+        let next = SCHEDULER.lock().get_next();
+        if let Some(thread) = next {
+            if idle {
+                // 空闲期间没有预约时间片，重新开始计算
+                IDLE_HARTS.fetch_and(!hart_bit, Ordering::SeqCst);
+                timer::leave_idle();
+                idle = false;
+            }
+            // 切换地址空间
+            thread.process.inner().memory_set.activate();
+            let next_context = {
+                let mut inner = thread.inner();
+                inner.status = ThreadStatus::Running;
+                &inner.task_context as *const TaskContext
+            };
+            processor.current = Some(thread);
+            unsafe { __switch(&mut processor.idle_context, next_context) };
+
+            // 线程让出了处理器
+            let previous = processor.current.take().unwrap();
+            let mut inner = previous.inner();
+            let status = inner.status;
+            match status {
+                // 主动让出或被抢占，或者在切换前就已经被唤醒
+                ThreadStatus::Running => {
+                    inner.status = ThreadStatus::Ready;
+                    drop(inner);
+                    push_ready(previous);
+                }
+                // 进入睡眠，等待 [`wake_thread`]
+                ThreadStatus::Blocking => inner.status = ThreadStatus::Sleeping,
+                // 线程结束，随 `previous` 一同释放
+                ThreadStatus::Zombie => {}
+                status => panic!("unexpected thread status {:?} after switch", status),
+            }
+        } else if !idle {
+            // 没有就绪的线程，只为定时器预约时钟中断。
+            // 先登记为空闲再检查一次调度队列，此后加入的线程一定会通过 IPI 唤醒这里
+            IDLE_HARTS.fetch_or(hart_bit, Ordering::SeqCst);
+            timer::enter_idle();
+            idle = true;
+        } else {
+            // 打开中断并等待
+            unsafe {
+                sstatus::set_sie();
+                llvm_asm!("wfi" :::: "volatile");
+                sstatus::clear_sie();
+            }
+        }
+    }
+}
+
+/// 从当前线程切换回调度循环
+///
+/// 线程被重新调度时从这里返回
+fn schedule() {
+    // 切换时关闭中断，切换回来后恢复。线程可能在另一个 hart 上被重新调度
+    let sie = sstatus::read().sie();
+    unsafe { sstatus::clear_sie() };
+    let processor = processor();
+    let current_context = {
+        let thread = processor.current.as_ref().unwrap();
+        &mut thread.inner().task_context as *mut TaskContext
+    };
+    unsafe { __switch(current_context, &processor.idle_context) };
+    if sie {
+        unsafe { sstatus::set_sie() };
+    }
+}
+
+/// 让出处理器，当前线程回到调度队列
+///
+/// 如果没有正在执行的线程（调度循环被中断）则什么也不做
+pub fn yield_current_thread() {
+    if without_interrupts(|| processor().current.is_some()) {
+        schedule();
+    }
+}
+
+/// 当前线程进入睡眠，直到被 [`wake_thread`] 唤醒
+///
+/// 调用者应当先将线程登记到某个等待队列中
+pub fn sleep_current_thread() {
+    current_thread().inner().status = ThreadStatus::Blocking;
+    schedule();
+}
+
+/// 唤醒一个线程
+///
+/// 如果线程还没有切换出去，则取消其睡眠
+pub fn wake_thread(thread: Arc<Thread>) {
+    let mut inner = thread.inner();
+    let status = inner.status;
+    match status {
+        ThreadStatus::Blocking => inner.status = ThreadStatus::Running,
+        ThreadStatus::Sleeping => {
+            inner.status = ThreadStatus::Ready;
+            drop(inner);
+            push_ready(thread);
+        }
+        _ => {}
+    }
+}
+
+/// 结束当前线程，不再返回
+pub fn exit_current_thread() -> ! {
+    unsafe { sstatus::clear_sie() };
+    current_thread().inner().status = ThreadStatus::Zombie;
+    schedule();
+    unreachable!()
+}
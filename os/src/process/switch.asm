@@ -0,0 +1,41 @@
+# 线程切换
+#
+# __switch(current: *mut TaskContext, next: *const TaskContext)
+# 按照函数调用规则，只需要保存 ra、sp 和 s0 至 s11，其余寄存器由调用者保存
+# 切换之后从 next 所保存的 ra 处继续执行，对新线程而言即 __restore
+
+    .section .text
+    .globl __switch
+__switch:
+    # 保存当前线程的 TaskContext
+    sd      ra, 0(a0)
+    sd      sp, 8(a0)
+    sd      s0, 16(a0)
+    sd      s1, 24(a0)
+    sd      s2, 32(a0)
+    sd      s3, 40(a0)
+    sd      s4, 48(a0)
+    sd      s5, 56(a0)
+    sd      s6, 64(a0)
+    sd      s7, 72(a0)
+    sd      s8, 80(a0)
+    sd      s9, 88(a0)
+    sd      s10, 96(a0)
+    sd      s11, 104(a0)
+
+    # 恢复下一个线程的 TaskContext
+    ld      ra, 0(a1)
+    ld      sp, 8(a1)
+    ld      s0, 16(a1)
+    ld      s1, 24(a1)
+    ld      s2, 32(a1)
+    ld      s3, 40(a1)
+    ld      s4, 48(a1)
+    ld      s5, 56(a1)
+    ld      s6, 64(a1)
+    ld      s7, 72(a1)
+    ld      s8, 80(a1)
+    ld      s9, 88(a1)
+    ld      s10, 96(a1)
+    ld      s11, 104(a1)
+    ret
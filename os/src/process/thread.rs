@@ -0,0 +1,155 @@
+//! 线程 [`Thread`]
+
+use super::*;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 线程 ID 使用 `usize` 表示
+pub type ThreadID = usize;
+
+/// 线程 ID 计数器
+static THREAD_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 线程切换时保存的寄存器，由 `switch.asm` 中的 `__switch` 读写
+///
+/// 按照函数调用规则，只需要保存 `ra`、`sp` 和 `s0` 至 `s11`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaskContext {
+    ra: usize,
+    sp: usize,
+    s: [usize; 12],
+}
+
+impl TaskContext {
+    /// 空的 `TaskContext`，用于调度循环
+    pub const fn zero() -> Self {
+        Self {
+            ra: 0,
+            sp: 0,
+            s: [0; 12],
+        }
+    }
+
+    /// 切换后通过 `__restore` 从 `context` 开始执行
+    pub fn goto_restore(context: *mut Context) -> Self {
+        extern "C" {
+            fn __restore();
+        }
+        Self {
+            ra: __restore as usize,
+            sp: context as usize,
+            s: [0; 12],
+        }
+    }
+}
+
+/// 线程的状态
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ThreadStatus {
+    /// 在调度队列中等待执行
+    Ready,
+    /// 正在执行
+    Running,
+    /// 已经决定睡眠，但尚未切换出去
+    Blocking,
+    /// 睡眠中，需要被唤醒才能继续执行
+    Sleeping,
+    /// 已经结束
+    Zombie,
+}
+
+/// 线程的信息
+pub struct Thread {
+    /// 线程 ID
+    pub id: ThreadID,
+    /// 所属的进程
+    pub process: Arc<Process>,
+    /// 线程的内核栈
+    pub kernel_stack: KernelStack,
+    /// 用 [`Lock`] 包装一些可变的变量，调度时会在中断处理中访问
+    pub inner: Lock<ThreadInner>,
+}
+
+/// 线程中需要可变的部分
+pub struct ThreadInner {
+    /// 线程状态
+    pub status: ThreadStatus,
+    /// 线程切换时保存的寄存器
+    pub task_context: TaskContext,
+}
+
+impl Thread {
+    /// 创建一个线程，从 `context` 开始执行
+    ///
+    /// 用户线程的 `context` 会保存在内核栈栈顶，与从用户态进入中断时的位置一致
+    pub fn new(process: Arc<Process>, context: Context) -> Arc<Thread> {
+        let kernel_stack = KernelStack::new();
+        let context_ptr = kernel_stack.push_context(context);
+        Arc::new(Thread {
+            id: THREAD_COUNTER.fetch_add(1, Ordering::Relaxed),
+            process,
+            kernel_stack,
+            inner: Lock::new(ThreadInner {
+                status: ThreadStatus::Ready,
+                task_context: TaskContext::goto_restore(context_ptr),
+            }),
+        })
+    }
+
+    /// 创建一个内核线程，执行 `entry_point(arguments)`
+    ///
+    /// 函数返回后线程结束
+    pub fn new_kernel(entry_point: usize, arguments: Option<&[usize]>) -> Arc<Thread> {
+        let kernel_stack = KernelStack::new();
+        // 内核线程的栈就是内核栈本身，Context 恢复之后其空间可以继续被栈使用
+        let stack_top = kernel_stack.context_ptr() as usize;
+        let mut context = Context::new(stack_top, entry_point, arguments, false);
+        context.x[1] = kernel_thread_exit as usize;
+        let context_ptr = kernel_stack.push_context(context);
+        Arc::new(Thread {
+            id: THREAD_COUNTER.fetch_add(1, Ordering::Relaxed),
+            process: KERNEL_PROCESS.clone(),
+            kernel_stack,
+            inner: Lock::new(ThreadInner {
+                status: ThreadStatus::Ready,
+                task_context: TaskContext::goto_restore(context_ptr),
+            }),
+        })
+    }
+
+    /// 上锁并获得可变部分的引用
+    pub fn inner(&self) -> LockGuard<ThreadInner> {
+        self.inner.lock()
+    }
+
+    /// 用户线程从用户态进入中断时保存的 [`Context`]
+    pub fn trap_context(&self) -> &'static mut Context {
+        unsafe { &mut *self.kernel_stack.context_ptr() }
+    }
+}
+
+/// 内核线程的入口函数返回后跳转至此，结束线程
+fn kernel_thread_exit() -> ! {
+    exit_current_thread()
+}
+
+/// 通过线程 ID 来判等
+impl PartialEq for Thread {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Thread {}
+
+/// 打印线程除了父进程以外的信息
+impl core::fmt::Debug for Thread {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter
+            .debug_struct("Thread")
+            .field("thread_id", &self.id)
+            .field("pid", &self.process.pid)
+            .field("status", &self.inner().status)
+            .finish()
+    }
+}
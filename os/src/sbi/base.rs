@@ -0,0 +1,52 @@
+//! Base 扩展，所有 SBI v0.2 之后的实现都支持
+
+use super::{sbi_call, SbiResult};
+
+pub const EXTENSION: usize = 0x10;
+
+const GET_SPEC_VERSION: usize = 0;
+const GET_IMPL_ID: usize = 1;
+const GET_IMPL_VERSION: usize = 2;
+const PROBE_EXTENSION: usize = 3;
+const GET_MVENDORID: usize = 4;
+const GET_MARCHID: usize = 5;
+const GET_MIMPID: usize = 6;
+
+fn call(function: usize, arg0: usize) -> SbiResult {
+    sbi_call(EXTENSION, function, arg0, 0, 0, 0, 0).into_result()
+}
+
+/// 规范版本，第 24 至 30 位为主版本号，低 24 位为次版本号
+pub fn spec_version() -> SbiResult {
+    call(GET_SPEC_VERSION, 0)
+}
+
+/// 实现的编号，例如 OpenSBI 为 1，RustSBI 为 4
+pub fn impl_id() -> SbiResult {
+    call(GET_IMPL_ID, 0)
+}
+
+/// 实现的版本
+pub fn impl_version() -> SbiResult {
+    call(GET_IMPL_VERSION, 0)
+}
+
+/// 是否支持扩展 `extension`
+pub fn probe_extension(extension: usize) -> SbiResult<bool> {
+    call(PROBE_EXTENSION, extension).map(|value| value != 0)
+}
+
+/// `mvendorid` 寄存器的值
+pub fn mvendorid() -> SbiResult {
+    call(GET_MVENDORID, 0)
+}
+
+/// `marchid` 寄存器的值
+pub fn marchid() -> SbiResult {
+    call(GET_MARCHID, 0)
+}
+
+/// `mimpid` 寄存器的值
+pub fn mimpid() -> SbiResult {
+    call(GET_MIMPID, 0)
+}
@@ -0,0 +1,49 @@
+//! HSM（Hart State Management）扩展，启动和停止 hart
+
+use super::{sbi_call, SbiResult};
+
+pub const EXTENSION: usize = 0x48_534D;
+
+const HART_START: usize = 0;
+const HART_STOP: usize = 1;
+const HART_GET_STATUS: usize = 2;
+
+/// hart 的状态
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HartStatus {
+    Started,
+    Stopped,
+    StartPending,
+    StopPending,
+    /// 规范中没有定义的状态
+    Unknown(usize),
+}
+
+/// 以 S 态启动 `hart_id`，从物理地址 `start_address` 开始执行
+///
+/// 启动后 `a0` 为 hart 编号，`a1` 为 `opaque`，`satp` 为 0
+pub fn hart_start(hart_id: usize, start_address: usize, opaque: usize) -> SbiResult<()> {
+    sbi_call(EXTENSION, HART_START, hart_id, start_address, opaque, 0, 0)
+        .into_result()
+        .map(|_| ())
+}
+
+/// 停止当前 hart，成功时不会返回
+pub fn hart_stop() -> SbiResult<()> {
+    sbi_call(EXTENSION, HART_STOP, 0, 0, 0, 0, 0)
+        .into_result()
+        .map(|_| ())
+}
+
+/// hart 的状态
+pub fn hart_get_status(hart_id: usize) -> SbiResult<HartStatus> {
+    sbi_call(EXTENSION, HART_GET_STATUS, hart_id, 0, 0, 0, 0)
+        .into_result()
+        .map(|status| match status {
+            0 => HartStatus::Started,
+            1 => HartStatus::Stopped,
+            2 => HartStatus::StartPending,
+            3 => HartStatus::StopPending,
+            status => HartStatus::Unknown(status),
+        })
+}
@@ -0,0 +1,16 @@
+//! IPI 扩展
+
+use super::{sbi_call, SbiResult};
+
+pub const EXTENSION: usize = 0x73_5049;
+
+const SEND_IPI: usize = 0;
+
+/// 向 hart 发送核间中断
+///
+/// `hart_mask` 的第 i 位表示 hart `hart_mask_base + i`；`hart_mask_base` 为 -1 时表示所有 hart
+pub fn send_ipi(hart_mask: usize, hart_mask_base: usize) -> SbiResult<()> {
+    sbi_call(EXTENSION, SEND_IPI, hart_mask, hart_mask_base, 0, 0, 0)
+        .into_result()
+        .map(|_| ())
+}
@@ -1,4 +1,6 @@
-#![allow(unused)]
+//! SBI v0.1 的调用
+//!
+//! 每个功能占用一个扩展号，返回值在 `a0` 中
 
 const SBI_SET_TIMER: usize = 0;
 const SBI_CONSOLE_PUTCHAR: usize = 1;
@@ -10,7 +12,6 @@ const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SBI_SHUTDOWN: usize = 8;
 
-
 #[inline(always)]
 fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     let ret: usize;
@@ -45,4 +46,24 @@ pub fn shutdown() -> ! {
 
 pub fn set_timer(time: usize) {
     sbi_call(SBI_SET_TIMER, time, 0, 0);
-}
\ No newline at end of file
+}
+
+/// 清除当前 hart 的核间中断
+pub fn clear_ipi() {
+    sbi_call(SBI_CLEAR_IPI, 0, 0, 0);
+}
+
+/// 向 `hart_mask` 中的 hart 发送核间中断，传入的是掩码的地址
+pub fn send_ipi(hart_mask: &usize) {
+    sbi_call(SBI_SEND_IPI, hart_mask as *const usize as usize, 0, 0);
+}
+
+/// 在 `hart_mask` 中的 hart 上执行 `fence.i`
+pub fn remote_fence_i(hart_mask: &usize) {
+    sbi_call(SBI_REMOTE_FENCE_I, hart_mask as *const usize as usize, 0, 0);
+}
+
+/// 在 `hart_mask` 中的 hart 上执行 `sfence.vma`
+pub fn remote_sfence_vma(hart_mask: &usize, start: usize, size: usize) {
+    sbi_call(SBI_REMOTE_SFENCE_VMA, hart_mask as *const usize as usize, start, size);
+}
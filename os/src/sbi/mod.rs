@@ -0,0 +1,233 @@
+//! 调用 SBI 提供的服务
+//!
+//! SBI v0.2 之后每个功能属于一个扩展，扩展号在 `a7` 中，功能号在 `a6` 中，
+//! 返回错误码和返回值 [`SbiRet`]。本模块包括：
+//! - [`base`]：规范版本、实现信息和扩展探测
+//! - [`time`]、[`ipi`]、[`rfence`]、[`hsm`]、[`srst`]：各个扩展
+//! - [`legacy`]：v0.1 的调用，每个功能占用一个扩展号
+//!
+//! 启动时 [`init`] 探测各个扩展，之后本模块顶层的函数会优先使用新的扩展，
+//! 不支持时退回到 v0.1 的调用
+
+#![allow(unused)]
+
+pub mod base;
+pub mod hsm;
+pub mod ipi;
+pub mod legacy;
+pub mod rfence;
+pub mod srst;
+pub mod time;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub use legacy::{console_getchar, console_putchar};
+pub use srst::{ResetReason, ResetType};
+
+/// SBI 调用的错误
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SbiError {
+    Failed,
+    NotSupported,
+    InvalidParam,
+    Denied,
+    InvalidAddress,
+    AlreadyAvailable,
+    AlreadyStarted,
+    AlreadyStopped,
+    /// 规范中没有定义的错误码
+    Unknown(isize),
+}
+
+impl SbiError {
+    fn from_code(code: isize) -> Self {
+        match code {
+            -1 => SbiError::Failed,
+            -2 => SbiError::NotSupported,
+            -3 => SbiError::InvalidParam,
+            -4 => SbiError::Denied,
+            -5 => SbiError::InvalidAddress,
+            -6 => SbiError::AlreadyAvailable,
+            -7 => SbiError::AlreadyStarted,
+            -8 => SbiError::AlreadyStopped,
+            code => SbiError::Unknown(code),
+        }
+    }
+}
+
+/// SBI 调用的结果
+pub type SbiResult<T = usize> = Result<T, SbiError>;
+
+/// SBI v0.2 之后的调用返回的错误码和返回值
+#[derive(Clone, Copy, Debug)]
+pub struct SbiRet {
+    pub error: isize,
+    pub value: usize,
+}
+
+impl SbiRet {
+    /// 错误码为 0 时返回 `Ok(value)`
+    pub fn into_result(self) -> SbiResult {
+        match self.error {
+            0 => Ok(self.value),
+            code => Err(SbiError::from_code(code)),
+        }
+    }
+}
+
+/// 以 SBI v0.2 之后的方式调用
+#[inline(always)]
+fn sbi_call(
+    extension: usize,
+    function: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SbiRet {
+    let error: isize;
+    let value: usize;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (error), "={x11}" (value)
+            : "{x10}" (arg0), "{x11}" (arg1), "{x12}" (arg2), "{x13}" (arg3), "{x14}" (arg4),
+              "{x16}" (function), "{x17}" (extension)
+            : "memory"
+            : "volatile");
+    }
+    SbiRet { error, value }
+}
+
+/// 启动时探测到的扩展
+static HAS_TIME: AtomicBool = AtomicBool::new(false);
+static HAS_IPI: AtomicBool = AtomicBool::new(false);
+static HAS_RFENCE: AtomicBool = AtomicBool::new(false);
+static HAS_HSM: AtomicBool = AtomicBool::new(false);
+static HAS_SRST: AtomicBool = AtomicBool::new(false);
+
+/// 探测 SBI 的版本和各个扩展
+///
+/// 只支持 v0.1 的实现没有 Base 扩展，此时所有功能都使用 v0.1 的调用
+pub fn init() {
+    let version = match base::spec_version() {
+        Ok(version) => version,
+        Err(_) => {
+            info!("SBI v0.1, using legacy calls");
+            return;
+        }
+    };
+    let probe = |extension| base::probe_extension(extension).unwrap_or(false);
+    HAS_TIME.store(probe(time::EXTENSION), Ordering::Relaxed);
+    HAS_IPI.store(probe(ipi::EXTENSION), Ordering::Relaxed);
+    HAS_RFENCE.store(probe(rfence::EXTENSION), Ordering::Relaxed);
+    HAS_HSM.store(probe(hsm::EXTENSION), Ordering::Relaxed);
+    HAS_SRST.store(probe(srst::EXTENSION), Ordering::Relaxed);
+    info!(
+        "SBI v{}.{}, implementation {} v0x{:x}, TIME {} IPI {} RFENCE {} HSM {} SRST {}",
+        version >> 24,
+        version & 0xff_ffff,
+        base::impl_id().unwrap_or(usize::MAX),
+        base::impl_version().unwrap_or(0),
+        has_time(),
+        has_ipi(),
+        has_rfence(),
+        has_hsm(),
+        has_srst(),
+    );
+}
+
+pub fn has_time() -> bool {
+    HAS_TIME.load(Ordering::Relaxed)
+}
+
+pub fn has_ipi() -> bool {
+    HAS_IPI.load(Ordering::Relaxed)
+}
+
+pub fn has_rfence() -> bool {
+    HAS_RFENCE.load(Ordering::Relaxed)
+}
+
+pub fn has_hsm() -> bool {
+    HAS_HSM.load(Ordering::Relaxed)
+}
+
+pub fn has_srst() -> bool {
+    HAS_SRST.load(Ordering::Relaxed)
+}
+
+/// 设置下一次时钟中断的时间
+pub fn set_timer(time: usize) {
+    if has_time() {
+        time::set_timer(time as u64).unwrap();
+    } else {
+        legacy::set_timer(time);
+    }
+}
+
+/// 向 `hart_mask` 中的 hart 发送核间中断，`hart_mask` 的第 i 位表示 hart i
+pub fn send_ipi(hart_mask: usize) {
+    if has_ipi() {
+        ipi::send_ipi(hart_mask, 0).unwrap();
+    } else {
+        legacy::send_ipi(&hart_mask);
+    }
+}
+
+/// 在 `hart_mask` 中的 hart 上执行 `sfence.vma`，`size` 为 0 表示刷新全部
+pub fn remote_sfence_vma(hart_mask: usize, start: usize, size: usize) {
+    if has_rfence() {
+        rfence::remote_sfence_vma(hart_mask, 0, start, size).unwrap();
+    } else {
+        legacy::remote_sfence_vma(&hart_mask, start, size);
+    }
+}
+
+/// 在 `hart_mask` 中的 hart 上执行 `fence.i`
+pub fn remote_fence_i(hart_mask: usize) {
+    if has_rfence() {
+        rfence::remote_fence_i(hart_mask, 0).unwrap();
+    } else {
+        legacy::remote_fence_i(&hart_mask);
+    }
+}
+
+/// 通过 System Reset 扩展关机或重启，成功时不会返回
+fn system_reset(reset_type: ResetType, reason: ResetReason) {
+    if has_srst() {
+        let _ = srst::system_reset(reset_type, reason);
+    }
+}
+
+/// 正常关机
+pub fn shutdown() -> ! {
+    system_reset(ResetType::Shutdown, ResetReason::NoReason);
+    // 不支持 System Reset 时使用 v0.1 的关机调用
+    legacy::shutdown()
+}
+
+/// 因为错误而关机，QEMU 会以非 0 的 `code` 退出
+pub fn shutdown_failure(code: u16) -> ! {
+    // SBI 无法传递退出码，QEMU 上优先使用 SiFive test 设备
+    if crate::drivers::sifive_test::is_present() {
+        crate::drivers::sifive_test::exit_failure(code);
+    }
+    system_reset(ResetType::Shutdown, ResetReason::SystemFailure);
+    legacy::shutdown()
+}
+
+/// 重启，`warm` 表示只重置处理器而不重置整个系统
+pub fn reboot(warm: bool) -> ! {
+    let reset_type = if warm {
+        ResetType::WarmReboot
+    } else {
+        ResetType::ColdReboot
+    };
+    system_reset(reset_type, ResetReason::NoReason);
+    // v0.1 没有重启调用，QEMU 上使用 SiFive test 设备
+    if crate::drivers::sifive_test::is_present() {
+        crate::drivers::sifive_test::reset();
+    }
+    panic!("reboot is not supported")
+}
@@ -0,0 +1,43 @@
+//! RFENCE 扩展，在其他 hart 上执行 fence 指令
+//!
+//! hart 的选择方式与 [`send_ipi`](super::ipi::send_ipi) 相同
+
+use super::{sbi_call, SbiResult};
+
+pub const EXTENSION: usize = 0x5246_4E43;
+
+const REMOTE_FENCE_I: usize = 0;
+const REMOTE_SFENCE_VMA: usize = 1;
+const REMOTE_SFENCE_VMA_ASID: usize = 2;
+
+/// 执行 `fence.i`
+pub fn remote_fence_i(hart_mask: usize, hart_mask_base: usize) -> SbiResult<()> {
+    sbi_call(EXTENSION, REMOTE_FENCE_I, hart_mask, hart_mask_base, 0, 0, 0)
+        .into_result()
+        .map(|_| ())
+}
+
+/// 对 `[start, start + size)` 执行 `sfence.vma`，`start` 和 `size` 均为 0 时刷新全部
+pub fn remote_sfence_vma(
+    hart_mask: usize,
+    hart_mask_base: usize,
+    start: usize,
+    size: usize,
+) -> SbiResult<()> {
+    sbi_call(EXTENSION, REMOTE_SFENCE_VMA, hart_mask, hart_mask_base, start, size, 0)
+        .into_result()
+        .map(|_| ())
+}
+
+/// 对地址空间 `asid` 中的 `[start, start + size)` 执行 `sfence.vma`
+pub fn remote_sfence_vma_asid(
+    hart_mask: usize,
+    hart_mask_base: usize,
+    start: usize,
+    size: usize,
+    asid: usize,
+) -> SbiResult<()> {
+    sbi_call(EXTENSION, REMOTE_SFENCE_VMA_ASID, hart_mask, hart_mask_base, start, size, asid)
+        .into_result()
+        .map(|_| ())
+}
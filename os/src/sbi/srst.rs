@@ -0,0 +1,29 @@
+//! SRST（System Reset）扩展，关机和重启
+
+use super::{sbi_call, SbiResult};
+
+pub const EXTENSION: usize = 0x5352_5354;
+
+const SYSTEM_RESET: usize = 0;
+
+/// System Reset 的类型
+#[derive(Clone, Copy, Debug)]
+pub enum ResetType {
+    Shutdown = 0,
+    ColdReboot = 1,
+    WarmReboot = 2,
+}
+
+/// System Reset 的原因
+#[derive(Clone, Copy, Debug)]
+pub enum ResetReason {
+    NoReason = 0,
+    SystemFailure = 1,
+}
+
+/// 关机或重启，成功时不会返回
+pub fn system_reset(reset_type: ResetType, reason: ResetReason) -> SbiResult<()> {
+    sbi_call(EXTENSION, SYSTEM_RESET, reset_type as usize, reason as usize, 0, 0, 0)
+        .into_result()
+        .map(|_| ())
+}
@@ -0,0 +1,14 @@
+//! TIME 扩展
+
+use super::{sbi_call, SbiResult};
+
+pub const EXTENSION: usize = 0x5449_4D45;
+
+const SET_TIMER: usize = 0;
+
+/// 在 `time` 寄存器达到 `stime_value` 时产生时钟中断，并清除当前等待的时钟中断
+pub fn set_timer(stime_value: u64) -> SbiResult<()> {
+    sbi_call(EXTENSION, SET_TIMER, stime_value as usize, 0, 0, 0, 0)
+        .into_result()
+        .map(|_| ())
+}
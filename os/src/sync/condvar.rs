@@ -0,0 +1,45 @@
+//! 条件变量 [`Condvar`]
+
+use super::{LockGuard, MutexGuard, WaitQueue};
+
+/// 条件变量
+///
+/// 与 [`Mutex`](super::Mutex) 或 [`Lock`](super::Lock) 配合使用，
+/// 等待时原子地释放锁并睡眠，被唤醒后重新获取锁。被唤醒后应当重新检查条件
+#[derive(Default)]
+pub struct Condvar {
+    wait_queue: WaitQueue,
+}
+
+impl Condvar {
+    /// 创建一个条件变量
+    pub fn new() -> Self {
+        Self {
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// 释放 [`Mutex`](super::Mutex) 并睡眠，被唤醒后重新获取
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex;
+        self.wait_queue.sleep_with(guard);
+        mutex.lock()
+    }
+
+    /// 释放 [`Lock`](super::Lock) 并睡眠，被唤醒后重新获取
+    pub fn wait_lock<'a, T>(&self, guard: LockGuard<'a, T>) -> LockGuard<'a, T> {
+        let lock = LockGuard::source(&guard);
+        self.wait_queue.sleep_with(guard);
+        lock.lock()
+    }
+
+    /// 唤醒一个等待的线程
+    pub fn notify_one(&self) -> bool {
+        self.wait_queue.wake_one()
+    }
+
+    /// 唤醒所有等待的线程
+    pub fn notify_all(&self) {
+        self.wait_queue.wake_all();
+    }
+}
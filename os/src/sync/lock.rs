@@ -0,0 +1,110 @@
+//! 关闭中断的自旋锁 [`Lock`]
+
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use riscv::register::sstatus;
+use spin::{Mutex, MutexGuard};
+
+/// 在关闭中断的情况下执行 `f`，之后恢复原来的中断状态
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let sie = sstatus::read().sie();
+    unsafe { sstatus::clear_sie() };
+    let result = f();
+    if sie {
+        unsafe { sstatus::set_sie() };
+    }
+    result
+}
+
+/// 关闭中断的自旋锁
+///
+/// 获取锁时保存并清除 `sstatus.SIE`，释放时恢复，因此持有锁期间不会被时钟中断打断，
+/// 中断处理中再次获取同一个锁也不会死锁。
+/// 多个锁嵌套时应当按照获取的相反顺序释放
+#[derive(Default)]
+pub struct Lock<T>(Mutex<T>);
+
+/// 封装 [`MutexGuard`]，drop 时恢复 `sstatus.SIE`
+pub struct LockGuard<'a, T> {
+    guard: ManuallyDrop<MutexGuard<'a, T>>,
+    /// 获取锁之前 `sstatus.SIE` 的值
+    sie: bool,
+    /// 所属的锁，用于 [`Condvar`](super::Condvar) 重新获取
+    lock: &'a Lock<T>,
+}
+
+impl<T> Lock<T> {
+    /// 创建一个锁
+    pub const fn new(data: T) -> Self {
+        Self(Mutex::new(data))
+    }
+
+    /// 关闭中断并获取锁
+    pub fn lock(&self) -> LockGuard<'_, T> {
+        let sie = sstatus::read().sie();
+        unsafe { sstatus::clear_sie() };
+        LockGuard {
+            guard: ManuallyDrop::new(self.0.lock()),
+            sie,
+            lock: self,
+        }
+    }
+
+    /// 尝试获取锁，失败时恢复中断状态并返回 `None`
+    pub fn try_lock(&self) -> Option<LockGuard<'_, T>> {
+        let sie = sstatus::read().sie();
+        unsafe { sstatus::clear_sie() };
+        match self.0.try_lock() {
+            Some(guard) => Some(LockGuard {
+                guard: ManuallyDrop::new(guard),
+                sie,
+                lock: self,
+            }),
+            None => {
+                if sie {
+                    unsafe { sstatus::set_sie() };
+                }
+                None
+            }
+        }
+    }
+
+    /// 强制释放锁
+    ///
+    /// 只应在无法继续执行的情况下使用（例如 panic），此时持有锁的一方不会再访问数据
+    pub unsafe fn force_unlock(&self) {
+        self.0.force_unlock()
+    }
+}
+
+impl<'a, T> LockGuard<'a, T> {
+    /// 守卫所属的锁
+    ///
+    /// 写作关联函数而不是方法，避免与 `T` 的方法冲突
+    pub fn source(guard: &Self) -> &'a Lock<T> {
+        guard.lock
+    }
+}
+
+/// 释放锁后恢复 `sstatus.SIE`
+impl<'a, T> Drop for LockGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        if self.sie {
+            unsafe { sstatus::set_sie() };
+        }
+    }
+}
+
+impl<'a, T> Deref for LockGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        self.guard.deref()
+    }
+}
+
+impl<'a, T> DerefMut for LockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.guard.deref_mut()
+    }
+}
@@ -0,0 +1,17 @@
+//! 内核中的同步原语
+//!
+//! - [`Lock`]：关闭中断的自旋锁，可以在中断处理中使用，持有期间不会被抢占
+//! - [`Mutex`]、[`Semaphore`]、[`Condvar`]：基于 [`WaitQueue`]，等待时线程睡眠而不是自旋，
+//!   只能在线程中使用
+
+mod condvar;
+mod lock;
+mod mutex;
+mod semaphore;
+mod wait_queue;
+
+pub use condvar::Condvar;
+pub use lock::{without_interrupts, Lock, LockGuard};
+pub use mutex::{Mutex, MutexGuard};
+pub use semaphore::Semaphore;
+pub use wait_queue::WaitQueue;
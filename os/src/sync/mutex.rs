@@ -0,0 +1,90 @@
+//! 睡眠锁 [`Mutex`]
+
+use super::{Lock, WaitQueue};
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+/// 睡眠锁
+///
+/// 锁被占用时，线程进入 [`WaitQueue`] 睡眠而不是自旋，适合临界区较长或可能睡眠的场景。
+/// 不能在中断处理中使用
+pub struct Mutex<T> {
+    /// 是否已经被占用
+    locked: Lock<bool>,
+    /// 等待锁的线程
+    wait_queue: WaitQueue,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+/// [`Mutex`] 的守卫，drop 时释放锁并唤醒一个等待的线程
+pub struct MutexGuard<'a, T> {
+    pub(super) mutex: &'a Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    /// 创建一个睡眠锁
+    pub fn new(data: T) -> Self {
+        Self {
+            locked: Lock::new(false),
+            wait_queue: WaitQueue::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// 获取锁，锁被占用时睡眠等待
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        loop {
+            let mut locked = self.locked.lock();
+            if !*locked {
+                *locked = true;
+                return MutexGuard { mutex: self };
+            }
+            self.wait_queue.sleep_with(locked);
+        }
+    }
+
+    /// 尝试获取锁，锁被占用时返回 `None`
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let mut locked = self.locked.lock();
+        if *locked {
+            None
+        } else {
+            *locked = true;
+            Some(MutexGuard { mutex: self })
+        }
+    }
+
+    /// 释放锁
+    fn unlock(&self) {
+        *self.locked.lock() = false;
+        self.wait_queue.wake_one();
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
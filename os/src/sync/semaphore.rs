@@ -0,0 +1,52 @@
+//! 信号量 [`Semaphore`]
+
+use super::{Lock, WaitQueue};
+
+/// 计数信号量
+pub struct Semaphore {
+    /// 剩余的资源数量
+    count: Lock<isize>,
+    /// 等待资源的线程
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    /// 创建一个初始资源数量为 `count` 的信号量
+    pub fn new(count: isize) -> Self {
+        Self {
+            count: Lock::new(count),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// P 操作，资源不足时睡眠等待
+    pub fn acquire(&self) {
+        loop {
+            let mut count = self.count.lock();
+            if *count > 0 {
+                *count -= 1;
+                return;
+            }
+            self.wait_queue.sleep_with(count);
+        }
+    }
+
+    /// 尝试 P 操作，资源不足时返回 `false`
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.lock();
+        if *count > 0 {
+            *count -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// V 操作，唤醒一个等待的线程
+    ///
+    /// 不会睡眠，可以在中断处理中使用
+    pub fn release(&self) {
+        *self.count.lock() += 1;
+        self.wait_queue.wake_one();
+    }
+}
@@ -0,0 +1,63 @@
+//! 等待队列 [`WaitQueue`]
+
+use super::Lock;
+use crate::process::{current_thread, wake_thread, yield_current_thread, Thread, ThreadStatus};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// 等待某个事件的线程队列
+#[derive(Default)]
+pub struct WaitQueue {
+    queue: Lock<VecDeque<Arc<Thread>>>,
+}
+
+impl WaitQueue {
+    /// 创建一个空的等待队列
+    pub fn new() -> Self {
+        Self {
+            queue: Lock::new(VecDeque::new()),
+        }
+    }
+
+    /// 将当前线程加入队列，释放 `guard` 后睡眠，直到被唤醒
+    ///
+    /// 线程在释放 `guard` 之前就已经标记为即将睡眠，因此释放之后立即到来的唤醒也不会丢失。
+    /// 通常 `guard` 保护了线程所等待的条件，被唤醒后应当重新检查
+    pub fn sleep_with<G>(&self, guard: G) {
+        let thread = current_thread();
+        thread.inner().status = ThreadStatus::Blocking;
+        self.queue.lock().push_back(thread);
+        drop(guard);
+        yield_current_thread();
+    }
+
+    /// 将当前线程加入队列并睡眠
+    pub fn sleep(&self) {
+        self.sleep_with(());
+    }
+
+    /// 唤醒最早加入队列的一个线程，返回是否有线程被唤醒
+    pub fn wake_one(&self) -> bool {
+        let thread = self.queue.lock().pop_front();
+        match thread {
+            Some(thread) => {
+                wake_thread(thread);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 唤醒队列中的所有线程
+    pub fn wake_all(&self) {
+        let threads = core::mem::take(&mut *self.queue.lock());
+        for thread in threads {
+            wake_thread(thread);
+        }
+    }
+
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().is_empty()
+    }
+}
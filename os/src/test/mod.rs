@@ -1,10 +1,59 @@
-pub fn break_test() {
+//! 内核测试
+//!
+//! 使用 `#![feature(custom_test_frameworks)]`，以 `#[test_case]` 标记的函数会在 `cargo test` 时
+//! 由 [`runner`] 依次执行。测试在一个内核线程中运行，因此可以睡眠、创建其他线程。
+//! 全部通过后以 0 退出 QEMU；任何一个测试 panic 都会以非 0 退出
+
+use crate::drivers::block::{BlockDevice, BlockResult, BLOCK_SIZE};
+use crate::drivers::sifive_test;
+use crate::process::{add_thread, loader, Thread};
+use crate::sync::Lock;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 可以运行的测试
+pub trait Testable {
+    fn run(&self);
+}
+
+/// 运行测试函数，并打印其名称和结果
+impl<T: Fn()> Testable for T {
+    fn run(&self) {
+        println!("test {} ...", core::any::type_name::<T>());
+        self();
+        println!("test {} ... \x1b[32mok\x1b[0m", core::any::type_name::<T>());
+    }
+}
+
+/// 测试框架的入口，依次运行所有测试后退出 QEMU
+pub fn runner(tests: &[&dyn Testable]) {
+    println!("running {} tests", tests.len());
+    for test in tests {
+        test.run();
+    }
+    println!("test result: \x1b[32mok\x1b[0m. {} passed", tests.len());
+    sifive_test::exit_success();
+}
+
+/// 创建运行测试的内核线程，之后由调度循环执行
+pub fn start() {
+    fn run_tests() {
+        crate::fs::init();
+        crate::test_main();
+    }
+    loader::init();
+    add_thread(Thread::new_kernel(run_tests as usize, None));
+}
+
+#[test_case]
+fn break_test() {
     unsafe {
         llvm_asm!("ebreak"::::"volatile");
     };
 }
 
-pub fn memory_test() {
+#[test_case]
+fn memory_test() {
     // 动态内存分配测试
     use alloc::boxed::Box;
     use alloc::vec::Vec;
@@ -24,22 +73,674 @@ pub fn memory_test() {
     println!("Heap test passes")
 }
 
-pub fn kernel_address_test() {
+#[test_case]
+fn kernel_address_test() {
     println!("kernel_address: 0x{:x}", (*crate::memory::config::KERNEL_END_ADDRESS).0);
 }
 
-pub fn physical_memory_test() {
+#[test_case]
+fn physical_memory_test() {
     use crate::memory;
     for _ in 0..2 {
-        let frame_0 = match memory::frame::FRAME_ALLOCATOR.lock().alloc() {
+        let frame_0 = match memory::frame::alloc() {
            Result::Ok(frame_tracker) => frame_tracker,
             Result::Err(err) => panic!("{}", err)
         };
 
-        let frame_1 = match memory::frame::FRAME_ALLOCATOR.lock().alloc() {
+        let frame_1 = match memory::frame::alloc() {
             Result::Ok(frame_tracker) => frame_tracker,
             Result::Err(err) => panic!("{}", err)
         };
         println!("{} and {}", frame_0.address(), frame_1.address())
     }
-}
\ No newline at end of file
+}
+
+/// 测试每个 hart 的帧缓存：刚释放的帧会被立即重新分配
+#[test_case]
+fn frame_cache_test() {
+    use crate::memory::frame;
+    use crate::sync::without_interrupts;
+
+    // 关中断以保证两次分配在同一个 hart 上
+    without_interrupts(|| {
+        let before = frame::stats();
+        let first = frame::alloc().unwrap();
+        let address = first.address();
+        drop(first);
+        let second = frame::alloc().unwrap();
+        assert_eq!(second.address(), address);
+        let after = frame::stats();
+        assert!(after.hits > before.hits);
+        assert!(after.free <= after.total && after.cached <= after.free);
+    });
+}
+
+#[test_case]
+fn user_access_test() {
+    use crate::kernel::errno::EFAULT;
+    use crate::memory::address::VirtualAddress;
+    use crate::memory::mapping::{Flags, MapType, MemorySet, Segment};
+    use crate::memory::range::Range;
+    use crate::memory::user::{copy_from_user, copy_to_user, strncpy_from_user};
+
+    // 在 MemorySet 中登记用户段，但不激活，此时实际访问会触发缺页异常
+    let mut memory_set = MemorySet::new_kernel().unwrap();
+    memory_set
+        .add_segment(
+            Segment {
+                map_type: MapType::Framed,
+                range: Range::from(0x1000usize..0x2000usize),
+                flags: Flags::USER | Flags::READABLE | Flags::WRITABLE,
+            },
+            None,
+        )
+        .unwrap();
+
+    let mut buffer = [0u8; 16];
+    // 未登记的地址，在检查时失败
+    assert_eq!(copy_from_user(&memory_set, &mut buffer, VirtualAddress(0x3000)), Err(EFAULT));
+    // 跨越用户段结尾
+    assert_eq!(copy_to_user(&memory_set, VirtualAddress(0x1ff8), &buffer), Err(EFAULT));
+    // 内核地址不带 USER 标志
+    let kernel_address = VirtualAddress::from(buffer.as_ptr());
+    assert_eq!(copy_from_user(&memory_set, &mut buffer, kernel_address), Err(EFAULT));
+    // 通过检查但未映射，由异常表修复
+    assert_eq!(copy_from_user(&memory_set, &mut buffer, VirtualAddress(0x1000)), Err(EFAULT));
+    assert_eq!(strncpy_from_user(&memory_set, &mut buffer, VirtualAddress(0x1000)), Err(EFAULT));
+    println!("User access test passes");
+}
+
+/// 同步原语测试中共享的状态
+mod sync_state {
+    use crate::sync::{Condvar, Mutex, Semaphore};
+    use lazy_static::*;
+
+    pub const WORKERS: usize = 4;
+    pub const ROUNDS: usize = 100;
+
+    lazy_static! {
+        pub static ref COUNTER: Mutex<usize> = Mutex::new(0);
+        pub static ref FINISHED: Semaphore = Semaphore::new(0);
+        pub static ref READY: Mutex<bool> = Mutex::new(false);
+        pub static ref READY_CONDVAR: Condvar = Condvar::new();
+    }
+}
+
+/// 创建若干内核线程，测试 [`Mutex`](crate::sync::Mutex)、
+/// [`Semaphore`](crate::sync::Semaphore) 和 [`Condvar`](crate::sync::Condvar)
+#[test_case]
+fn sync_test() {
+    use sync_state::*;
+
+    fn worker(id: usize) {
+        // 等待测试线程发出开始的信号
+        let mut ready = READY.lock();
+        while !*ready {
+            ready = READY_CONDVAR.wait(ready);
+        }
+        drop(ready);
+
+        for _ in 0..ROUNDS {
+            let mut counter = COUNTER.lock();
+            let value = *counter;
+            // 持有锁时让出处理器，其他线程只能睡眠等待
+            crate::process::yield_current_thread();
+            *counter = value + 1;
+        }
+        println!("sync worker {} finished", id);
+        FINISHED.release();
+    }
+
+    for id in 0..WORKERS {
+        add_thread(Thread::new_kernel(worker as usize, Some(&[id])));
+    }
+    *READY.lock() = true;
+    READY_CONDVAR.notify_all();
+    for _ in 0..WORKERS {
+        FINISHED.acquire();
+    }
+    assert_eq!(*COUNTER.lock(), WORKERS * ROUNDS);
+}
+
+/// 测试单调时钟、定时器回调和线程睡眠
+#[test_case]
+fn timer_test() {
+    use crate::interrupt::timer;
+    use core::time::Duration;
+
+    static FIRED: AtomicUsize = AtomicUsize::new(0);
+
+    let start = timer::now();
+    let periodic = timer::add_periodic(Duration::from_millis(5), || {
+        FIRED.fetch_add(1, Ordering::Relaxed);
+    });
+    timer::sleep(Duration::from_millis(50));
+    assert!(timer::now() - start >= Duration::from_millis(50));
+    assert!(timer::cancel(periodic));
+    assert!(FIRED.load(Ordering::Relaxed) >= 5);
+
+    // 在回调中取消自己，之后不再触发
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    static ID: AtomicUsize = AtomicUsize::new(usize::MAX);
+    static CANCELLED: AtomicUsize = AtomicUsize::new(0);
+    let id = timer::add_periodic(Duration::from_millis(2), || {
+        if COUNT.fetch_add(1, Ordering::SeqCst) + 1 == 3 {
+            let cancelled = timer::cancel(ID.load(Ordering::SeqCst));
+            CANCELLED.store(cancelled as usize + 1, Ordering::SeqCst);
+        }
+    });
+    ID.store(id, Ordering::SeqCst);
+    timer::sleep(Duration::from_millis(30));
+    assert_eq!(CANCELLED.load(Ordering::SeqCst), 2);
+    assert_eq!(COUNT.load(Ordering::SeqCst), 3);
+    assert!(!timer::cancel(id));
+}
+
+/// 测试在其他 hart 上执行函数并等待完成
+#[test_case]
+fn ipi_call_test() {
+    use crate::hart;
+    use crate::interrupt::ipi;
+
+    static CALLED: AtomicUsize = AtomicUsize::new(0);
+
+    let before = CALLED.load(Ordering::SeqCst);
+    ipi::call_others(
+        || {
+            CALLED.fetch_add(1, Ordering::SeqCst);
+        },
+        true,
+    );
+    assert_eq!(CALLED.load(Ordering::SeqCst) - before, hart::online_count() - 1);
+}
+
+/// 测试线程在内核态被时钟中断抢占、在其他 hart 上恢复后，`tp` 仍是所在 hart 的编号
+///
+/// S 态无法读取 `mhartid`，因此检查 `PROCESSORS[hart_id()]` 中正在执行的线程是否为自己：
+/// `tp` 过时的话会取到其他 hart 的调度状态
+#[test_case]
+fn migration_test() {
+    use crate::hart::{self, hart_id};
+    use crate::interrupt::timer;
+    use crate::process::current_thread;
+    use crate::sync::{without_interrupts, Semaphore};
+    use core::time::Duration;
+    use lazy_static::*;
+
+    /// 多于 hart 数的忙碌线程，共用一个调度队列，必然在 hart 之间迁移
+    const WORKERS: usize = 8;
+    static IDS: Lock<Vec<usize>> = Lock::new(Vec::new());
+    static MIGRATIONS: AtomicUsize = AtomicUsize::new(0);
+    lazy_static! {
+        static ref FINISHED: Semaphore = Semaphore::new(0);
+    }
+
+    fn worker(index: usize) {
+        let id = IDS.lock()[index];
+        let start = timer::now();
+        let mut last = None;
+        // 只忙等，不主动让出，所有切换都来自时钟中断
+        while timer::now() - start < Duration::from_millis(100) {
+            let (hart, current) = without_interrupts(|| (hart_id(), current_thread().id));
+            assert_eq!(current, id, "stale tp {} after preemption", hart);
+            assert!(hart::online_mask() & (1 << hart) != 0);
+            if last.map_or(false, |last| last != hart) {
+                MIGRATIONS.fetch_add(1, Ordering::Relaxed);
+            }
+            last = Some(hart);
+        }
+        FINISHED.release();
+    }
+
+    for index in 0..WORKERS {
+        let thread = Thread::new_kernel(worker as usize, Some(&[index]));
+        IDS.lock().push(thread.id);
+        add_thread(thread);
+    }
+    for _ in 0..WORKERS {
+        FINISHED.acquire();
+    }
+    assert!(hart::online_count() == 1 || MIGRATIONS.load(Ordering::Relaxed) > 0);
+}
+
+/// 测试设备树的查询：QEMU virt 平台上应当能找到串口和时基频率
+#[test_case]
+fn device_tree_test() {
+    use crate::drivers::devicetree;
+
+    let fdt = devicetree::tree().expect("no device tree");
+    let uart = fdt.find_compatible(&["ns16550a"]).expect("no UART in device tree");
+    assert_eq!(uart.base_name(), "uart");
+    assert!(uart.first_reg().is_some());
+    assert!(uart.interrupt().is_some());
+    let cpus = fdt.find_node("/cpus").expect("no /cpus node");
+    assert!(cpus.property("timebase-frequency").is_some());
+    assert!(cpus.children().any(|cpu| cpu.base_name() == "cpu"));
+}
+
+/// 测试 virtio-blk：通过块缓存写入磁盘镜像的最后一块，写回后直接从设备读出，最后恢复原来的内容
+#[test_case]
+fn block_device_test() {
+    use crate::drivers::block;
+
+    // `make test` 总是挂载磁盘镜像，没有块设备说明驱动初始化失败
+    let device = block::get(0).expect("no block device, run the tests with `make test`");
+    let block_id = device.num_blocks() - 1;
+    let mut original = [0u8; BLOCK_SIZE];
+    device.read_block(block_id, &mut original).unwrap();
+
+    let mut pattern = [0u8; BLOCK_SIZE];
+    for (i, byte) in pattern.iter_mut().enumerate() {
+        *byte = (i * 7 + 3) as u8;
+    }
+    device.write_block(block_id, &pattern).unwrap();
+    device.sync().unwrap();
+    // 绕过块缓存，从设备读出写回的内容
+    let mut read_back = [0u8; BLOCK_SIZE];
+    device.device().read_block(block_id, &mut read_back).unwrap();
+    assert!(read_back[..] == pattern[..]);
+
+    device.write_block(block_id, &original).unwrap();
+    device.sync().unwrap();
+    assert!(device.read_block(device.num_blocks(), &mut read_back).is_err());
+}
+
+/// 在内存中的块设备，供块缓存和文件系统的测试使用，记录写入次数
+struct RamDisk {
+    blocks: Lock<Vec<[u8; BLOCK_SIZE]>>,
+    writes: AtomicUsize,
+}
+
+impl RamDisk {
+    fn new(blocks: Vec<[u8; BLOCK_SIZE]>) -> Self {
+        RamDisk {
+            blocks: Lock::new(blocks),
+            writes: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn read_block(&self, block_id: usize, buffer: &mut [u8]) -> BlockResult {
+        buffer.copy_from_slice(&self.blocks.lock()[block_id]);
+        Ok(())
+    }
+
+    fn write_block(&self, block_id: usize, buffer: &[u8]) -> BlockResult {
+        self.blocks.lock()[block_id].copy_from_slice(buffer);
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> usize {
+        self.blocks.lock().len()
+    }
+}
+
+/// 测试块缓存的 LRU 替换和写回
+#[test_case]
+fn block_cache_test() {
+    use crate::drivers::block::BlockCache;
+    use alloc::{sync::Arc, vec};
+
+    let disk = Arc::new(RamDisk::new(vec![[0; BLOCK_SIZE]; 4]));
+    let cache = BlockCache::new(disk.clone(), 2);
+    let mut buffer = [0u8; BLOCK_SIZE];
+
+    cache.write_block(0, &[1; BLOCK_SIZE]).unwrap();
+    cache.write_block(1, &[2; BLOCK_SIZE]).unwrap();
+    assert_eq!(disk.writes.load(Ordering::SeqCst), 0);
+    // 读取块 0 使块 1 成为最久没有使用的块
+    cache.read_block(0, &mut buffer).unwrap();
+    assert_eq!(buffer[0], 1);
+    cache.write_block(2, &[3; BLOCK_SIZE]).unwrap();
+    assert_eq!(disk.writes.load(Ordering::SeqCst), 1);
+    assert_eq!(disk.blocks.lock()[1][0], 2);
+
+    cache.sync().unwrap();
+    assert_eq!(disk.writes.load(Ordering::SeqCst), 3);
+    assert_eq!(disk.blocks.lock()[2][0], 3);
+    let stats = cache.stats("test");
+    assert_eq!((stats.hits, stats.misses, stats.cached), (1, 3, 2));
+}
+
+/// 测试 SFS 的文件和目录操作，以及从根文件系统读取用户程序
+#[test_case]
+fn sfs_test() {
+    use crate::drivers::block::BlockCache;
+    use crate::fs::{self, FileSystem, FileType, SimpleFileSystem};
+    use crate::kernel::errno::{EEXIST, ENOENT, ENOTEMPTY};
+    use alloc::{borrow::Cow, format, sync::Arc, vec};
+
+    let disk = Arc::new(RamDisk::new(vec![[0; BLOCK_SIZE]; 2048]));
+    let sfs = SimpleFileSystem::format(Arc::new(BlockCache::new(disk.clone(), 16))).unwrap();
+    let root = sfs.root();
+    let dir = root.create("dir", FileType::Directory).unwrap();
+    assert_eq!(root.create("dir", FileType::Regular).err(), Some(EEXIST));
+    let file = dir.create("file", FileType::Regular).unwrap();
+    // 跨越直接索引和一级间接索引
+    let data: Vec<u8> = (0..40_000).map(|i| (i % 251) as u8).collect();
+    assert_eq!(file.write_at(0, &data).unwrap(), data.len());
+    sfs.sync().unwrap();
+
+    // 重新打开后内容不变
+    let sfs = SimpleFileSystem::open(Arc::new(BlockCache::new(disk, 16))).unwrap();
+    let dir = sfs.root().lookup("dir").unwrap();
+    let entry = dir.readdir(0).unwrap().unwrap();
+    assert_eq!((entry.name.as_str(), entry.file_type), ("file", FileType::Regular));
+    assert!(dir.readdir(1).unwrap().is_none());
+    let file = dir.lookup("file").unwrap();
+    assert_eq!(file.metadata().unwrap().size, data.len());
+    assert!(file.read_all().unwrap() == data);
+    file.resize(10).unwrap();
+    assert!(file.read_all().unwrap()[..] == data[..10]);
+
+    assert_eq!(sfs.root().unlink("dir").err(), Some(ENOTEMPTY));
+    dir.unlink("file").unwrap();
+    assert_eq!(dir.lookup("file").err(), Some(ENOENT));
+    sfs.root().unlink("dir").unwrap();
+
+    // `make test` 总是挂载 target/disk.img，用户程序从磁盘读取，而不是使用嵌入内核的副本
+    let (point, _) = fs::mounts()
+        .into_iter()
+        .find(|(_, name)| *name == "sfs")
+        .expect("no SFS mounted, run the tests with `make test`");
+    let path = format!("{}/initproc", point.trim_end_matches('/'));
+    let on_disk = fs::lookup(&path).unwrap().read_all().unwrap();
+    assert!(on_disk.starts_with(b"\x7fELF"));
+    match loader::load(&path).unwrap() {
+        Cow::Owned(elf_data) => assert!(elf_data == on_disk),
+        Cow::Borrowed(_) => panic!("{} is loaded from the kernel image", path),
+    }
+}
+
+/// 测试 FAT32 的长文件名、簇链分配和删除
+#[test_case]
+fn fat32_test() {
+    use crate::drivers::block::BlockCache;
+    use crate::fs::{Fat32FileSystem, FileSystem, FileType};
+    use crate::kernel::errno::{ENOENT, ENOTEMPTY};
+    use alloc::{sync::Arc, vec};
+
+    // 最小的 FAT32 卷：每簇 1 扇区，32 个保留扇区，1 个 8 扇区的 FAT，根目录在 2 号簇
+    const TOTAL_SECTORS: usize = 1024;
+    let mut blocks = vec![[0u8; BLOCK_SIZE]; TOTAL_SECTORS];
+    let boot = &mut blocks[0];
+    boot[11..13].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+    boot[13] = 1;
+    boot[14..16].copy_from_slice(&32u16.to_le_bytes());
+    boot[16] = 1;
+    boot[32..36].copy_from_slice(&(TOTAL_SECTORS as u32).to_le_bytes());
+    boot[36..40].copy_from_slice(&8u32.to_le_bytes());
+    boot[44..48].copy_from_slice(&2u32.to_le_bytes());
+    boot[510] = 0x55;
+    boot[511] = 0xaa;
+    blocks[32][..12].copy_from_slice(&[
+        0xf8, 0xff, 0xff, 0x0f, 0xff, 0xff, 0xff, 0x0f, 0xff, 0xff, 0xff, 0x0f,
+    ]);
+    let disk = Arc::new(RamDisk::new(blocks));
+
+    let fat = Fat32FileSystem::open(Arc::new(BlockCache::new(disk.clone(), 16))).unwrap();
+    let directory = fat
+        .root()
+        .create("Test Artifacts", FileType::Directory)
+        .unwrap();
+    let file = directory
+        .create("a rather long file name.log", FileType::Regular)
+        .unwrap();
+    // 跨越多个簇
+    let data: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+    file.write_at(0, &data).unwrap();
+    directory.create("SHORT.TXT", FileType::Regular).unwrap();
+    fat.sync().unwrap();
+
+    // 重新打开后内容不变，查找不区分大小写
+    let fat = Fat32FileSystem::open(Arc::new(BlockCache::new(disk, 16))).unwrap();
+    let directory = fat.root().lookup("test artifacts").unwrap();
+    let entry = directory.readdir(0).unwrap().unwrap();
+    assert_eq!(entry.name, "a rather long file name.log");
+    assert_eq!(directory.readdir(1).unwrap().unwrap().name, "SHORT.TXT");
+    assert!(directory.readdir(2).unwrap().is_none());
+    let file = directory.lookup("A Rather Long File Name.LOG").unwrap();
+    assert!(file.read_all().unwrap() == data);
+    file.resize(100).unwrap();
+    assert_eq!(file.metadata().unwrap().blocks, 1);
+    assert!(file.read_all().unwrap()[..] == data[..100]);
+
+    assert_eq!(fat.root().unlink("Test Artifacts").err(), Some(ENOTEMPTY));
+    directory.unlink("a rather long file name.log").unwrap();
+    directory.unlink("short.txt").unwrap();
+    assert_eq!(file.metadata().err(), Some(ENOENT));
+    fat.root().unlink("Test Artifacts").unwrap();
+    assert!(fat.root().readdir(0).unwrap().is_none());
+}
+
+/// 测试 tmpfs 的读写、帧的回收，以及 cpio 归档的解包
+#[test_case]
+fn tmpfs_test() {
+    use crate::fs::{initramfs, FileSystem, FileType, TmpFs};
+    use crate::kernel::errno::{EINVAL, ENOSPC, ENOTEMPTY};
+    use crate::memory::frame;
+    use alloc::format;
+
+    /// 按照 newc 格式追加一项
+    fn push_entry(archive: &mut Vec<u8>, name: &str, mode: u32, data: &[u8]) {
+        let fields = [0, mode, 0, 0, 1, 0, data.len() as u32, 0, 0, 0, 0, name.len() as u32 + 1, 0];
+        archive.extend_from_slice(b"070701");
+        for field in fields.iter() {
+            archive.extend_from_slice(format!("{:08X}", field).as_bytes());
+        }
+        archive.extend_from_slice(name.as_bytes());
+        archive.push(0);
+        archive.resize((archive.len() + 3) & !3, 0);
+        archive.extend_from_slice(data);
+        archive.resize((archive.len() + 3) & !3, 0);
+    }
+
+    let free = frame::stats().free;
+    let tmpfs = TmpFs::new();
+    let root = tmpfs.root();
+    let file = root.create("file", FileType::Regular).unwrap();
+    // 从第二页中间开始写，第一页读出为 0
+    let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+    file.write_at(5000, &data).unwrap();
+    assert_eq!(file.metadata().unwrap().size, 15_000);
+    assert_eq!(frame::stats().free, free - 4);
+    let content = file.read_all().unwrap();
+    assert!(content[..5000].iter().all(|&byte| byte == 0));
+    assert!(content[5000..] == data[..]);
+    // 缩小后再扩大，被截去的部分读出为 0
+    file.resize(6000).unwrap();
+    file.resize(8000).unwrap();
+    let content = file.read_all().unwrap();
+    assert!(content[5000..6000] == data[..1000]);
+    assert!(content[6000..].iter().all(|&byte| byte == 0));
+    // 超出空闲内存的写入失败，且不占用物理页
+    let before = frame::stats().free;
+    assert_eq!(file.write_at(1 << 40, b"x").err(), Some(ENOSPC));
+    assert_eq!(file.metadata().unwrap().size, 8000);
+    assert_eq!(frame::stats().free, before);
+    root.unlink("file").unwrap();
+    drop((file, content));
+    assert_eq!(frame::stats().free, free);
+
+    let mut archive = Vec::new();
+    push_entry(&mut archive, ".", 0o040755, b"");
+    push_entry(&mut archive, "./etc", 0o040755, b"");
+    push_entry(&mut archive, "./etc/motd", 0o100644, b"hello");
+    // 父目录没有单独列出
+    push_entry(&mut archive, "bin/sh", 0o100755, &data);
+    push_entry(&mut archive, "bin/link", 0o120777, b"sh");
+    push_entry(&mut archive, "TRAILER!!!", 0, b"");
+    assert_eq!(initramfs::unpack(&archive, &root), Ok(2));
+    let motd = root.lookup("etc").unwrap().lookup("motd").unwrap();
+    assert!(motd.read_all().unwrap() == b"hello");
+    let bin = root.lookup("bin").unwrap();
+    assert!(bin.lookup("sh").unwrap().read_all().unwrap() == data);
+    assert!(bin.readdir(1).unwrap().is_none());
+    assert_eq!(root.readdir(0).unwrap().unwrap().file_type, FileType::Directory);
+    assert_eq!(root.unlink("bin").err(), Some(ENOTEMPTY));
+
+    // 再次解包覆盖已有的文件，损坏的归档返回 EINVAL
+    let mut update = Vec::new();
+    push_entry(&mut update, "etc/motd", 0o100644, b"hi");
+    push_entry(&mut update, "TRAILER!!!", 0, b"");
+    assert_eq!(initramfs::unpack(&update, &root), Ok(1));
+    assert!(motd.read_all().unwrap() == b"hi");
+    assert_eq!(initramfs::unpack(&archive[..200], &root), Err(EINVAL));
+}
+
+/// 测试 devfs 中的设备文件和 procfs 中生成的内容
+#[test_case]
+fn devfs_procfs_test() {
+    use crate::fs::{DevFs, FileSystem, ProcFs};
+    use crate::kernel::errno::{EACCES, ENOENT, ENOSPC};
+    use alloc::string::String;
+
+    let dev = DevFs::new().root();
+    let mut buffer = [0xffu8; 64];
+    assert_eq!(dev.lookup("null").unwrap().read_at(0, &mut buffer), Ok(0));
+    assert_eq!(dev.lookup("null").unwrap().write_at(0, &buffer), Ok(64));
+    assert_eq!(dev.lookup("zero").unwrap().read_at(0, &mut buffer), Ok(64));
+    assert!(buffer.iter().all(|&byte| byte == 0));
+    dev.lookup("random").unwrap().read_at(0, &mut buffer).unwrap();
+    assert!(buffer.iter().any(|&byte| byte != 0));
+    assert_eq!(dev.lookup("tty").err(), Some(ENOENT));
+    assert_eq!(dev.unlink("null").err(), Some(EACCES));
+
+    // 在磁盘末尾跨越两个不完整的块读写，之后恢复原来的内容
+    let block0 = dev
+        .lookup("block0")
+        .expect("no block device, run the tests with `make test`");
+    let size = block0.metadata().unwrap().size;
+    let offset = size - 700;
+    let mut original = [0u8; 700];
+    assert_eq!(block0.read_at(offset, &mut original), Ok(700));
+    let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+    assert_eq!(block0.write_at(offset, &data), Ok(700));
+    let mut buffer = [0u8; 1000];
+    assert_eq!(block0.read_at(offset, &mut buffer), Ok(700));
+    assert!(buffer[..700] == data[..700]);
+    assert_eq!(block0.read_at(size, &mut buffer), Ok(0));
+    assert_eq!(block0.write_at(size, &data).err(), Some(ENOSPC));
+    assert_eq!(block0.write_at(offset, &original), Ok(700));
+    block0.sync().unwrap();
+
+    let procfs = ProcFs::new().root();
+    let read = |path: &[&str]| {
+        let mut inode = procfs.clone();
+        for name in path {
+            inode = inode.lookup(name).unwrap();
+        }
+        String::from_utf8(inode.read_all().unwrap()).unwrap()
+    };
+    assert!(read(&["meminfo"]).starts_with("MemTotal:"));
+    assert!(read(&["uptime"]).ends_with('\n'));
+    assert!(read(&["interrupts"]).contains("timer:"));
+    assert!(read(&["processes"]).lines().any(|line| line.trim_start().starts_with("0 ")));
+    // 测试在内核进程中运行，其地址空间中至少有内核的各个段
+    assert!(!read(&["self", "maps"]).is_empty());
+    assert!(read(&["0", "status"]).starts_with("Pid:\t0\n"));
+    assert_eq!(procfs.lookup("65535").err(), Some(ENOENT));
+    assert_eq!(
+        procfs.lookup("uptime").unwrap().write_at(0, b"0").err(),
+        Some(EACCES)
+    );
+}
+
+/// 测试按模块的日志过滤规则
+#[test_case]
+fn logger_filter_test() {
+    use crate::logger;
+    use log::{Level, Metadata};
+
+    let enabled = |target: &str, level: Level| {
+        log::logger().enabled(&Metadata::builder().target(target).level(level).build())
+    };
+    logger::set_filter("warn, memory=debug,os::memory::frame=error,process=bogus,trace=x");
+    assert!(enabled("os::kernel", Level::Warn));
+    assert!(!enabled("os::kernel", Level::Info));
+    // 规则对子模块生效，最长的规则优先
+    assert!(enabled("os::memory::heap", Level::Debug));
+    assert!(!enabled("os::memory::frame::cache", Level::Warn));
+    // 只是前缀相同的模块不匹配
+    assert!(!enabled("os::memoryx", Level::Info));
+    // 无法解析的级别被忽略
+    assert!(!enabled("os::process", Level::Info));
+    assert_eq!(log::max_level(), log::LevelFilter::Debug);
+    logger::set_filter(option_env!("LOG").unwrap_or("info"));
+}
+
+/// 测试挂载和路径解析，以及通过 [`fs::open`](crate::fs::open) 打开的文件
+#[test_case]
+fn vfs_test() {
+    use crate::fs::{self, File, FileSystem, FileType, OpenFlags, SeekFrom, TmpFs};
+    use crate::kernel::errno::{EBADF, EINVAL, EISDIR, ENOENT, ENOTDIR};
+
+    // 根文件系统可能在磁盘上，挂载点在上一次运行时可能已经创建
+    let (root, name) = fs::lookup_parent("/vfs_test").unwrap();
+    if let Err(ENOENT) = root.lookup(&name) {
+        root.create(&name, FileType::Directory).unwrap();
+    }
+    let tmpfs = TmpFs::new();
+    fs::mount("/vfs_test", tmpfs.clone()).unwrap();
+    assert!(fs::mounts().contains(&("/vfs_test".into(), "tmpfs")));
+
+    let key = |path: &str| {
+        let metadata = fs::lookup(path).unwrap().metadata().unwrap();
+        (metadata.dev, metadata.inode)
+    };
+    let mounted = tmpfs.root().metadata().unwrap();
+    assert_eq!(key("/vfs_test"), (mounted.dev, mounted.inode));
+    let dir = tmpfs.root().create("dir", FileType::Directory).unwrap();
+    dir.create("file", FileType::Regular).unwrap();
+    // `.`、`..` 和多余的 `/`，`..` 可以从被挂载的文件系统回到挂载点的上一级
+    assert_eq!(key("//vfs_test/./dir/../dir//file"), key("/vfs_test/dir/file"));
+    assert_eq!(key("/vfs_test/.."), key("/"));
+    assert_eq!(key("/../.."), key("/"));
+    assert_eq!(key("vfs_test/dir"), key("/vfs_test/dir"));
+    assert_eq!(fs::lookup("/vfs_test/missing").err(), Some(ENOENT));
+    assert_eq!(fs::lookup("/vfs_test/dir/file/x").err(), Some(ENOTDIR));
+    assert_eq!(fs::mount("/vfs_test/dir/file", TmpFs::new()).err(), Some(ENOTDIR));
+
+    let (parent, name) = fs::lookup_parent("/vfs_test/dir/file/").unwrap();
+    assert_eq!(name, "file");
+    assert_eq!(parent.metadata().unwrap().inode, dir.metadata().unwrap().inode);
+    assert_eq!(fs::lookup_parent("/vfs_test/..").err(), Some(EINVAL));
+    assert_eq!(fs::lookup_parent("/missing/file").err(), Some(ENOENT));
+
+    let file = fs::open("/vfs_test/dir/new", OpenFlags::RDWR | OpenFlags::CREATE).unwrap();
+    assert_eq!(file.write(b"0123456789"), Ok(10));
+    assert_eq!(file.seek(SeekFrom::End(-4)), Ok(6));
+    let mut buffer = [0u8; 8];
+    assert_eq!(file.read(&mut buffer), Ok(4));
+    assert!(buffer[..4] == b"6789"[..]);
+    assert_eq!(file.seek(SeekFrom::Current(-11)).err(), Some(EINVAL));
+    let readonly = fs::open("/vfs_test/dir/new", OpenFlags::empty()).unwrap();
+    assert_eq!(readonly.write(b"x").err(), Some(EBADF));
+    assert_eq!(fs::open("/vfs_test/dir", OpenFlags::WRONLY).err(), Some(EISDIR));
+    assert_eq!(
+        fs::open("/vfs_test/dir/new", OpenFlags::DIRECTORY).err(),
+        Some(ENOTDIR)
+    );
+    let truncated = fs::open("/vfs_test/dir/new", OpenFlags::WRONLY | OpenFlags::TRUNC).unwrap();
+    assert_eq!(truncated.metadata().unwrap().size, 0);
+}
+
+/// 运行用户程序 `fs_test`，测试文件相关的系统调用
+#[test_case]
+fn fs_syscall_test() {
+    use crate::interrupt::timer;
+    use crate::process::Process;
+    use alloc::sync::Weak;
+    use core::time::Duration;
+
+    let elf_data = loader::load("fs_test").expect("fs_test is not found");
+    let process = Process::from_elf(&elf_data, Weak::new()).unwrap();
+    let start = timer::now();
+    while process.exit_code().is_none() {
+        assert!(timer::now() - start < Duration::from_secs(10), "fs_test timed out");
+        timer::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(process.exit_code(), Some(0));
+}
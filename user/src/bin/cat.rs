@@ -0,0 +1,38 @@
+//! 依次输出参数中各个文件的内容，例如 `cat /proc/meminfo`
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::console::STDOUT;
+use user_lib::{close, open, read, write, O_RDONLY};
+
+#[no_mangle]
+pub fn main(_argc: usize, argv: &[&str]) -> i32 {
+    let mut buffer = [0u8; 1024];
+    let mut result = 0;
+    for path in argv.iter().skip(1) {
+        let fd = open(path, O_RDONLY);
+        if fd < 0 {
+            println!("cat: cannot open {} ({})", path, fd);
+            result = -1;
+            continue;
+        }
+        loop {
+            let size = read(fd as usize, &mut buffer);
+            if size < 0 {
+                println!("cat: failed to read {} ({})", path, size);
+                result = -1;
+                break;
+            }
+            if size == 0 {
+                break;
+            }
+            write(STDOUT, &buffer[..size as usize]);
+        }
+        close(fd as usize);
+    }
+    result
+}
@@ -0,0 +1,22 @@
+//! 输出内核日志
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::console::STDOUT;
+use user_lib::{read_kernel_log, write};
+
+#[no_mangle]
+pub fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut buffer = [0u8; 4096];
+    let size = read_kernel_log(&mut buffer);
+    if size < 0 {
+        println!("dmesg: failed to read kernel log ({})", size);
+        return -1;
+    }
+    write(STDOUT, &buffer[..size as usize]);
+    0
+}
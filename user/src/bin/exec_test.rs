@@ -0,0 +1,16 @@
+//! 使用参数 exec 另一个程序
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::exec;
+
+#[no_mangle]
+pub fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    exec("hello_world", &["hello_world", "from", "exec_test"]);
+    println!("exec_test: exec failed");
+    -1
+}
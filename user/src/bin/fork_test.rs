@@ -0,0 +1,36 @@
+//! 创建多个子进程，检查 waitpid 回收的 pid 和退出码
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, wait, ECHILD};
+
+/// 子进程个数
+const CHILDREN: usize = 8;
+
+#[no_mangle]
+pub fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut pids = [0isize; CHILDREN];
+    for i in 0..CHILDREN {
+        let pid = fork();
+        if pid == 0 {
+            // 子进程以序号作为退出码
+            println!("child {} running, pid = {}", i, getpid());
+            exit(i as i32);
+        }
+        assert!(pid > 0);
+        pids[i] = pid;
+    }
+    for _ in 0..CHILDREN {
+        let mut exit_code = 0;
+        let pid = wait(&mut exit_code);
+        let index = pids.iter().position(|&p| p == pid).expect("unknown child");
+        assert_eq!(exit_code, index as i32);
+    }
+    assert_eq!(wait(&mut 0), -ECHILD);
+    println!("fork_test passed");
+    0
+}
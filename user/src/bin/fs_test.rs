@@ -0,0 +1,73 @@
+//! 测试文件相关的系统调用：openat、read、write、lseek、dup、fstat 和 close
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+/// 测试使用的文件，每次运行时清空
+const PATH: &str = "/fs_test.tmp";
+
+#[no_mangle]
+pub fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let fd = open(PATH, O_RDWR | O_CREAT | O_TRUNC);
+    assert!(fd >= 0, "cannot create {}: {}", PATH, fd);
+    let fd = fd as usize;
+    assert_eq!(open(PATH, O_RDWR | O_CREAT | O_EXCL), -EEXIST);
+    assert_eq!(open("/no/such/file", O_RDONLY), -ENOENT);
+
+    assert_eq!(write(fd, b"hello, world"), 12);
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.size, 12);
+    assert_eq!(stat.mode & S_IFDIR, 0);
+
+    // dup 得到的文件描述符共享读写位置
+    let copy = dup(fd);
+    assert!(copy > fd as isize);
+    let copy = copy as usize;
+    assert_eq!(lseek(copy, 7, SEEK_SET), 7);
+    let mut buffer = [0u8; 16];
+    assert_eq!(read(fd, &mut buffer), 5);
+    assert_eq!(&buffer[..5], b"world");
+    assert_eq!(read(fd, &mut buffer), 0);
+    assert_eq!(lseek(fd, -5, SEEK_END), 7);
+    assert_eq!(lseek(fd, 2, SEEK_CUR), 9);
+    // 溢出或者为负的位置不改变原来的位置
+    assert_eq!(lseek(fd, isize::MAX, SEEK_CUR), -EINVAL);
+    assert_eq!(lseek(fd, isize::MAX, SEEK_END), -EINVAL);
+    assert_eq!(lseek(fd, -10, SEEK_CUR), -EINVAL);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 9);
+
+    // 关闭后原来的文件描述符无效，另一个仍然可用
+    assert_eq!(close(fd), 0);
+    assert_eq!(close(fd), -EBADF);
+    assert_eq!(read(fd, &mut buffer), -EBADF);
+    assert_eq!(lseek(copy, 0, SEEK_SET), 0);
+    assert_eq!(read(copy, &mut buffer), 12);
+    assert_eq!(close(copy), 0);
+
+    // 追加写入，重新打开后读出完整的内容
+    let fd = open(PATH, O_WRONLY | O_APPEND) as usize;
+    assert_eq!(write(fd, b"!"), 1);
+    close(fd);
+    // 内核不支持的标志被忽略
+    let fd = open(PATH, O_RDONLY | O_CLOEXEC | O_NONBLOCK);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(read(fd, &mut buffer), 13);
+    assert_eq!(&buffer[..13], b"hello, world!");
+    assert_eq!(write(fd, b"x"), -EBADF);
+    close(fd);
+
+    let fd = open("/", O_RDONLY | O_DIRECTORY);
+    assert!(fd >= 0);
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    assert_ne!(stat.mode & S_IFDIR, 0);
+    close(fd as usize);
+    println!("fs_test passed");
+    0
+}
@@ -0,0 +1,29 @@
+//! 初始进程：启动 shell，并不断回收被交给它的子进程
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, fork, wait, yield_, ECHILD};
+
+#[no_mangle]
+pub fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    if fork() == 0 {
+        exec("user_shell", &[]);
+        panic!("failed to start user_shell");
+    }
+    loop {
+        let mut exit_code = 0;
+        let pid = wait(&mut exit_code);
+        if pid == -ECHILD {
+            yield_();
+            continue;
+        }
+        println!(
+            "[initproc] released a zombie process, pid = {}, exit_code = {}",
+            pid, exit_code
+        );
+    }
+}
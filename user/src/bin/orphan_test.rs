@@ -0,0 +1,23 @@
+//! 父进程先于子进程退出，子进程应当被交给初始进程回收
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, yield_};
+
+#[no_mangle]
+pub fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    if fork() == 0 {
+        // 等待父进程退出
+        for _ in 0..16 {
+            yield_();
+        }
+        println!("orphan {} exiting, should be released by initproc", getpid());
+        exit(7);
+    }
+    println!("orphan_test parent exiting");
+    0
+}
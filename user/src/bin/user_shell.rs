@@ -0,0 +1,40 @@
+//! 一个简单的交互式 shell
+//!
+//! 输入程序名和参数后 fork 并 exec，等待其结束后打印退出码；输入 `exit` 退出
+
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::console::read_line;
+use user_lib::{exec, exit, fork, waitpid};
+
+#[no_mangle]
+pub fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    println!("rCore user shell");
+    loop {
+        print!(">> ");
+        let line = read_line();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first() {
+            None => continue,
+            Some(&"exit") => exit(0),
+            Some(&program) => {
+                let pid = fork();
+                if pid == 0 {
+                    // 子进程，exec 成功时不会返回
+                    let error = exec(program, &words);
+                    println!("{}: cannot execute ({})", program, error);
+                    exit(-1);
+                }
+                let mut exit_code = 0;
+                waitpid(pid as usize, &mut exit_code);
+                println!("[shell] process {} exited with code {}", pid, exit_code);
+            }
+        }
+    }
+}
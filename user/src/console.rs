@@ -0,0 +1,74 @@
+//! 用户程序的控制台输入输出
+//!
+//! 与内核中的 `console.rs` 相同，声明一个实现了 [`core::fmt::Write`] 的类型，
+//! 通过 `write` 系统调用输出
+
+use crate::syscall::{sys_read, sys_write};
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+/// 标准输入的文件描述符
+pub const STDIN: usize = 0;
+/// 标准输出的文件描述符
+pub const STDOUT: usize = 1;
+
+/// 一个 [Zero-Sized Type]，实现 [`core::fmt::Write`] trait 来进行格式化输出
+struct Stdout;
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        sys_write(STDOUT, s.as_bytes());
+        Ok(())
+    }
+}
+
+/// 打印由 [`core::format_args!`] 格式化后的数据
+pub fn print(args: fmt::Arguments) {
+    Stdout.write_fmt(args).unwrap();
+}
+
+/// 实现类似于标准库中的 `print!` 宏
+#[macro_export]
+macro_rules! print {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::print(format_args!($fmt $(, $($arg)+)?));
+    }
+}
+
+/// 实现类似于标准库中的 `println!` 宏
+#[macro_export]
+macro_rules! println {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::print(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
+    }
+}
+
+/// 从控制台读取一个字符（阻塞）
+pub fn getchar() -> u8 {
+    let mut c = [0u8; 1];
+    sys_read(STDIN, &mut c);
+    c[0]
+}
+
+/// 从控制台读取一行，回显输入的字符，支持退格
+pub fn read_line() -> String {
+    let mut line = String::new();
+    loop {
+        match getchar() {
+            b'\r' | b'\n' => {
+                println!("");
+                return line;
+            }
+            // 退格
+            0x08 | 0x7f => {
+                if line.pop().is_some() {
+                    print!("\x08 \x08");
+                }
+            }
+            c => {
+                print!("{}", c as char);
+                line.push(c as char);
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! 用户程序的运行时
+//!
+//! - 程序入口 `_start`：初始化堆，整理参数，调用用户定义的 `main`，并以其返回值退出
+//! - 用户堆，使得用户程序可以使用 `alloc`
+//! - 输出宏 [`print!`] 和 [`println!`]
+//! - 系统调用的封装
+
+#![no_std]
+#![feature(llvm_asm)]
+#![feature(linkage)]
+#![feature(panic_info_message)]
+#![feature(alloc_error_handler)]
+
+#[macro_use]
+pub mod console;
+pub mod syscall;
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use buddy_system_allocator::LockedHeap;
+use core::panic::PanicInfo;
+pub use syscall::*;
+
+/// 用户程序的堆大小（32K）
+const USER_HEAP_SIZE: usize = 0x8000;
+
+/// 用户程序的堆空间，放在 bss 段
+static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
+
+/// 用户程序的堆
+#[global_allocator]
+static HEAP: LockedHeap = LockedHeap::empty();
+
+/// 打印 panic 信息并以 -1 退出
+#[panic_handler]
+fn panic_handler(info: &PanicInfo) -> ! {
+    match (info.location(), info.message()) {
+        (Some(location), Some(message)) => println!(
+            "\x1b[1;31m{}:{}: '{}'\x1b[0m",
+            location.file(),
+            location.line(),
+            message
+        ),
+        (None, Some(message)) => println!("\x1b[1;31mpanic: '{}'\x1b[0m", message),
+        _ => println!("\x1b[1;31mpanic\x1b[0m"),
+    }
+    exit(-1)
+}
+
+/// 空间分配错误的回调，直接 panic 退出
+#[alloc_error_handler]
+fn alloc_error_handler(_: alloc::alloc::Layout) -> ! {
+    panic!("alloc error")
+}
+
+/// 程序入口，内核通过 `a0` 和 `a1` 传入参数个数和参数指针数组
+#[no_mangle]
+#[link_section = ".text.entry"]
+pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
+    unsafe {
+        HEAP.lock()
+            .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
+    }
+    let mut arguments: Vec<&'static str> = Vec::new();
+    for i in 0..argc {
+        let pointer = unsafe { *((argv + i * core::mem::size_of::<usize>()) as *const usize) };
+        let len = (0usize..)
+            .find(|&j| unsafe { *((pointer + j) as *const u8) } == 0)
+            .unwrap();
+        arguments.push(unsafe {
+            core::str::from_utf8_unchecked(core::slice::from_raw_parts(pointer as *const u8, len))
+        });
+    }
+    exit(main(argc, arguments.as_slice()))
+}
+
+/// 默认的 `main`，用户程序中定义的 `main` 会覆盖它
+#[linkage = "weak"]
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    panic!("no main() linked");
+}
+
+/// 写入文件描述符 `fd`
+pub fn write(fd: usize, buffer: &[u8]) -> isize {
+    sys_write(fd, buffer)
+}
+
+/// 从文件描述符 `fd` 读取
+pub fn read(fd: usize, buffer: &mut [u8]) -> isize {
+    sys_read(fd, buffer)
+}
+
+/// 按照 `flags`（`O_RDONLY` 等）打开文件，返回文件描述符
+pub fn open(path: &str, flags: u32) -> isize {
+    let path = terminate(path);
+    sys_openat(AT_FDCWD, path.as_ptr(), flags)
+}
+
+/// 关闭文件描述符
+pub fn close(fd: usize) -> isize {
+    sys_close(fd)
+}
+
+/// 移动读写位置，`whence` 为 `SEEK_SET` 等，返回新的位置
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+
+/// 复制文件描述符，返回编号最小的空闲文件描述符
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+
+/// 读取文件的信息
+pub fn fstat(fd: usize, stat: &mut Stat) -> isize {
+    sys_fstat(fd, stat)
+}
+
+/// 以 `exit_code` 结束进程
+pub fn exit(exit_code: i32) -> ! {
+    sys_exit(exit_code)
+}
+
+/// 让出处理器
+pub fn yield_() -> isize {
+    sys_yield()
+}
+
+/// 当前进程的 pid
+pub fn getpid() -> isize {
+    sys_getpid()
+}
+
+/// 复制当前进程，父进程返回子进程的 pid，子进程返回 0
+pub fn fork() -> isize {
+    sys_fork()
+}
+
+/// 执行程序 `path`，成功时不会返回
+pub fn exec(path: &str, arguments: &[&str]) -> isize {
+    // 系统调用使用以 '\0' 结尾的字符串
+    let path = terminate(path);
+    let arguments: Vec<Vec<u8>> = arguments.iter().map(|argument| terminate(argument)).collect();
+    let mut pointers: Vec<*const u8> = arguments.iter().map(|argument| argument.as_ptr()).collect();
+    pointers.push(core::ptr::null());
+    sys_exec(path.as_ptr(), pointers.as_ptr())
+}
+
+/// 等待任意一个子进程退出，返回其 pid，退出码写入 `exit_code`
+pub fn wait(exit_code: &mut i32) -> isize {
+    sys_waitpid(-1, exit_code as *mut i32)
+}
+
+/// 等待子进程 `pid` 退出，退出码写入 `exit_code`
+pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
+    sys_waitpid(pid as isize, exit_code as *mut i32)
+}
+
+/// 读取内核日志中最新的至多 `buffer.len()` 字节，返回读取的字节数
+pub fn read_kernel_log(buffer: &mut [u8]) -> isize {
+    const SYSLOG_ACTION_READ_ALL: usize = 3;
+    sys_syslog(SYSLOG_ACTION_READ_ALL, buffer)
+}
+
+/// 在字符串末尾加上 '\0'
+fn terminate(string: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(string.len() + 1);
+    bytes.extend_from_slice(string.as_bytes());
+    bytes.push(0);
+    bytes
+}
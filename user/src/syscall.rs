@@ -0,0 +1,160 @@
+//! 系统调用
+//!
+//! 系统调用号在 `a7` 中，参数依次在 `a0` 至 `a2` 中，返回值在 `a0` 中。
+//! 出错时返回错误码的相反数
+
+pub const SYS_DUP: usize = 23;
+pub const SYS_OPENAT: usize = 56;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_LSEEK: usize = 62;
+pub const SYS_READ: usize = 63;
+pub const SYS_WRITE: usize = 64;
+pub const SYS_FSTAT: usize = 80;
+pub const SYS_EXIT: usize = 93;
+pub const SYS_SYSLOG: usize = 116;
+pub const SYS_YIELD: usize = 124;
+pub const SYS_GETPID: usize = 172;
+pub const SYS_FORK: usize = 220;
+pub const SYS_EXEC: usize = 221;
+pub const SYS_WAITPID: usize = 260;
+
+/// 文件不存在
+pub const ENOENT: isize = 2;
+/// 文件描述符无效
+pub const EBADF: isize = 9;
+/// 没有可以等待的子进程
+pub const ECHILD: isize = 10;
+/// 文件已经存在
+pub const EEXIST: isize = 17;
+/// 参数无效
+pub const EINVAL: isize = 22;
+
+/// `openat` 中表示从当前工作目录开始解析
+pub const AT_FDCWD: isize = -100;
+
+/// 打开文件的方式
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1;
+pub const O_RDWR: u32 = 2;
+pub const O_CREAT: u32 = 0o100;
+pub const O_EXCL: u32 = 0o200;
+pub const O_TRUNC: u32 = 0o1000;
+pub const O_APPEND: u32 = 0o2000;
+pub const O_DIRECTORY: u32 = 0o200000;
+pub const O_NONBLOCK: u32 = 0o4000;
+pub const O_CLOEXEC: u32 = 0o2000000;
+
+/// `lseek` 的 `whence`
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+/// `st_mode` 中表示目录的位
+pub const S_IFDIR: u32 = 0o040000;
+
+/// 文件的信息，与 Linux（riscv64）的 `struct stat` 布局相同
+#[repr(C)]
+#[derive(Default, Debug)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    _pad0: u64,
+    pub size: i64,
+    pub blksize: u32,
+    _pad1: u32,
+    pub blocks: u64,
+    pub atime: [i64; 2],
+    pub mtime: [i64; 2],
+    pub ctime: [i64; 2],
+    _unused: [u32; 2],
+}
+
+/// 将参数放在对应寄存器中，并执行 `ecall`
+fn syscall(id: usize, arg0: usize, arg1: usize, arg2: usize) -> isize {
+    let ret: isize;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (ret)
+            : "{x10}" (arg0), "{x11}" (arg1), "{x12}" (arg2), "{x17}" (id)
+            : "memory"
+            : "volatile"
+        );
+    }
+    ret
+}
+
+/// 读取文件，返回读取的字节数
+pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(SYS_READ, fd, buffer.as_mut_ptr() as usize, buffer.len())
+}
+
+/// 写入文件，返回写入的字节数
+pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
+    syscall(SYS_WRITE, fd, buffer.as_ptr() as usize, buffer.len())
+}
+
+/// 结束进程
+pub fn sys_exit(exit_code: i32) -> ! {
+    syscall(SYS_EXIT, exit_code as usize, 0, 0);
+    unreachable!()
+}
+
+/// 让出处理器
+pub fn sys_yield() -> isize {
+    syscall(SYS_YIELD, 0, 0, 0)
+}
+
+/// 当前进程的 pid
+pub fn sys_getpid() -> isize {
+    syscall(SYS_GETPID, 0, 0, 0)
+}
+
+/// 复制当前进程
+pub fn sys_fork() -> isize {
+    syscall(SYS_FORK, 0, 0, 0)
+}
+
+/// 执行程序，`path` 和每个参数均以 '\0' 结尾，`argv` 以空指针结尾
+pub fn sys_exec(path: *const u8, argv: *const *const u8) -> isize {
+    syscall(SYS_EXEC, path as usize, argv as usize, 0)
+}
+
+/// 等待子进程退出，`pid` 为 -1 时等待任意子进程
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
+    syscall(SYS_WAITPID, pid as usize, exit_code as usize, 0)
+}
+
+/// 读取内核日志，`action` 与 Linux 的 `syslog` 相同
+pub fn sys_syslog(action: usize, buffer: &mut [u8]) -> isize {
+    syscall(SYS_SYSLOG, action, buffer.as_mut_ptr() as usize, buffer.len())
+}
+
+/// 打开文件，`path` 以 '\0' 结尾，返回文件描述符
+pub fn sys_openat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    syscall(SYS_OPENAT, dirfd as usize, path as usize, flags as usize)
+}
+
+/// 关闭文件描述符
+pub fn sys_close(fd: usize) -> isize {
+    syscall(SYS_CLOSE, fd, 0, 0)
+}
+
+/// 移动读写位置，返回新的位置
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    syscall(SYS_LSEEK, fd, offset as usize, whence)
+}
+
+/// 复制文件描述符
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYS_DUP, fd, 0, 0)
+}
+
+/// 读取文件的信息
+pub fn sys_fstat(fd: usize, stat: &mut Stat) -> isize {
+    syscall(SYS_FSTAT, fd, stat as *mut Stat as usize, 0)
+}